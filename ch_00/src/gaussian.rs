@@ -1,45 +1,124 @@
+use clap::Parser;
 use nannou::prelude::*;
-use rand;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::seed::{self, SeedArgs};
+use rand::rngs::StdRng;
 use rand_distr::{Distribution, Normal};
 
+const WIDTH: u32 = 400;
+const HEIGHT: u32 = 400;
+
+/// Dots splattered with a normal distribution around the centre.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, nth| {
+                step(&mut model);
+                render(&model, canvas, nth);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
     x: f32,
     y: f32,
+    rng: StdRng,
+    playback: Playback,
+    recorder: Recorder,
 }
 
-fn get_random_normal() -> f32 {
+impl Model {
+    fn new(args: &Args) -> Self {
+        let mut rng = seed::rng(seed::resolve(args.seed.seed));
+        let x = get_random_normal(&mut rng);
+        let y = get_random_normal(&mut rng);
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            x,
+            y,
+            rng,
+            playback,
+            recorder,
+        }
+    }
+}
+
+fn get_random_normal(rng: &mut StdRng) -> f32 {
     let dist = Normal::new(0.0, 1.0).unwrap();
-    let v = dist.sample(&mut rand::thread_rng()) as f32;
+    let v = dist.sample(rng) as f32;
     map_range(v, -5.0, 5.0, -200.0, 200.0)
 }
 
 fn model(app: &App) -> Model {
-    let _window = app.new_window().size(400, 400).view(view).build().unwrap();
-    let x = get_random_normal();
-    let y = get_random_normal();
-    Model { x, y }
+    let _window = app
+        .new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    model.x = get_random_normal();
-    model.y = get_random_normal();
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn step(model: &mut Model) {
+    model.x = get_random_normal(&mut model.rng);
+    model.y = get_random_normal(&mut model.rng);
+}
+
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    model.playback.show_in_title(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    if frame.nth() == 0 {
-        draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R, nth: u64) {
+    if nth == 0 {
+        renderer.background(WHITE);
     }
 
-    draw.ellipse()
-        .radius(2.0)
-        .x_y(model.x, model.y)
-        .rgba(0.5, 0.5, 0.5, 0.2);
+    renderer.ellipse(
+        pt2(model.x, model.y),
+        4.0,
+        4.0,
+        Style::fill(rgba(0.5, 0.5, 0.5, 0.2)),
+    );
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw, frame.nth());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
@@ -1,16 +1,136 @@
+// nannou re-exports both of noise's `Perlin` generators through a glob
+#![allow(ambiguous_glob_imports)]
+
+use clap::Parser;
 use nannou::{
     noise::{NoiseFn, Perlin},
     prelude::*,
+    winit::event::WindowEvent,
 };
+use nannou_egui::egui;
+use noc::panel::{Controls, Panel};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::Renderer;
+use noc::scene::{check_finite, Scene, SceneArgs, SceneError, Validate};
+use noc::seed::{self, SeedArgs};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+const STEP: f32 = 0.005;
+
+/// A fan of jittered Perlin noise curves.
+#[derive(Parser)]
+struct Args {
+    /// How far the curves move through the noise each frame.
+    #[arg(long, default_value_t = STEP)]
+    step: f32,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+/// The sketch's part of a scene file.
+#[derive(Clone, Serialize, Deserialize)]
+struct Setup {
+    step: f32,
+    /// Whether the curves are shaken sideways every frame.
+    jitter: bool,
+}
+
+impl Controls for Setup {
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::Slider::new(&mut self.step, 0.0..=0.1)
+                .logarithmic(true)
+                .text("step"),
+        );
+        ui.checkbox(&mut self.jitter, "jitter");
+    }
+}
+
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        check_finite("step", &[self.step])
+    }
+}
+
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        let setup = Setup {
+            step: args.step,
+            jitter: true,
+        };
+        Scene::new(WIDTH, HEIGHT, setup)
+    })
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
     t: f32,
     points: Vec<Point2>,
     noise: Perlin,
+    rng: StdRng,
+    /// Seeds this frame's jitter, so drawing doesn't need a mutable model.
+    jitter_seed: u64,
+    setup: Setup,
+    panel: Panel<Setup>,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let t: f32 = 0.015;
+        let noise: Perlin = Perlin::new();
+        let points: Vec<Vec2> = generate_points(noise, t);
+        let mut rng = seed::rng(seed::resolve(args.seed.seed.or(scene.seed)));
+        let jitter_seed = rng.gen();
+        let panel = Panel::new(scene.clone(), args.scene.save_path("perlin.toml"));
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            t,
+            points,
+            noise,
+            rng,
+            jitter_seed,
+            setup: scene.sketch.clone(),
+            panel,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn generate_points(noise: Perlin, t: f32) -> Vec<Point2> {
@@ -18,9 +138,9 @@ fn generate_points(noise: Perlin, t: f32) -> Vec<Point2> {
     let x_max: i32 = 400;
     let points: Vec<Vec2> = (x_min..x_max)
         .map(|i: i32| {
-            let x: f32 = map_range(i as f32, x_min as f32, x_max as f32, -400.0, 400.0) as f32;
+            let x: f32 = map_range(i as f32, x_min as f32, x_max as f32, -400.0, 400.0);
             let scaled_i: f32 = i as f32 * 0.001;
-            let y: f32 = noise.get([(t + scaled_i as f32) as f64, 0.0]) as f32;
+            let y: f32 = noise.get([(t + scaled_i) as f64, 0.0]) as f32;
             let mapped_y: f32 = y * 200.0;
             Point2::new(x, mapped_y)
         })
@@ -29,50 +149,79 @@ fn generate_points(noise: Perlin, t: f32) -> Vec<Point2> {
 }
 
 fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-    let t: f32 = 0.015;
-    let noise: Perlin = Perlin::new();
-    let points: Vec<Vec2> = generate_points(noise.clone(), t);
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let window: WindowId = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .raw_event(raw_window_event)
+        .build()
+        .unwrap();
+    let mut model = Model::new(&args, &scene);
+    model.panel.attach(&app.window(window).unwrap());
+    model
+}
 
-    let _window: WindowId = app.new_window().size(w, h).view(view).build().unwrap();
-    Model { t, points, noise }
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.panel.wants_keyboard() {
+        return;
+    }
+    model.panel.key_pressed(key);
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    model.t += 0.005;
-    model.points = generate_points(model.noise.clone(), model.t);
+fn raw_window_event(_app: &App, model: &mut Model, event: &WindowEvent) {
+    model.panel.raw_event(event);
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw: Draw = app.draw();
+fn step(model: &mut Model) {
+    model.t += model.setup.step;
+    model.points = generate_points(model.noise, model.t);
+    model.jitter_seed = model.rng.gen();
+}
 
-    draw.background().color(BLACK);
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.panel.update(&update, &mut model.setup);
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
+
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(BLACK);
+    let mut rng = seed::rng(model.jitter_seed);
+    let jitter = if model.setup.jitter { 100 } else { 1 };
 
     (0..20).for_each(|i| {
-        draw.polyline()
-            .weight(1.0)
-            .points(
-            model
-                .points
-                .clone()
-                .into_iter()
-                .map(|p| [p.x + random_range(0, 100) as f32, -p.y]),
-)
-            .color(RED)
-            .rotate(map_range(i as f32, 0.0, 20.0, 0.0, 2.0 * PI));
+        let rotation = map_range(i as f32, 0.0, 20.0, 0.0, 2.0 * PI);
+        let points: Vec<Point2> = model
+            .points
+            .iter()
+            .map(|p| pt2(p.x + rng.gen_range(0..jitter) as f32, -p.y).rotate(rotation))
+            .collect();
+        renderer.polyline(&points, 1.0, RED);
     });
 
-    draw.polyline()
-        .weight(1.0)
-        .points(
-            model
-                .points
-                .clone()
-                .into_iter()
-                .map(|p| [p.x + random_range(0, 100) as f32, p.y]),
-        )
-        .color(BLUE);
+    let points: Vec<Point2> = model
+        .points
+        .iter()
+        .map(|p| pt2(p.x + rng.gen_range(0..jitter) as f32, p.y))
+        .collect();
+    renderer.polyline(&points, 1.0, BLUE);
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
+    model.panel.draw_to_frame(&frame);
+}
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
 }
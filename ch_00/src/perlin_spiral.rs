@@ -1,16 +1,81 @@
+// nannou re-exports both of noise's `Perlin` generators through a glob
+#![allow(ambiguous_glob_imports)]
+
+use clap::Parser;
 use nannou::{
     noise::{NoiseFn, Perlin},
     prelude::*,
 };
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::Renderer;
+use noc::seed::{self, SeedArgs};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+/// Red and blue spirals of jittered Perlin noise curves.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
     t: f32,
     points: Vec<Point2>,
     noise: Perlin,
+    rng: StdRng,
+    /// Seeds this frame's jitter, so drawing doesn't need a mutable model.
+    jitter_seed: u64,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args) -> Self {
+        let t: f32 = 0.015;
+        let noise: Perlin = Perlin::new();
+        let points: Vec<Vec2> = generate_points(noise, t);
+        let mut rng = seed::rng(seed::resolve(args.seed.seed));
+        let jitter_seed = rng.gen();
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            t,
+            points,
+            noise,
+            rng,
+            jitter_seed,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn generate_points(noise: Perlin, t: f32) -> Vec<Point2> {
@@ -18,9 +83,9 @@ fn generate_points(noise: Perlin, t: f32) -> Vec<Point2> {
     let x_max: i32 = 400;
     let points: Vec<Vec2> = (x_min..x_max)
         .map(|i: i32| {
-            let x: f32 = map_range(i as f32, x_min as f32, x_max as f32, -400.0, 400.0) as f32;
+            let x: f32 = map_range(i as f32, x_min as f32, x_max as f32, -400.0, 400.0);
             let scaled_i: f32 = i as f32 * 0.001;
-            let y: f32 = noise.get([(t + scaled_i as f32) as f64, 0.0]) as f32;
+            let y: f32 = noise.get([(t + scaled_i) as f64, 0.0]) as f32;
             let mapped_y: f32 = y * 200.0;
             Point2::new(x, mapped_y)
         })
@@ -29,52 +94,66 @@ fn generate_points(noise: Perlin, t: f32) -> Vec<Point2> {
 }
 
 fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-    let t: f32 = 0.015;
-    let noise: Perlin = Perlin::new();
-    let points: Vec<Vec2> = generate_points(noise.clone(), t);
-
-    let _window: WindowId = app.new_window().size(w, h).view(view).build().unwrap();
-    Model { t, points, noise }
+    let _window: WindowId = app
+        .new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn step(model: &mut Model) {
     model.t += 0.005;
-    model.points = generate_points(model.noise.clone(), model.t);
+    model.points = generate_points(model.noise, model.t);
+    model.jitter_seed = model.rng.gen();
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw: Draw = app.draw();
-
-    draw.background().color(BLACK);
-
-    (0..20).for_each(|i: i32| {
-        draw.polyline()
-            .weight(1.0)
-            .points(
-                model
-                    .points
-                    .clone()
-                    .into_iter()
-                    .map(|p| [p.x + random_range(0, 100) as f32, -p.y]),
-            )
-            .color(RED)
-            .rotate(map_range(i as f32, 0.0, 20.0, 0.0, 2.0 * PI));
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
+
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(BLACK);
+    let mut rng = seed::rng(model.jitter_seed);
+
+    (0..20).for_each(|i| {
+        let rotation = map_range(i as f32, 0.0, 20.0, 0.0, 2.0 * PI);
+        let points: Vec<Point2> = model
+            .points
+            .iter()
+            .map(|p| pt2(p.x + rng.gen_range(0..100) as f32, -p.y).rotate(rotation))
+            .collect();
+        renderer.polyline(&points, 1.0, RED);
     });
 
-    (0..20).for_each(|i: i32| {
-        draw.polyline()
-            .weight(1.0)
-            .points(
-                model
-                    .points
-                    .clone()
-                    .into_iter()
-                    .map(|p| [p.x + random_range(0, 100) as f32, p.y]),
-            )
-            .color(BLUE)
-            .rotate(map_range(i as f32, 0.0, 20.0, PI / 2.0, 5.0 * PI / 2.0));
+    (0..20).for_each(|i| {
+        let rotation = map_range(i as f32, 0.0, 20.0, PI / 2.0, 5.0 * PI / 2.0);
+        let points: Vec<Point2> = model
+            .points
+            .iter()
+            .map(|p| pt2(p.x + rng.gen_range(0..100) as f32, p.y).rotate(rotation))
+            .collect();
+        renderer.polyline(&points, 1.0, BLUE);
     });
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
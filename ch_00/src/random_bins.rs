@@ -1,41 +1,120 @@
+use clap::Parser;
 use nannou::prelude::*;
-use std::collections::HashMap;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::seed::{self, SeedArgs};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+const WIDTH: u32 = 400;
+const HEIGHT: u32 = 400;
+
+/// A histogram of uniformly random integers.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
-  bins: HashMap<i32, i32>,
+    bins: BTreeMap<i32, i32>,
+    rng: StdRng,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args) -> Self {
+        let bins = BTreeMap::new();
+        let rng = seed::rng(seed::resolve(args.seed.seed));
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            bins,
+            rng,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn model(app: &App) -> Model {
-    let _window = app.new_window().size(400,400).view(view).build().unwrap();
-    let bins = HashMap::new();
-    Model { bins }
+    let _window = app
+        .new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    let random_int = random_range(0, 10);
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
+}
+
+fn step(model: &mut Model) {
+    let random_int = model.rng.gen_range(0..10);
     let count = model.bins.entry(random_int).or_insert(0);
     *count += 1;
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
     // draw a rectangle for each bin
     for (i, count) in model.bins.iter() {
         let height = *count as f32 * 0.1;
-        draw.rect()
-            .w_h(40.0,height) 
-            .x_y(-200.0 + (*i as f32) * 40.0 + 20.0, 0.0 + height / 2.0)
-            .rgba(0.5, 0.5, 0.5, 1.0)
-            .stroke_weight(2.0)
-            .stroke(BLACK);
+        renderer.rect(
+            pt2(-200.0 + (*i as f32) * 40.0 + 20.0, 0.0 + height / 2.0),
+            40.0,
+            height,
+            0.0,
+            Style::fill(rgba(0.5, 0.5, 0.5, 1.0)).with_stroke(BLACK, 2.0),
+        );
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
@@ -1,41 +1,108 @@
-use nannou::{noise::{self, NoiseFn}, prelude::*};
+// nannou re-exports both of noise's `Perlin` generators through a glob
+#![allow(ambiguous_glob_imports)]
+
+use clap::Parser;
+use nannou::{
+    noise::{self, NoiseFn},
+    prelude::*,
+};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::seed::{self, SeedArgs};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+/// A random walker that drifts towards the mouse.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, nth| {
+                step(&mut model, pt2(0.0, 0.0), nth as f32 / 60.0);
+                render(&model, canvas, nth);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
     x: f32,
     y: f32,
+    rng: StdRng,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args) -> Self {
+        let x: f32 = 0.0;
+        let y: f32 = 0.0;
+        let rng = seed::rng(seed::resolve(args.seed.seed));
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            x,
+            y,
+            rng,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-    let x: f32 = 0.0;
-    let y: f32 = 0.0;
+    let _window = app
+        .new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
+}
 
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    Model { x, y }
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn update(app: &App, model: &mut Model, _update: Update) {
-    let x_rand = random_range(-1, 2);
-    let y_rand = random_range(-1, 2);
+fn step(model: &mut Model, mouse: Point2, time: f32) {
+    let x_rand = model.rng.gen_range(-1..2);
+    let y_rand = model.rng.gen_range(-1..2);
 
     let perlin = noise::Perlin::new();
-    let value = perlin.get([model.x as f64, model.y as f64, app.time as f64]);
-
+    let value = perlin.get([model.x as f64, model.y as f64, time as f64]);
 
-    if random_f32() < 0.1 {
-    // if false {
-        if app.mouse.position().x > model.x {
+    if model.rng.gen::<f32>() < 0.1 {
+        // if false {
+        if mouse.x > model.x {
             model.x += 1.0;
         } else {
             model.x -= 1.0;
         }
 
-        if app.mouse.position().y > model.y {
+        if mouse.y > model.y {
             model.y += 1.0;
         } else {
             model.y -= 1.0;
@@ -46,18 +113,33 @@ fn update(app: &App, model: &mut Model, _update: Update) {
     }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    model.playback.show_in_title(app);
+    for _ in 0..model.playback.ticks() {
+        step(model, app.mouse.position(), app.time);
+    }
+}
 
-    if frame.nth() == 0 {
-        draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R, nth: u64) {
+    if nth == 0 {
+        renderer.background(WHITE);
     }
 
-    draw.ellipse()
-        .x_y(model.x, model.y)
-        .w_h(5.0, 5.0)
-        .rgba(0.5, 0.5, 0.5, 1.0)
-        .stroke(BLACK);
+    renderer.ellipse(
+        pt2(model.x, model.y),
+        5.0,
+        5.0,
+        Style::fill(rgba(0.5, 0.5, 0.5, 1.0)).with_stroke(BLACK, 1.0),
+    );
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw, frame.nth());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
@@ -1,110 +1,104 @@
+use clap::Parser;
 use nannou::prelude::*;
-
-fn main() {
-    nannou::app(model).update(update).run();
+use noc::mover::Mover;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+/// A mover steered with the arrow keys.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    record: RecordArgs,
 }
 
-struct Mover {
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    top_speed: f32,
-}
-
-impl Mover {
-    fn new() -> Self {
-        let position = pt2(0.0, 0.0);
-        let velocity = vec2(0.0, 0.0);
-        let acceleration = vec2(0.0, 0.0);
-        let top_speed = 5.0;
-        Mover {
-            position,
-            velocity,
-            acceleration,
-            top_speed,
-        }
-    }
-
-    fn update(&mut self) {
-        if (self.velocity + self.acceleration).length() <= self.top_speed {
-            self.velocity += self.acceleration;
-        };
-        self.position += self.velocity;
-    }
-
-    fn check_edges(&mut self, app: &App) {
-        if self.position.x > app.window_rect().right() {
-            self.position.x = app.window_rect().left();
-        } else if self.position.x < app.window_rect().left() {
-            self.position.x = app.window_rect().right();
-        }
-
-        if self.position.y > app.window_rect().top() {
-            self.position.y = app.window_rect().bottom();
-        } else if self.position.y < app.window_rect().bottom() {
-            self.position.y = app.window_rect().top();
-        }
-    }
-
-    fn accelerate(&mut self, jerk: Vec2) {
-        self.acceleration += jerk;
+fn main() {
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        let bounds = Rect::from_w_h(WIDTH as f32, HEIGHT as f32);
+        recorder::run_headless(&args.record, WIDTH, HEIGHT, frames, |canvas: &mut Canvas, _| {
+            step(&mut model, bounds);
+            render(&model, canvas);
+        });
+        return;
     }
+    nannou::app(model).update(update).exit(exit).run();
+}
 
-    fn display(&self, draw: &Draw) {
-        draw.ellipse()
-            .xy(self.position)
-            .w_h(16.0, 16.0)
-            .rgba(0.5, 0.5, 0.5, 1.0)
-            .stroke(BLACK);
-
-        draw.arrow()
-            .start(self.position)
-            .end(self.position + (self.velocity * 100.0))
-            .weight(2.0)
-            .color(BLACK);
-
-        draw.text(&format!(
+fn display<R: Renderer>(mover: &Mover, renderer: &mut R) {
+    renderer.ellipse(
+        mover.position,
+        16.0,
+        16.0,
+        Style::fill(rgba(0.5, 0.5, 0.5, 1.0)).with_stroke(BLACK, 1.0),
+    );
+
+    renderer.arrow(
+        mover.position,
+        mover.position + (mover.velocity * 100.0),
+        2.0,
+        BLACK,
+    );
+
+    renderer.text(
+        &format!(
             "Current velocity: {x:.3},{y:.3}",
-            x = self.velocity.x,
-            y = self.velocity.y
-        ))
-        .xy(pt2(-250.0, 380.0))
-        .w(800.0)
-        .font_size(20)
-        .color(BLACK);
-
-        draw.text(&format!(
+            x = mover.velocity.x,
+            y = mover.velocity.y
+        ),
+        pt2(-250.0, 380.0),
+        20,
+        BLACK,
+    );
+
+    renderer.text(
+        &format!(
             "Current acceleration:  {x:.3},{y:.3}",
-            x = self.acceleration.x,
-            y = self.acceleration.y
-        ))
-        .xy(pt2(-230.0, 340.0))
-        .w(800.0)
-        .font_size(20)
-        .color(BLACK);
-    }
+            x = mover.acceleration.x,
+            y = mover.acceleration.y
+        ),
+        pt2(-230.0, 340.0),
+        20,
+        BLACK,
+    );
 }
 
 struct Model {
     mover: Mover,
+    playback: Playback,
+    recorder: Recorder,
 }
 
-fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
+impl Model {
+    fn new(args: &Args) -> Self {
+        let mover = Mover::new(pt2(0.0, 0.0), 5.0);
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            mover,
+            playback,
+            recorder,
+        }
+    }
+}
 
+fn model(app: &App) -> Model {
     let _window = app
         .new_window()
-        .size(w, h)
+        .size(WIDTH, HEIGHT)
         .view(view)
         .key_pressed(key_pressed)
         .build()
         .unwrap();
-    let mover = Mover::new();
-    Model { mover }
+    Model::new(&Args::parse())
 }
 
-fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
     match key {
         Key::Up => {
             let jerk = vec2(0.0, 0.001);
@@ -122,21 +116,38 @@ fn key_pressed(_app: &App, model: &mut Model, key: Key) {
             let jerk = vec2(-0.001, 0.0);
             model.mover.accelerate(jerk);
         }
-        _ => {}
+        _ => {
+            model.playback.key_pressed(key);
+            model.recorder.key_pressed(app, key);
+        }
     };
 }
 
-fn update(app: &App, model: &mut Model, _update: Update) {
+fn step(model: &mut Model, bounds: Rect) {
     model.mover.update();
-    model.mover.check_edges(app);
+    model.mover.check_edges(bounds);
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model, app.window_rect());
+    }
+}
 
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
-    model.mover.display(&draw);
+    display(&model.mover, renderer);
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
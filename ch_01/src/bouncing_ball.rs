@@ -1,8 +1,186 @@
+use clap::Parser;
 use nannou::prelude::*;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::scene::{check_finite, check_positive, Scene, SceneArgs, SceneError, Validate};
+use noc::seed::{self, SeedArgs};
+use rand::Rng;
 use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+/// Balls bouncing off a spinning fan inside a box.
+#[derive(Parser)]
+struct Args {
+    /// Number of balls dropped into the box.
+    #[arg(long, default_value_t = BALL_COUNT)]
+    balls: usize,
+
+    /// Radius of each ball, in physics units.
+    #[arg(long, default_value_t = BALL_RADIUS)]
+    ball_radius: f32,
+
+    /// Restitution of the balls; 1 is perfectly elastic.
+    #[arg(long, default_value_t = BALL_COEFFICIENT_OF_RESTITUTION)]
+    ball_restitution: f32,
+
+    /// Height the balls are dropped from.
+    #[arg(long, default_value_t = INITIAL_ALTITUDE)]
+    initial_altitude: f32,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+/// A fixed box collider.
+#[derive(Serialize, Deserialize)]
+struct Wall {
+    center: [f32; 2],
+    half_extents: [f32; 2],
+    restitution: f32,
+}
+
+/// A thin box attached to the fan's hub, rotated about it.
+#[derive(Serialize, Deserialize)]
+struct Blade {
+    length: f32,
+    thickness: f32,
+    rotation: f32,
+    restitution: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Fan {
+    /// Starting spin in radians per second; the fan is undamped.
+    angular_velocity: f32,
+    blades: Vec<Blade>,
+}
+
+/// Balls dropped from just above the fan.
+#[derive(Serialize, Deserialize)]
+struct Balls {
+    count: usize,
+    radius: f32,
+    restitution: f32,
+    altitude: f32,
+}
+
+/// The sketch's part of a scene file.
+#[derive(Serialize, Deserialize)]
+struct Setup {
+    walls: Vec<Wall>,
+    fan: Fan,
+    balls: Balls,
+}
+
+impl Setup {
+    fn from_args(args: &Args) -> Self {
+        let wall = |center: [f32; 2], half_extents: [f32; 2]| Wall {
+            center,
+            half_extents,
+            restitution: LEFT_BLADE_COEFFICIENT_OF_RESTITUTION,
+        };
+        Setup {
+            walls: vec![
+                wall([-40.0, 0.0], [1.0, 39.0]),
+                wall([40.0, 0.0], [1.0, 39.0]),
+                wall([0.0, 40.0], [39.0, 1.0]),
+                wall([0.0, -40.0], [39.0, 1.0]),
+            ],
+            fan: Fan {
+                angular_velocity: PI / 2.0,
+                blades: vec![
+                    Blade {
+                        length: LEFT_BLADE_LENGTH,
+                        thickness: LEFT_BLADE_THICKNESS,
+                        rotation: LEFT_BLADE_ROTATION,
+                        restitution: LEFT_BLADE_COEFFICIENT_OF_RESTITUTION,
+                    },
+                    Blade {
+                        length: RIGHT_BLADE_LENGTH,
+                        thickness: RIGHT_BLADE_THICKNESS,
+                        rotation: RIGHT_BLADE_ROTATION,
+                        restitution: RIGHT_BLADE_COEFFICIENT_OF_RESTITUTION,
+                    },
+                ],
+            },
+            balls: Balls {
+                count: args.balls,
+                radius: args.ball_radius,
+                restitution: args.ball_restitution,
+                altitude: args.initial_altitude,
+            },
+        }
+    }
+}
+
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        // Colliders need a size, and a negative restitution adds energy on
+        // every bounce
+        let restitution = |field, restitution: f32| {
+            if restitution >= 0.0 && restitution.is_finite() {
+                Ok(())
+            } else {
+                Err(SceneError::Invalid {
+                    field,
+                    reason: "must be a finite number of at least 0".to_string(),
+                })
+            }
+        };
+        for wall in &self.walls {
+            check_finite("walls.center", &wall.center)?;
+            check_positive("walls.half_extents", wall.half_extents[0])?;
+            check_positive("walls.half_extents", wall.half_extents[1])?;
+            restitution("walls.restitution", wall.restitution)?;
+        }
+        check_finite("fan.angular_velocity", &[self.fan.angular_velocity])?;
+        for blade in &self.fan.blades {
+            check_positive("fan.blades.length", blade.length)?;
+            check_positive("fan.blades.thickness", blade.thickness)?;
+            check_finite("fan.blades.rotation", &[blade.rotation])?;
+            restitution("fan.blades.restitution", blade.restitution)?;
+        }
+        check_positive("balls.radius", self.balls.radius)?;
+        check_finite("balls.altitude", &[self.balls.altitude])?;
+        restitution("balls.restitution", self.balls.restitution)
+    }
+}
+
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        Scene::new(WIDTH, HEIGHT, Setup::from_args(args))
+    })
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
@@ -20,8 +198,11 @@ struct Model {
     query_pipeline: QueryPipeline,
     physics_hooks: (),
     event_handler: (),
+    playback: Playback,
+    recorder: Recorder,
 }
 
+const BALL_COUNT: usize = 10;
 const BALL_RADIUS: f32 = 2.0;
 const BALL_COEFFICIENT_OF_RESTITUTION: f32 = 1.0;
 const LEFT_BLADE_COEFFICIENT_OF_RESTITUTION: f32 = 1.0;
@@ -35,109 +216,108 @@ const RIGHT_BLADE_LENGTH: f32 = 40.0;
 const RIGHT_BLADE_THICKNESS: f32 = 1.0;
 const SCALE_FACTOR: f32 = 10.0;
 
-fn model(app: &App) -> Model {
-    let mut rigid_body_set = RigidBodySet::new();
-    let mut collider_set = ColliderSet::new();
-
-    let left_wall = ColliderBuilder::cuboid(1.0, 39.0)
-        .translation(vector![-40.0, 0.0])
-        .restitution(LEFT_BLADE_COEFFICIENT_OF_RESTITUTION)
-        .friction(0.0)
-        .build();
-    collider_set.insert(left_wall);
-
-    let right_wall = ColliderBuilder::cuboid(1.0, 39.0)
-        .translation(vector![40.0, 0.0])
-        .restitution(LEFT_BLADE_COEFFICIENT_OF_RESTITUTION)
-        .friction(0.0)
-        .build();
-    collider_set.insert(right_wall);
-
-    let ceiling = ColliderBuilder::cuboid(39.0, 1.0)
-        .translation(vector![0.0, 40.0])
-        .restitution(LEFT_BLADE_COEFFICIENT_OF_RESTITUTION)
-        .friction(0.0)
-        .build();
-    collider_set.insert(ceiling);
-
-    let floor = ColliderBuilder::cuboid(39.0, 1.0)
-        .translation(vector![0.0, -40.0])
-        .restitution(LEFT_BLADE_COEFFICIENT_OF_RESTITUTION)
-        .friction(0.0)
-        .build();
-    collider_set.insert(floor);
-
-    let fan = RigidBodyBuilder::dynamic()
-        .lock_translations()
-        .angvel(PI / 2.0)
-        .angular_damping(0.0)
-        .linear_damping(0.0)
-        .build();
-    let left_blade_collider =
-        ColliderBuilder::cuboid(LEFT_BLADE_LENGTH / 2.0, LEFT_BLADE_THICKNESS / 2.0)
-            .rotation(LEFT_BLADE_ROTATION)
-            .restitution(LEFT_BLADE_COEFFICIENT_OF_RESTITUTION)
-            .friction(0.0)
-            .build();
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let setup = &scene.sketch;
+        let mut rng = seed::rng(seed::resolve(args.seed.seed.or(scene.seed)));
+        let mut rigid_body_set = RigidBodySet::new();
+        let mut collider_set = ColliderSet::new();
 
-    let right_blade_collider =
-        ColliderBuilder::cuboid(RIGHT_BLADE_LENGTH / 2.0, RIGHT_BLADE_THICKNESS / 2.0)
-            .rotation(RIGHT_BLADE_ROTATION)
-            .restitution(RIGHT_BLADE_COEFFICIENT_OF_RESTITUTION)
-            .friction(0.0)
+        for wall in setup.walls.iter() {
+            let collider = ColliderBuilder::cuboid(wall.half_extents[0], wall.half_extents[1])
+                .translation(vector![wall.center[0], wall.center[1]])
+                .restitution(wall.restitution)
+                .friction(0.0)
+                .build();
+            collider_set.insert(collider);
+        }
+
+        let fan = RigidBodyBuilder::dynamic()
+            .lock_translations()
+            .angvel(setup.fan.angular_velocity)
+            .angular_damping(0.0)
+            .linear_damping(0.0)
             .build();
+        let fan_handle = rigid_body_set.insert(fan);
+        for blade in setup.fan.blades.iter() {
+            let collider = ColliderBuilder::cuboid(blade.length / 2.0, blade.thickness / 2.0)
+                .rotation(blade.rotation)
+                .restitution(blade.restitution)
+                .friction(0.0)
+                .build();
+            collider_set.insert_with_parent(collider, fan_handle, &mut rigid_body_set);
+        }
 
-    let fan_handle = rigid_body_set.insert(fan);
-    collider_set.insert_with_parent(left_blade_collider, fan_handle, &mut rigid_body_set);
-    collider_set.insert_with_parent(right_blade_collider, fan_handle, &mut rigid_body_set);
+        /* Create the bouncing ball. */
+        let balls = &setup.balls;
+        for _ in 0..balls.count {
+            let rigid_body = RigidBodyBuilder::dynamic()
+                .translation(vector![rng.gen_range(-1.0..1.0), balls.altitude])
+                .build();
+            let collider = ColliderBuilder::ball(balls.radius)
+                .restitution(balls.restitution)
+                .friction(0.0)
+                .build();
+            let ball_body_handle = rigid_body_set.insert(rigid_body);
+            collider_set.insert_with_parent(collider, ball_body_handle, &mut rigid_body_set);
+        }
 
-    /* Create the bouncing ball. */
-    for _ in 0..10 {
-        let rigid_body = RigidBodyBuilder::dynamic()
-            .translation(vector![random_range(-1.0, 1.0), INITIAL_ALTITUDE])
-            .build();
-        let collider = ColliderBuilder::ball(BALL_RADIUS)
-            .restitution(BALL_COEFFICIENT_OF_RESTITUTION)
-            .friction(0.0)
-            .build();
-        let ball_body_handle = rigid_body_set.insert(rigid_body);
-        collider_set.insert_with_parent(collider, ball_body_handle, &mut rigid_body_set);
-    }
+        /* Create other structures necessary for the simulation. */
+        let gravity = vector![0.0, -9.81];
+        let integration_parameters = IntegrationParameters::default();
+        let physics_pipeline = PhysicsPipeline::new();
+        let island_manager = IslandManager::new();
+        let broad_phase = DefaultBroadPhase::new();
+        let narrow_phase = NarrowPhase::new();
+        let impulse_joint_set = ImpulseJointSet::new();
+        let multibody_joint_set = MultibodyJointSet::new();
+        let ccd_solver = CCDSolver::new();
+        let query_pipeline = QueryPipeline::new();
+        let physics_hooks = ();
+        let event_handler = ();
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
 
-    /* Create other structures necessary for the simulation. */
-    let gravity = vector![0.0, -9.81];
-    let integration_parameters = IntegrationParameters::default();
-    let physics_pipeline = PhysicsPipeline::new();
-    let island_manager = IslandManager::new();
-    let broad_phase = DefaultBroadPhase::new();
-    let narrow_phase = NarrowPhase::new();
-    let impulse_joint_set = ImpulseJointSet::new();
-    let multibody_joint_set = MultibodyJointSet::new();
-    let ccd_solver = CCDSolver::new();
-    let query_pipeline = QueryPipeline::new();
-    let physics_hooks = ();
-    let event_handler = ();
-
-    let _window = app.new_window().size(800, 800).view(view).build().unwrap();
-    Model {
-        gravity,
-        integration_parameters,
-        rigid_body_set,
-        collider_set,
-        physics_pipeline,
-        island_manager,
-        broad_phase,
-        narrow_phase,
-        impulse_joint_set,
-        multibody_joint_set,
-        ccd_solver,
-        query_pipeline,
-        physics_hooks,
-        event_handler,
+        Model {
+            gravity,
+            integration_parameters,
+            rigid_body_set,
+            collider_set,
+            physics_pipeline,
+            island_manager,
+            broad_phase,
+            narrow_phase,
+            impulse_joint_set,
+            multibody_joint_set,
+            ccd_solver,
+            query_pipeline,
+            physics_hooks,
+            event_handler,
+            playback,
+            recorder,
+        }
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn model(app: &App) -> Model {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let _window = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&args, &scene)
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
+}
+
+fn step(model: &mut Model) {
     model.physics_pipeline.step(
         &model.gravity,
         &model.integration_parameters,
@@ -155,10 +335,15 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     );
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw: Draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
     for (_, collider) in model.collider_set.iter() {
         let position = collider.position().translation.vector;
@@ -167,26 +352,35 @@ fn view(app: &App, model: &Model, frame: Frame) {
         match shape.shape_type() {
             ShapeType::Ball => {
                 let ball = shape.as_ball().unwrap();
-                draw.ellipse()
-                    .x_y(position.x * SCALE_FACTOR, position.y * SCALE_FACTOR)
-                    .radius(ball.radius * SCALE_FACTOR)
-                    .rgba(0.5, 0.5, 0.5, 1.0)
-                    .stroke(BLACK);
+                renderer.ellipse(
+                    pt2(position.x * SCALE_FACTOR, position.y * SCALE_FACTOR),
+                    ball.radius * 2.0 * SCALE_FACTOR,
+                    ball.radius * 2.0 * SCALE_FACTOR,
+                    Style::fill(rgba(0.5, 0.5, 0.5, 1.0)).with_stroke(BLACK, 1.0),
+                );
             }
             ShapeType::Cuboid => {
                 let cuboid = shape.as_cuboid().unwrap();
-                draw.rect()
-                    .x_y(position.x * SCALE_FACTOR, position.y * SCALE_FACTOR)
-                    .w_h(
-                        cuboid.half_extents.x * 2.0 * SCALE_FACTOR,
-                        cuboid.half_extents.y * 2.0 * SCALE_FACTOR,
-                    )
-                    .rotate(collider.position().rotation.angle())
-                    .color(BLACK);
+                renderer.rect(
+                    pt2(position.x * SCALE_FACTOR, position.y * SCALE_FACTOR),
+                    cuboid.half_extents.x * 2.0 * SCALE_FACTOR,
+                    cuboid.half_extents.y * 2.0 * SCALE_FACTOR,
+                    collider.position().rotation.angle(),
+                    Style::fill(BLACK),
+                );
             }
             _ => {}
         }
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
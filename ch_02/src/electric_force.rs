@@ -1,165 +1,213 @@
-use itertools::Itertools;
+use clap::Parser;
 use nannou::prelude::*;
+use noc::forces::{self, Body};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::scene::{BodyScene, RandomBodies, Scene, SceneArgs, SceneError, Validate};
+use noc::seed::{self, SeedArgs};
+use noc::snapshot::{SnapshotArgs, Snapshots};
+use serde::{Deserialize, Serialize};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
 
 const ELECTRIC_CONSTANT: f32 = 1.0e3;
 
-fn main() {
-    nannou::app(model).update(update).run();
-}
-
-#[derive(Clone)]
-struct Body {
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    mass: f32,
-    net_force: Vec2,
-    alive: bool,
-    charge: f32,
-}
-
-impl Body {
-    fn new(position: Vec2, velocity: Vec2, acceleration: Vec2, mass: f32) -> Self {
-        Body {
-            position,
-            velocity,
-            acceleration,
-            mass,
-            net_force: vec2(0.0, 0.0),
-            alive: true,
-            charge: random_range(-1.0, 1.0)
-        }
-    }
+/// Charged bodies attracting and repelling each other, leaving trails.
+#[derive(Parser)]
+struct Args {
+    /// Coulomb constant scaling the force between charges.
+    #[arg(long, default_value_t = ELECTRIC_CONSTANT)]
+    electric_constant: f32,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+/// The sketch's part of a scene file.
+#[derive(Serialize, Deserialize)]
+struct Setup {
+    electric_constant: f32,
+    bodies: BodyScene,
+}
 
-    fn get_color(&self) -> Rgb {
-        if self.charge > 0.0 {
-            return rgb(self.charge, 0.0, 1.0 - self.charge);
-        } else if self.charge < 0.0 {
-            return rgb(1.0 - self.charge, 0.0, self.charge);
-        } else {
-            return rgb(1.0, 1.0, 1.0);
+impl Setup {
+    fn from_args(args: &Args) -> Self {
+        Setup {
+            electric_constant: args.electric_constant,
+            bodies: BodyScene {
+                bodies: vec![],
+                random: Some(RandomBodies {
+                    count: 500,
+                    extent: 400.0,
+                    speed: 0.0,
+                    mass: [1.0, 5.0],
+                    charge: [-1.0, 1.0],
+                }),
+            },
         }
     }
+}
 
-    fn apply_force(&mut self, force: Vec2) {
-        self.net_force += force;
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        self.bodies.validate()
     }
+}
 
-    fn reset_force(&mut self) {
-        self.net_force = vec2(0.0, 0.0);
-    }
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        Scene::new(WIDTH, HEIGHT, Setup::from_args(args))
+    })
+}
 
-    fn radius(&self) -> f32 {
-        self.mass.log2()
+fn main() {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, nth| {
+                step(&mut model);
+                render(&model, canvas, nth);
+            },
+        );
+        model.snapshots.finish(|| &model.bodies);
+        return;
     }
+    nannou::app(model).update(update).exit(exit).run();
+}
 
-    fn display(&self, draw: &Draw) {
-        draw.ellipse()
-            .xy(self.position)
-            .radius(self.radius())
-            .color(self.get_color());
+// Negative charges mirror positive ones, taking the charge's size
+fn get_color(body: &Body) -> Rgb {
+    if body.charge > 0.0 {
+        rgb(body.charge, 0.0, 1.0 - body.charge)
+    } else if body.charge < 0.0 {
+        rgb(1.0 + body.charge, 0.0, -body.charge)
+    } else {
+        rgb(1.0, 1.0, 1.0)
     }
 }
 
+fn display<R: Renderer>(body: &Body, renderer: &mut R) {
+    let diameter = body.radius() * 2.0;
+    renderer.ellipse(
+        body.position,
+        diameter,
+        diameter,
+        Style::fill(get_color(body)),
+    );
+}
+
 struct Model {
     bodies: Vec<Body>,
+    electric_constant: f32,
+    snapshots: Snapshots,
+    playback: Playback,
+    recorder: Recorder,
 }
 
-fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    let mut bodies = vec![];
-    let n = 500;
-    for _i in 0..n {
-        let position: Vec2 = vec2(random_range(-400.0, 400.0), random_range(-400.0, 400.0));
-        // let velocity: Vec2 = vec2(random_f32() * 100.0 - 50.0, random_f32() * 100.0 - 50.0);
-        let velocity: Vec2 = vec2(0.0, 0.0);
-        let acceleration: Vec2 = vec2(0.0, 0.0);
-        let mass: f32 = random_range(1.0, 5.0);
-        bodies.push(Body::new(position, velocity, acceleration, mass));
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let mut rng = seed::rng(seed::resolve(args.seed.seed.or(scene.seed)));
+        let bodies = args
+            .snapshot
+            .resume()
+            .unwrap_or_else(|| scene.sketch.bodies.build(&mut rng));
+        let snapshots = Snapshots::new(&args.snapshot, "electric_force_snapshot.bin");
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            bodies,
+            electric_constant: scene.sketch.electric_constant,
+            snapshots,
+            playback,
+            recorder,
+        }
     }
-    Model { bodies }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    let delta_t: f32 = 1.0 / 60.0;
+fn model(app: &App) -> Model {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let _window = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&args, &scene)
+}
 
-    // apply electrical between each pair of bodies
-    for (i, j) in (0..model.bodies.len()).tuple_combinations() {
-        let (left, right) = model.bodies.split_at_mut(j);
-        let body1: &mut Body = &mut left[i];
-        let body2: &mut Body = &mut right[0];
-        let displacement: Vec2 = body2.position - body1.position;
-        // kludge to avoid division by zero
-        if displacement.length() <= 1.0 {
-            continue;
-        }
-        let force: Vec2 = ELECTRIC_CONSTANT * (body1.charge * body2.charge) / displacement.length().powi(2)
-            * -displacement.normalize();
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.snapshots.key_pressed(key, || &model.bodies);
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
+}
 
-        body1.apply_force(force);
-        body2.apply_force(-force);
-    }
+fn step(model: &mut Model) {
+    let delta_t: f32 = 1.0 / 60.0;
+    let k = model.electric_constant;
+
+    // apply electrical force between each pair of bodies
+    forces::apply_pairwise(&mut model.bodies, 1.0, |b1, b2| forces::electric(k, b1, b2));
 
     for body in model.bodies.iter_mut() {
-        body.acceleration = body.net_force / body.mass;
-        body.velocity += body.acceleration * delta_t / 2.0;
-        body.position += body.velocity * delta_t;
-        body.reset_force();
+        body.kick(delta_t);
+        body.drift(delta_t);
     }
 
-    // apply force between each pair of bodies
-    for (i, j) in (0..model.bodies.len()).tuple_combinations() {
-        let (left, right) = model.bodies.split_at_mut(j);
-        let body1: &mut Body = &mut left[i];
-        let body2: &mut Body = &mut right[0];
-        let displacement: Vec2 = body2.position - body1.position;
-        // kludge to avoid division by zero
-        if displacement.length() <= body1.radius() || displacement.length() <= body2.radius() {
-            if body1.mass > body2.mass {
-                body1.mass += body2.mass;
-                body2.alive = false;
-            } else {
-                body2.mass += body1.mass;
-                body1.alive = false;
-            }
-            body1.charge += body2.charge;
-            body2.charge += body1.charge;
-            continue;
-        }
-        let force: Vec2 =
-            ELECTRIC_CONSTANT * (body1.charge * body2.charge) / displacement.length().pow(2) * -displacement.normalize();
-
-        body1.apply_force(force);
-        body2.apply_force(-force);
-    }
+    // apply force between each pair of bodies, merging bodies that collide
+    forces::apply_pairwise_with_merging(&mut model.bodies, |b1, b2| forces::electric(k, b1, b2));
 
     for body in model.bodies.iter_mut() {
-        body.acceleration = body.net_force / body.mass;
-        body.velocity += body.acceleration * delta_t / 2.0;
-        body.reset_force();
+        body.kick(delta_t);
     }
 
-    model.bodies = model
-        .bodies
-        .iter()
-        .filter(|b: &&Body| b.alive)
-        .cloned()
-        .collect();
+    forces::remove_dead(&mut model.bodies);
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    model.playback.show_in_title(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    if frame.nth() == 0 {
-      draw.background().color(BLACK);
+fn render<R: Renderer>(model: &Model, renderer: &mut R, nth: u64) {
+    if nth == 0 {
+        renderer.background(BLACK);
     }
 
     for body in model.bodies.iter() {
-        body.display(&draw);
+        display(body, renderer);
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw, frame.nth());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+    model.snapshots.finish(|| &model.bodies);
+}
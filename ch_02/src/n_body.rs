@@ -1,157 +1,239 @@
-use itertools::Itertools;
+use clap::Parser;
 use nannou::prelude::*;
+use nannou::winit::event::WindowEvent;
+use nannou_egui::egui;
+use noc::forces::Body;
+use noc::n_body::NBody;
+use noc::panel::{Controls, Panel};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::scene::{BodyScene, RandomBodies, Scene, SceneArgs, SceneError, Validate};
+use noc::seed::{self, SeedArgs};
+use noc::simulation::Simulation;
+use noc::snapshot::{SnapshotArgs, Snapshots};
+use serde::{Deserialize, Serialize};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
 
 const G: f32 = 1.0e4;
 const CENTRAL_MASS: f32 = 500.0;
+const DT: f32 = 1.0 / 60.0;
 
-fn main() {
-    nannou::app(model).update(update).run();
-}
-
-#[derive(Clone)]
-struct Body {
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    mass: f32,
-    net_force: Vec2,
-    alive: bool,
-    color: Rgb,
-}
-
-impl Body {
-    fn new(position: Vec2, velocity: Vec2, acceleration: Vec2, mass: f32) -> Self {
-        Body {
-            position,
-            velocity,
-            acceleration,
-            mass,
-            net_force: vec2(0.0, 0.0),
-            alive: true,
-            color: rgb(random(), random(), random()),
-        }
-    }
+/// Bodies attracting each other while orbiting a central mass.
+#[derive(Parser)]
+struct Args {
+    /// Gravitational constant.
+    #[arg(long, default_value_t = G)]
+    g: f32,
 
-    fn apply_force(&mut self, force: Vec2) {
-        self.net_force += force;
-    }
+    /// Mass of the fixed body at the centre of the window.
+    #[arg(long, default_value_t = CENTRAL_MASS)]
+    central_mass: f32,
+
+    /// Seconds of simulated time per tick.
+    #[arg(long, default_value_t = DT)]
+    dt: f32,
+
+    #[command(flatten)]
+    scene: SceneArgs,
 
-    fn reset_force(&mut self) {
-        self.net_force = vec2(0.0, 0.0);
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+/// The sketch's part of a scene file.
+#[derive(Clone, Serialize, Deserialize)]
+struct Setup {
+    g: f32,
+    central_mass: f32,
+    #[serde(default = "default_dt")]
+    dt: f32,
+    bodies: BodyScene,
+}
+
+fn default_dt() -> f32 {
+    DT
+}
+
+impl Setup {
+    fn from_args(args: &Args) -> Self {
+        Setup {
+            g: args.g,
+            central_mass: args.central_mass,
+            dt: args.dt,
+            bodies: BodyScene {
+                bodies: vec![],
+                random: Some(RandomBodies {
+                    count: 500,
+                    extent: 400.0,
+                    speed: 50.0,
+                    mass: [1.0, 5.0],
+                    charge: [0.0, 0.0],
+                }),
+            },
+        }
     }
+}
 
-    fn radius(&self) -> f32 {
-        self.mass.log2()
+impl Controls for Setup {
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::Slider::new(&mut self.g, 0.0..=1.0e5)
+                .logarithmic(true)
+                .text("G"),
+        );
+        ui.add(egui::Slider::new(&mut self.central_mass, 0.0..=5000.0).text("central mass"));
+        ui.add(
+            egui::Slider::new(&mut self.dt, 0.001..=0.1)
+                .logarithmic(true)
+                .text("time step"),
+        );
     }
+}
 
-    fn display(&self, draw: &Draw) {
-        draw.ellipse()
-            .xy(self.position)
-            .radius(self.radius())
-            .color(self.color);
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        self.bodies.validate()
     }
 }
 
-struct Model {
-    bodies: Vec<Body>,
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        Scene::new(WIDTH, HEIGHT, Setup::from_args(args))
+    })
 }
 
-fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    let mut bodies = vec![];
-    let n = 500;
-    for _i in 0..n {
-        let position: Vec2 = vec2(random_range(-400.0, 400.0), random_range(-400.0, 400.0));
-        let velocity: Vec2 = vec2(random_f32() * 100.0 - 50.0, random_f32() * 100.0 - 50.0);
-        let acceleration: Vec2 = vec2(0.0, 0.0);
-        let mass: f32 = random_range(1.0, 5.0);
-        bodies.push(Body::new(position, velocity, acceleration, mass));
+fn main() {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        model.snapshots.finish(|| model.simulation.state());
+        return;
     }
-    Model { bodies }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    let delta_t: f32 = 1.0 / 60.0;
-
-    // apply gravitational force between each pair of bodies
-    for (i, j) in (0..model.bodies.len()).tuple_combinations() {
-        let (left, right) = model.bodies.split_at_mut(j);
-        let body1: &mut Body = &mut left[i];
-        let body2: &mut Body = &mut right[0];
-        let displacement: Vec2 = body2.position - body1.position;
-        // kludge to avoid division by zero
-        if displacement.length() <= 1.0 {
-            continue;
-        }
-        let force: Vec2 = G * (body1.mass * body2.mass) / displacement.length().powi(2)
-            * displacement.normalize();
+fn display<R: Renderer>(body: &Body, renderer: &mut R) {
+    let diameter = body.radius() * 2.0;
+    renderer.ellipse(body.position, diameter, diameter, Style::fill(body.color));
+}
 
-        body1.apply_force(force);
-        body2.apply_force(-force);
-    }
+struct Model {
+    simulation: NBody,
+    setup: Setup,
+    panel: Panel<Setup>,
+    snapshots: Snapshots,
+    playback: Playback,
+    recorder: Recorder,
+}
 
-    for body in model.bodies.iter_mut() {
-        let central_force = -1.0 * G * body.mass * CENTRAL_MASS / body.position.length().powi(2)
-            * body.position.normalize();
-        body.apply_force(central_force);
-        body.acceleration = body.net_force / body.mass;
-        body.velocity += body.acceleration * delta_t / 2.0;
-        body.position += body.velocity * delta_t;
-        body.reset_force();
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let setup = &scene.sketch;
+        let mut rng = seed::rng(seed::resolve(args.seed.seed.or(scene.seed)));
+        let bodies = args
+            .snapshot
+            .resume()
+            .unwrap_or_else(|| setup.bodies.build(&mut rng));
+        let simulation = NBody::new(bodies, setup.g, setup.central_mass);
+        let panel = Panel::new(scene.clone(), args.scene.save_path("n_body.toml"));
+        let snapshots = Snapshots::new(&args.snapshot, "n_body_snapshot.bin");
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            simulation,
+            setup: setup.clone(),
+            panel,
+            snapshots,
+            playback,
+            recorder,
+        }
     }
+}
 
-    // apply gravitational force between each pair of bodies
-    for (i, j) in (0..model.bodies.len()).tuple_combinations() {
-        let (left, right) = model.bodies.split_at_mut(j);
-        let body1: &mut Body = &mut left[i];
-        let body2: &mut Body = &mut right[0];
-        let displacement: Vec2 = body2.position - body1.position;
-        // kludge to avoid division by zero
-        if displacement.length() <= body1.radius() || displacement.length() <= body2.radius() {
-            if body1.mass > body2.mass {
-                body1.mass += body2.mass;
-                body2.alive = false;
-            } else {
-                body2.mass += body1.mass;
-                body1.alive = false;
-            }
-            continue;
-        }
-        let force: Vec2 =
-            G * (body1.mass * body2.mass) / displacement.length().pow(2) * displacement.normalize();
+fn model(app: &App) -> Model {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let window = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .raw_event(raw_window_event)
+        .build()
+        .unwrap();
+    let mut model = Model::new(&args, &scene);
+    model.panel.attach(&app.window(window).unwrap());
+    model
+}
 
-        body1.apply_force(force);
-        body2.apply_force(-force);
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.panel.wants_keyboard() {
+        return;
     }
+    model.panel.key_pressed(key);
+    model
+        .snapshots
+        .key_pressed(key, || model.simulation.state());
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
+}
 
-    for body in model.bodies.iter_mut() {
-        let central_force = -1.0 * G * body.mass * CENTRAL_MASS / body.position.length().powi(2)
-            * body.position.normalize();
-        body.apply_force(central_force);
-        body.acceleration = body.net_force / body.mass;
-        body.velocity += body.acceleration * delta_t / 2.0;
-        body.reset_force();
-    }
+fn raw_window_event(_app: &App, model: &mut Model, event: &WindowEvent) {
+    model.panel.raw_event(event);
+}
 
-    model.bodies = model
-        .bodies
-        .iter()
-        .filter(|b: &&Body| b.alive)
-        .cloned()
-        .collect();
+fn step(model: &mut Model) {
+    model.simulation.g = model.setup.g;
+    model.simulation.central_mass = model.setup.central_mass;
+    model.simulation.step(model.setup.dt);
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.panel.update(&update, &mut model.setup);
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    draw.background().color(BLACK);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(BLACK);
 
-    for body in model.bodies.iter() {
-        body.display(&draw);
+    for body in model.simulation.state().iter() {
+        display(body, renderer);
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
+    model.panel.draw_to_frame(&frame);
+}
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+    model.snapshots.finish(|| model.simulation.state());
 }
@@ -1,8 +1,99 @@
+use clap::Parser;
 use nannou::prelude::*;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::scene::{check_finite, check_positive, Scene, SceneArgs, SceneError, Validate};
 use rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+/// A row of pendulums knocking into each other.
+#[derive(Parser)]
+struct Args {
+    /// Number of pendulums in the row.
+    #[arg(long, default_value_t = PENDULUM_COUNT)]
+    pendulums: usize,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+/// One pendulum: a fixed pivot with a bob hanging `length` below it.
+#[derive(Serialize, Deserialize)]
+struct PendulumSpec {
+    pivot: [f32; 2],
+    length: f32,
+    /// Starting velocity of the bob.
+    #[serde(default)]
+    velocity: [f32; 2],
+}
+
+/// The sketch's part of a scene file.
+#[derive(Serialize, Deserialize)]
+struct Setup {
+    ball_radius: f32,
+    pendulums: Vec<PendulumSpec>,
+}
+
+impl Setup {
+    fn from_args(args: &Args) -> Self {
+        let pendulums = (0..args.pendulums)
+            .map(|i| PendulumSpec {
+                pivot: [4.1 * i as f32, 100.0],
+                length: 100.0,
+                velocity: if i <= 1 { [-10.0, 0.0] } else { [0.0, 0.0] },
+            })
+            .collect();
+        Setup {
+            ball_radius: BALL_RADIUS,
+            pendulums,
+        }
+    }
+}
+
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        check_positive("ball_radius", self.ball_radius)?;
+        for pendulum in &self.pendulums {
+            check_finite("pendulums.pivot", &pendulum.pivot)?;
+            check_positive("pendulums.length", pendulum.length)?;
+            check_finite("pendulums.velocity", &pendulum.velocity)?;
+        }
+        Ok(())
+    }
+}
+
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        Scene::new(WIDTH, HEIGHT, Setup::from_args(args))
+    })
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
@@ -20,74 +111,102 @@ struct Model {
     query_pipeline: QueryPipeline,
     physics_hooks: (),
     event_handler: (),
+    ball_radius: f32,
+    playback: Playback,
+    recorder: Recorder,
 }
 
+const PENDULUM_COUNT: usize = 10;
 const BALL_RADIUS: f32 = 2.0;
 const SCALE_FACTOR: f32 = 2.0;
 
-fn model(app: &App) -> Model {
-    // simulate several pendula
-    let mut rigid_body_set = RigidBodySet::new();
-    let mut collider_set = ColliderSet::new();
-    let mut impulse_joint_set = ImpulseJointSet::new();
-
-    for i in 0..10 {
-        let pivot = RigidBodyBuilder::fixed()
-            .translation(vector![4.1 * i as f32, 100.0])
-            .build();
-        let pivot_handle = rigid_body_set.insert(pivot);
-
-        let mut ball = RigidBodyBuilder::dynamic()
-            .translation(vector![4.1 * i as f32, 0.0])
-            .build();
-        if i <= 1 {
-            ball.set_linvel(vector![-10.0, 0.0], true);
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let setup = &scene.sketch;
+        // simulate several pendula
+        let mut rigid_body_set = RigidBodySet::new();
+        let mut collider_set = ColliderSet::new();
+        let mut impulse_joint_set = ImpulseJointSet::new();
+
+        for pendulum in setup.pendulums.iter() {
+            let [x, y] = pendulum.pivot;
+            let pivot = RigidBodyBuilder::fixed().translation(vector![x, y]).build();
+            let pivot_handle = rigid_body_set.insert(pivot);
+
+            let ball = RigidBodyBuilder::dynamic()
+                .translation(vector![x, y - pendulum.length])
+                .linvel(vector![pendulum.velocity[0], pendulum.velocity[1]])
+                .build();
+            let ball_handle = rigid_body_set.insert(ball);
+            let ball_collider = ColliderBuilder::ball(setup.ball_radius)
+                .restitution(1.0)
+                .build();
+            collider_set.insert_with_parent(ball_collider, ball_handle, &mut rigid_body_set);
+
+            // connect ball to pivot
+            let joint = RevoluteJointBuilder::new()
+                .local_anchor1(point![0.0, 0.0])
+                .local_anchor2(point![0.0, pendulum.length])
+                .build();
+            impulse_joint_set.insert(pivot_handle, ball_handle, joint, true);
         }
-        let ball_handle = rigid_body_set.insert(ball);
-        let ball_collider = ColliderBuilder::ball(BALL_RADIUS).restitution(1.0).build();
-        collider_set.insert_with_parent(ball_collider, ball_handle, &mut rigid_body_set);
-
-        // connect ball to pivot
-        let joint = RevoluteJointBuilder::new()
-            .local_anchor1(point![0.0, 0.0])
-            .local_anchor2(point![0.0, 100.0])
-            .build();
-        impulse_joint_set.insert(pivot_handle, ball_handle, joint, true);
-    }
 
-    /* Create other structures necessary for the simulation. */
-    let gravity = vector![0.0, -9.81];
-    let integration_parameters = IntegrationParameters::default();
-    let physics_pipeline = PhysicsPipeline::new();
-    let island_manager = IslandManager::new();
-    let broad_phase = DefaultBroadPhase::new();
-    let narrow_phase = NarrowPhase::new();
-    let multibody_joint_set = MultibodyJointSet::new();
-    let ccd_solver = CCDSolver::new();
-    let query_pipeline = QueryPipeline::new();
-    let physics_hooks = ();
-    let event_handler = ();
-
-    let _window = app.new_window().size(800, 800).view(view).build().unwrap();
-    Model {
-        gravity,
-        integration_parameters,
-        rigid_body_set,
-        collider_set,
-        physics_pipeline,
-        island_manager,
-        broad_phase,
-        narrow_phase,
-        impulse_joint_set,
-        multibody_joint_set,
-        ccd_solver,
-        query_pipeline,
-        physics_hooks,
-        event_handler,
+        /* Create other structures necessary for the simulation. */
+        let gravity = vector![0.0, -9.81];
+        let integration_parameters = IntegrationParameters::default();
+        let physics_pipeline = PhysicsPipeline::new();
+        let island_manager = IslandManager::new();
+        let broad_phase = DefaultBroadPhase::new();
+        let narrow_phase = NarrowPhase::new();
+        let multibody_joint_set = MultibodyJointSet::new();
+        let ccd_solver = CCDSolver::new();
+        let query_pipeline = QueryPipeline::new();
+        let physics_hooks = ();
+        let event_handler = ();
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+
+        Model {
+            gravity,
+            integration_parameters,
+            rigid_body_set,
+            collider_set,
+            physics_pipeline,
+            island_manager,
+            broad_phase,
+            narrow_phase,
+            impulse_joint_set,
+            multibody_joint_set,
+            ccd_solver,
+            query_pipeline,
+            physics_hooks,
+            event_handler,
+            ball_radius: setup.ball_radius,
+            playback,
+            recorder,
+        }
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn model(app: &App) -> Model {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let _window = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&args, &scene)
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
+}
+
+fn step(model: &mut Model) {
     model.physics_pipeline.step(
         &model.gravity,
         &model.integration_parameters,
@@ -105,36 +224,53 @@ fn update(_app: &App, model: &mut Model, _update: Update) {
     );
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw: Draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
     // draw each joint as a line
     for (_, joint) in model.impulse_joint_set.iter() {
         let body1 = model.rigid_body_set.get(joint.body1).unwrap();
         let body2 = model.rigid_body_set.get(joint.body2).unwrap();
-        draw.line()
-            .start(pt2(
+        renderer.line(
+            pt2(
                 body1.position().translation.x * SCALE_FACTOR,
                 body1.position().translation.y * SCALE_FACTOR,
-            ))
-            .end(pt2(
+            ),
+            pt2(
                 body2.position().translation.x * SCALE_FACTOR,
                 body2.position().translation.y * SCALE_FACTOR,
-            ))
-            .color(BLACK);
+            ),
+            1.0,
+            BLACK,
+        );
 
         let body_position = body2.position().translation;
-        draw.ellipse()
-            .x_y(
+        renderer.ellipse(
+            pt2(
                 body_position.x * SCALE_FACTOR,
                 body_position.y * SCALE_FACTOR,
-            )
-            .radius(2.0 * SCALE_FACTOR)
-            .rgba(0.5, 0.5, 0.5, 1.0)
-            .stroke(BLACK);
+            ),
+            model.ball_radius * 2.0 * SCALE_FACTOR,
+            model.ball_radius * 2.0 * SCALE_FACTOR,
+            Style::fill(rgba(0.5, 0.5, 0.5, 1.0)).with_stroke(BLACK, 1.0),
+        );
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
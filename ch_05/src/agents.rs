@@ -1,87 +1,101 @@
+use clap::Parser;
 use nannou::prelude::*;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::Renderer;
+use noc::steering::Vehicle;
 
-fn main() {
-    nannou::app(model).update(update).run();
-}
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
 
-struct Vehicle {
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    max_speed: f32,
-    max_acceleration: f32,
+/// A vehicle arriving at the mouse.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    record: RecordArgs,
 }
 
-impl Vehicle {
-    fn new() -> Self {
-        let position = pt2(0.0, 0.0);
-        let velocity = vec2(0.0, 0.0);
-        let acceleration = vec2(0.0, 0.0);
-        let max_speed = 1.0;
-        let max_acceleration = 0.005;
-
-        Vehicle {
-            position,
-            velocity,
-            acceleration,
-            max_speed,
-            max_acceleration,
-        }
-    }
-
-    fn update(&mut self, target: Point2) {
-        let displacement_vector = target - self.position;
-        let d = displacement_vector.length();
-        let desired_velocity;
-        if d < 100.0 {
-            let m = map_range(d, 0.0, 100.0, 0.0, self.max_speed);
-            desired_velocity = displacement_vector.normalize() * m;
-        } else {
-            desired_velocity = displacement_vector.normalize() * self.max_speed;
-        }
-        self.acceleration =
-            (desired_velocity - self.velocity).clamp_length_max(self.max_acceleration);
-        self.velocity += self.acceleration;
-        self.position += self.velocity;
-        self.acceleration = vec2(0.0, 0.0);
-    }
-
-    fn display(&self, draw: &Draw) {
-        draw.tri()
-            .points(
-                pt2(10.0, 0.0),
-                pt2(10.0, 0.0).rotate(2.5 * PI / 3.0),
-                pt2(10.0, 0.0).rotate(-2.5 * PI / 3.0),
-            )
-            .x_y(self.position.x, self.position.y)
-            .rotate(self.velocity.angle())
-            .color(BLACK);
+fn main() {
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model, pt2(0.0, 0.0));
+                render(&model, canvas);
+            },
+        );
+        return;
     }
+    nannou::app(model).update(update).exit(exit).run();
 }
 
 struct Model {
     vehicle: Vehicle,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args) -> Self {
+        let vehicle = Vehicle::new(pt2(0.0, 0.0), 1.0, 0.005);
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            vehicle,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
+    let _window = app
+        .new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
+}
 
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    let vehicle = Vehicle::new();
-    Model { vehicle }
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn update(app: &App, model: &mut Model, _update: Update) {
-    model.vehicle.update(app.mouse.position());
+fn step(model: &mut Model, target: Point2) {
+    let steer = model.vehicle.arrive(target, 100.0);
+    model.vehicle.apply_force(steer);
+    model.vehicle.update();
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model, app.mouse.position());
+    }
+}
 
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
-    model.vehicle.display(&draw);
+    model.vehicle.render(renderer, BLACK);
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
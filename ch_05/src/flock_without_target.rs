@@ -1,170 +1,233 @@
+use clap::Parser;
 use nannou::prelude::*;
-use uuid::Uuid;
-
-const SEPARATION_STRENGTH: f32 = 0.8;
-const ALIGN_STRENGTH: f32 = 1.0;
-const COHERE_STRENGTH: f32 = 0.5;
+use nannou::winit::event::WindowEvent;
+use nannou_egui::egui;
+use noc::flock::Flock;
+use noc::panel::{Controls, Panel};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::Renderer;
+use noc::scene::{Scene, SceneArgs, SceneError, Validate};
+use noc::seed::{self, SeedArgs};
+use noc::simulation::Simulation;
+use noc::snapshot::{SnapshotArgs, Snapshots};
+use noc::steering::{FlockWeights, Vehicle};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+const WEIGHTS: FlockWeights = FlockWeights {
+    seek: 0.0,
+    separation: 0.8,
+    align: 1.0,
+    cohere: 0.5,
+};
+
+/// A free-roaming flock wrapping around the window edges.
+#[derive(Parser)]
+struct Args {
+    /// Weight of steering towards the target.
+    #[arg(long, default_value_t = WEIGHTS.seek)]
+    seek: f32,
+
+    /// Weight of steering away from close neighbours.
+    #[arg(long, default_value_t = WEIGHTS.separation)]
+    separation: f32,
+
+    /// Weight of matching the neighbours' heading.
+    #[arg(long, default_value_t = WEIGHTS.align)]
+    align: f32,
+
+    /// Weight of steering towards the neighbours' centre.
+    #[arg(long, default_value_t = WEIGHTS.cohere)]
+    cohere: f32,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
-fn main() {
-    nannou::app(model).update(update).run();
-}
-
-#[derive(PartialEq, Clone)]
-struct Vehicle {
-    id: String,
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    max_speed: f32,
-    max_acceleration: f32,
-}
-
-impl Vehicle {
-    fn new() -> Self {
-        let position: Vec2 = vec2(random_range(-400.0, 400.0), random_range(-400.0, 400.0));
-        let velocity: Vec2 = vec2(0.0, 0.0);
-        let acceleration: Vec2 = vec2(0.0, 0.0);
-
-        let max_speed = 1.0;
-        let max_acceleration = 0.005;
-        let id = Uuid::new_v4().to_string();
-
-        Vehicle {
-            id,
-            position,
-            velocity,
-            acceleration,
-            max_speed,
-            max_acceleration,
+impl Args {
+    fn weights(&self) -> FlockWeights {
+        FlockWeights {
+            seek: self.seek,
+            separation: self.separation,
+            align: self.align,
+            cohere: self.cohere,
         }
     }
+}
 
-    fn separate(&self, vehicles: &Vec<Vehicle>) -> Vec2 {
-        let desired_separation = 25.0;
-        let mut steer = vec2(0.0, 0.0);
-        for vehicle in vehicles.iter() {
-            if vehicle.id == self.id {
-                continue;
-            }
-            let d = self.position.distance(vehicle.position);
-            if d > 0.0 && d < desired_separation {
-                let diff = self.position - vehicle.position;
-                steer += diff;
-            }
-        }
+/// The sketch's part of a scene file.
+#[derive(Clone, Serialize, Deserialize)]
+struct Setup {
+    weights: FlockWeights,
+    /// Whether vehicles leaving the window come back on the other side.
+    wrap: bool,
+}
 
-        steer.normalize_or_zero()
+impl Controls for Setup {
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        self.weights.ui(ui);
+        ui.checkbox(&mut self.wrap, "wrap around edges");
     }
+}
 
-    fn seek(&self, target: Point2) -> Vec2 {
-        (target - self.position).normalize_or_zero()
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        self.weights.validate()
     }
+}
 
-    fn align(&self, vehicles: &Vec<Vehicle>) -> Vec2 {
-        let neighbor_distance = 50.0;
-        let mut sum = vec2(0.0, 0.0);
-        for vehicle in vehicles.iter() {
-            if vehicle.id == self.id {
-                continue;
-            }
-            let d = self.position.distance(vehicle.position);
-            if d < neighbor_distance {
-                sum += vehicle.velocity;
-            }
-        }
-        return sum.normalize_or_zero();
-    }
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        let setup = Setup {
+            weights: args.weights(),
+            wrap: true,
+        };
+        Scene::new(WIDTH, HEIGHT, setup)
+    })
+}
 
-    fn cohere(&self, vehicles: &Vec<Vehicle>) -> Vec2 {
-        let neighbor_distance = 50.0;
-        let mut sum = vec2(0.0, 0.0);
-        for vehicle in vehicles.iter() {
-            if vehicle.id == self.id {
-                continue;
-            }
-            let d = self.position.distance(vehicle.position);
-            if d < neighbor_distance {
-                sum += self.seek(vehicle.position);
-            }
-        }
-        return sum.normalize_or_zero();
+fn main() {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        model.snapshots.finish(|| model.simulation.state());
+        return;
     }
+    nannou::app(model).update(update).exit(exit).run();
+}
 
-    fn wrap_position(&mut self) {
-        let w = 400.0;
-        let h = 400.0;
-        if self.position.x > w {
-            self.position.x = -w;
-        } else if self.position.x < -w {
-            self.position.x = w;
-        }
-        if self.position.y > h {
-            self.position.y = -h;
-        } else if self.position.y < -h {
-            self.position.y = h;
+fn new_vehicle(rng: &mut StdRng) -> Vehicle {
+    let position: Vec2 = vec2(rng.gen_range(-400.0..400.0), rng.gen_range(-400.0..400.0));
+    Vehicle::new(position, 1.0, 0.005)
+}
+
+struct Model {
+    simulation: Flock,
+    setup: Setup,
+    panel: Panel<Setup>,
+    bounds: Rect,
+    snapshots: Snapshots,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let mut rng = seed::rng(seed::resolve(args.seed.seed.or(scene.seed)));
+        let vehicles: Vec<Vehicle> = args
+            .snapshot
+            .resume()
+            .unwrap_or_else(|| (0..200).map(|_| new_vehicle(&mut rng)).collect());
+        let simulation = Flock::new(vehicles, scene.sketch.weights);
+        let bounds = Rect::from_w_h(scene.window.width as f32, scene.window.height as f32);
+        let panel = Panel::new(
+            scene.clone(),
+            args.scene.save_path("flock_without_target.toml"),
+        );
+        let snapshots = Snapshots::new(&args.snapshot, "flock_without_target_snapshot.bin");
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            simulation,
+            setup: scene.sketch.clone(),
+            panel,
+            bounds,
+            snapshots,
+            playback,
+            recorder,
         }
     }
+}
 
-    fn update(&mut self, vehicles: &Vec<Vehicle>) {
-        let separation_acceleration: Vec2 = self.separate(vehicles) * SEPARATION_STRENGTH;
-        let align_acceleration: Vec2 = self.align(vehicles) * ALIGN_STRENGTH;
-        let cohere_acceleration: Vec2 = self.cohere(vehicles) * COHERE_STRENGTH;
-        let total_accel: Vec2 =
-            separation_acceleration + align_acceleration + cohere_acceleration;
-        self.acceleration = total_accel.clamp_length_max(self.max_acceleration);
-
-        self.velocity += self.acceleration;
-        self.velocity = self.velocity.clamp_length_max(self.max_speed);
-        self.position += self.velocity;
-        self.wrap_position();
-        self.acceleration = vec2(0.0, 0.0);
-    }
+fn model(app: &App) -> Model {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let window = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .raw_event(raw_window_event)
+        .build()
+        .unwrap();
+    let mut model = Model::new(&args, &scene);
+    model.panel.attach(&app.window(window).unwrap());
+    model
+}
 
-    fn display(&self, draw: &Draw) {
-        draw.tri()
-            .points(
-                pt2(10.0, 0.0),
-                pt2(10.0, 0.0).rotate(2.5 * PI / 3.0),
-                pt2(10.0, 0.0).rotate(-2.5 * PI / 3.0),
-            )
-            .x_y(self.position.x, self.position.y)
-            .rotate(self.velocity.angle())
-            .color(BLACK);
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.panel.wants_keyboard() {
+        return;
     }
+    model.panel.key_pressed(key);
+    model
+        .snapshots
+        .key_pressed(key, || model.simulation.state());
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-struct Model {
-    vehicles: Vec<Vehicle>,
+fn raw_window_event(_app: &App, model: &mut Model, event: &WindowEvent) {
+    model.panel.raw_event(event);
 }
 
-fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    let mut vehicles: Vec<Vehicle> = vec![];
-    let n = 200;
-    for _i in 0..n {
-        vehicles.push(Vehicle::new());
-    }
-    Model { vehicles }
+fn step(model: &mut Model) {
+    model.simulation.weights = model.setup.weights;
+    model.simulation.bounds = model.setup.wrap.then_some(model.bounds);
+    model.simulation.step(1.0 / 60.0);
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    let vehicles = model.vehicles.clone();
-    for vehicle in model.vehicles.iter_mut() {
-        vehicle.update(&vehicles);
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.panel.update(&update, &mut model.setup);
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
     }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
-
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
-    for vehicle in model.vehicles.iter() {
-        vehicle.display(&draw);
+    for vehicle in model.simulation.state().iter() {
+        vehicle.render(renderer, BLACK);
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
+    model.panel.draw_to_frame(&frame);
+}
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+    model.snapshots.finish(|| model.simulation.state());
 }
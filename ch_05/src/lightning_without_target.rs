@@ -1,223 +1,374 @@
+use clap::Parser;
 use nannou::prelude::*;
-use uuid::Uuid;
+use nannou::winit::event::WindowEvent;
+use nannou_egui::egui;
+use noc::mover::wrap;
+use noc::panel::{Controls, Panel};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::Renderer;
+use noc::scene::{Scene, SceneArgs, SceneError, Validate};
+use noc::seed::{self, SeedArgs};
+use noc::snapshot::{SnapshotArgs, Snapshots};
+use noc::steering::{FlockRadii, FlockWeights, Vehicle};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 const VEHICLE_COUNT: usize = 150;
 
-const SEPARATION_STRENGTH: f32 = 0.9;
-const ALIGN_STRENGTH: f32 = 0.7;
-const COHERE_STRENGTH: f32 = 0.3;
-const SEEK_STRENGTH: f32 = 1.0;
+const WEIGHTS: FlockWeights = FlockWeights {
+    seek: 1.0,
+    separation: 0.9,
+    align: 0.7,
+    cohere: 0.3,
+};
 
 const FLASH_THRESHOLD: i32 = 200;
 const MAX_FLASH_TIMER: i32 = 400;
 
-fn main() {
-    nannou::app(model).update(update).run();
-}
-
-#[derive(PartialEq, Clone)]
-struct Vehicle {
-    id: String,
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    max_speed: f32,
-    max_acceleration: f32,
-    flash_timer: i32,
-}
-
-impl Vehicle {
-    fn new() -> Self {
-        let position: Vec2 = vec2(random_range(-400.0, 400.0), random_range(-400.0, 400.0));
-        let velocity: Vec2 = vec2(0.0, 0.0);
-        let acceleration: Vec2 = vec2(0.0, 0.0);
-
-        let max_speed = 1.0;
-        let max_acceleration = 0.005;
-        let id = Uuid::new_v4().to_string();
-        let flash_timer = random_range(0, MAX_FLASH_TIMER);
-
-        Vehicle {
-            id,
-            position,
-            velocity,
-            acceleration,
-            max_speed,
-            max_acceleration,
-            flash_timer,
-        }
-    }
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
 
-    fn separate(&self, vehicles: &Vec<Vehicle>) -> Vec2 {
-        let desired_separation = 25.0 * 25.0;
-        let mut steer = vec2(0.0, 0.0);
-        for vehicle in vehicles.iter() {
-            if vehicle.id == self.id {
-                continue;
-            }
-            let d = self.position.distance_squared(vehicle.position);
-            if d > 0.0 && d < desired_separation {
-                let diff = self.position - vehicle.position;
-                steer += diff;
-            }
-        }
+/// Fireflies roaming after a wandering target and flashing in sync.
+#[derive(Parser)]
+struct Args {
+    /// Number of fireflies.
+    #[arg(long, default_value_t = VEHICLE_COUNT)]
+    vehicles: usize,
 
-        steer.normalize_or_zero()
-    }
+    /// Weight of steering towards the target.
+    #[arg(long, default_value_t = WEIGHTS.seek)]
+    seek: f32,
 
-    fn seek(&self, target: Point2) -> Vec2 {
-        (target - self.position).normalize_or_zero()
-    }
+    /// Weight of steering away from close neighbours.
+    #[arg(long, default_value_t = WEIGHTS.separation)]
+    separation: f32,
 
-    fn align(&self, vehicles: &Vec<Vehicle>) -> Vec2 {
-        let neighbor_distance = 50.0 * 50.0;
-        let mut sum = vec2(0.0, 0.0);
-        for vehicle in vehicles.iter() {
-            if vehicle.id == self.id {
-                continue;
-            }
-            let d = self.position.distance_squared(vehicle.position);
-            if d < neighbor_distance {
-                sum += vehicle.velocity;
-            }
+    /// Weight of matching the neighbours' heading.
+    #[arg(long, default_value_t = WEIGHTS.align)]
+    align: f32,
+
+    /// Weight of steering towards the neighbours' centre.
+    #[arg(long, default_value_t = WEIGHTS.cohere)]
+    cohere: f32,
+
+    /// Flash timer value above which a firefly is lit and can set off its
+    /// neighbours.
+    #[arg(long, default_value_t = FLASH_THRESHOLD)]
+    flash_threshold: i32,
+
+    /// Flash timer value a firefly is reset to when it flashes.
+    #[arg(long, default_value_t = MAX_FLASH_TIMER)]
+    max_flash_timer: i32,
+
+    #[command(flatten)]
+    scene: SceneArgs,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+impl Args {
+    fn weights(&self) -> FlockWeights {
+        FlockWeights {
+            seek: self.seek,
+            separation: self.separation,
+            align: self.align,
+            cohere: self.cohere,
         }
-        return sum.normalize_or_zero();
     }
+}
 
-    fn cohere(&self, vehicles: &Vec<Vehicle>) -> Vec2 {
-        let neighbor_distance = 50.0 * 50.0;
-        let mut sum = vec2(0.0, 0.0);
-        for vehicle in vehicles.iter() {
-            if vehicle.id == self.id {
-                continue;
-            }
-            let d = self.position.distance_squared(vehicle.position);
-            if d < neighbor_distance {
-                sum += self.seek(vehicle.position);
-            }
+/// The sketch's part of a scene file.
+#[derive(Clone, Serialize, Deserialize)]
+struct Setup {
+    vehicles: usize,
+    weights: FlockWeights,
+    flash_threshold: i32,
+    max_flash_timer: i32,
+}
+
+impl Setup {
+    fn from_args(args: &Args) -> Self {
+        Setup {
+            vehicles: args.vehicles,
+            weights: args.weights(),
+            flash_threshold: args.flash_threshold,
+            max_flash_timer: args.max_flash_timer,
         }
-        return sum.normalize_or_zero();
     }
+}
 
-    fn wrap_position(&mut self) {
-        let w = 400.0;
-        let h = 400.0;
-        if self.position.x > w {
-            self.position.x = -w;
-        } else if self.position.x < -w {
-            self.position.x = w;
-        }
-        if self.position.y > h {
-            self.position.y = -h;
-        } else if self.position.y < -h {
-            self.position.y = h;
-        }
+impl Controls for Setup {
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        self.weights.ui(ui);
+        ui.add(egui::Slider::new(&mut self.max_flash_timer, 1..=1000).text("max flash timer"));
+        ui.add(
+            egui::Slider::new(&mut self.flash_threshold, 0..=self.max_flash_timer - 1)
+                .text("flash threshold"),
+        );
+        // the threshold has to stay below the timer it counts down from
+        self.flash_threshold = self.flash_threshold.min(self.max_flash_timer - 1);
     }
+}
 
-    fn handle_flash(&mut self, vehicles: &Vec<Vehicle>) {
-        self.flash_timer -= 1;
-        let mut flashing_count = 0;
-        if self.flash_timer < 0 {
-            for vehicle in vehicles.iter() {
-                if vehicle.id == self.id {
-                    continue;
-                }
-                let d = self.position.distance_squared(vehicle.position);
-                if d < pow(35.0, 2) && vehicle.flash_timer > FLASH_THRESHOLD {
-                    self.flash_timer = random_range(FLASH_THRESHOLD, MAX_FLASH_TIMER);
-                    flashing_count += 1;
-                    if flashing_count > 0 {
-                        break;
-                    }
-                }
-            }
+impl Validate for Setup {
+    fn validate(&self) -> Result<(), SceneError> {
+        self.weights.validate()?;
+        // Each firefly starts at a random point of its countdown
+        if self.max_flash_timer < 1 {
+            return Err(SceneError::Invalid {
+                field: "max_flash_timer",
+                reason: "must be at least 1".to_string(),
+            });
         }
-        if self.flash_timer < 0 {
-            let n = random_range(0, 100000);
-            if n < 5 {
-                self.flash_timer = random_range(FLASH_THRESHOLD, MAX_FLASH_TIMER);
-            }
+        if !(0..self.max_flash_timer).contains(&self.flash_threshold) {
+            return Err(SceneError::Invalid {
+                field: "flash_threshold",
+                reason: "must be at least 0 and below `max_flash_timer`".to_string(),
+            });
         }
+        Ok(())
     }
+}
+
+fn load_scene(args: &Args) -> Scene<Setup> {
+    Scene::from_args(&args.scene, || {
+        Scene::new(WIDTH, HEIGHT, Setup::from_args(args))
+    })
+}
 
-    fn update(&mut self, vehicles: &Vec<Vehicle>, target: Point2) {
-        let separation_acceleration: Vec2 = self.separate(vehicles) * SEPARATION_STRENGTH;
-        let align_acceleration: Vec2 = self.align(vehicles) * ALIGN_STRENGTH;
-        let cohere_acceleration: Vec2 = self.cohere(vehicles) * COHERE_STRENGTH;
-        let seek_acceleration: Vec2 = self.seek(target) * SEEK_STRENGTH;
-        let total_accel: Vec2 = seek_acceleration + separation_acceleration + align_acceleration + cohere_acceleration;
-        self.acceleration = total_accel.clamp_length_max(self.max_acceleration);
-
-        self.velocity += self.acceleration;
-        self.velocity = self.velocity.clamp_length_max(self.max_speed);
-        self.position += self.velocity;
-        self.wrap_position();
-        self.acceleration = vec2(0.0, 0.0);
-
-        self.handle_flash(vehicles);
+fn main() {
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args, &scene);
+        recorder::run_headless(
+            &args.record,
+            scene.window.width,
+            scene.window.height,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model);
+                render(&model, canvas);
+            },
+        );
+        model
+            .snapshots
+            .finish(|| (&model.vehicles, &model.flash_timers, model.target));
+        return;
     }
+    nannou::app(model).update(update).exit(exit).run();
+}
+
+fn new_vehicle(rng: &mut StdRng) -> Vehicle {
+    let position: Vec2 = vec2(rng.gen_range(-400.0..400.0), rng.gen_range(-400.0..400.0));
+    Vehicle::new(position, 1.0, 0.005)
+}
 
-    fn display(&self, draw: &Draw) {
-        let mut intensity = 0;
-        if self.flash_timer > FLASH_THRESHOLD {
-            intensity = map_range(self.flash_timer, FLASH_THRESHOLD, MAX_FLASH_TIMER, 0, 255);
+fn update_vehicle(
+    vehicle: &mut Vehicle,
+    vehicles: &[Vehicle],
+    target: Point2,
+    weights: FlockWeights,
+    bounds: Rect,
+) {
+    let total_accel = vehicle.flock(vehicles, Some(target), weights, FlockRadii::default());
+    let max_acceleration = vehicle.max_acceleration;
+    vehicle.apply_force(total_accel.clamp_length_max(max_acceleration));
+    vehicle.update();
+    wrap(&mut vehicle.position, bounds);
+}
+
+fn handle_flash(
+    flash_timer: &mut i32,
+    vehicle: &Vehicle,
+    vehicles: &[Vehicle],
+    flash_timers: &[i32],
+    threshold: i32,
+    max_timer: i32,
+    rng: &mut StdRng,
+) {
+    *flash_timer -= 1;
+    if *flash_timer < 0 {
+        for (other, &other_timer) in vehicles.iter().zip(flash_timers) {
+            if other.id == vehicle.id {
+                continue;
+            }
+            let d = vehicle.position.distance_squared(other.position);
+            if d < pow(35.0, 2) && other_timer > threshold {
+                *flash_timer = rng.gen_range(threshold..max_timer);
+                break;
+            }
         }
-        // make the gray base a bit more blue based on the intensity
-        let color = srgb(
-            GRAY.red,
-            GRAY.green,
-            (GRAY.blue as u32 + intensity as u32).clamp(0, 255) as u8,
-        );
+    }
+    if *flash_timer < 0 {
+        let n = rng.gen_range(0..100000);
+        if n < 5 {
+            *flash_timer = rng.gen_range(threshold..max_timer);
+        }
+    }
+}
 
-        draw.tri()
-            .points(
-                pt2(10.0, 0.0),
-                pt2(10.0, 0.0).rotate(2.5 * PI / 3.0),
-                pt2(10.0, 0.0).rotate(-2.5 * PI / 3.0),
-            )
-            .x_y(self.position.x, self.position.y)
-            .rotate(self.velocity.angle())
-            .color(color);
+fn flash_color(flash_timer: i32, threshold: i32, max_timer: i32) -> Srgb<u8> {
+    let mut intensity = 0;
+    if flash_timer > threshold {
+        intensity = map_range(flash_timer, threshold, max_timer, 0, 255);
     }
+    // make the gray base a bit more blue based on the intensity
+    srgb(
+        GRAY.red,
+        GRAY.green,
+        (GRAY.blue as u32 + intensity as u32).clamp(0, 255) as u8,
+    )
 }
 
 struct Model {
     vehicles: Vec<Vehicle>,
+    flash_timers: Vec<i32>,
     target: Point2,
+    setup: Setup,
+    panel: Panel<Setup>,
+    bounds: Rect,
+    rng: StdRng,
+    snapshots: Snapshots,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args, scene: &Scene<Setup>) -> Self {
+        let setup = &scene.sketch;
+        let mut rng = seed::rng(seed::resolve(args.seed.seed.or(scene.seed)));
+        let (vehicles, flash_timers, target) = args.snapshot.resume().unwrap_or_else(|| {
+            let mut vehicles: Vec<Vehicle> = vec![];
+            let mut flash_timers: Vec<i32> = vec![];
+            for _i in 0..setup.vehicles {
+                vehicles.push(new_vehicle(&mut rng));
+                flash_timers.push(rng.gen_range(0..setup.max_flash_timer));
+            }
+            (vehicles, flash_timers, vec2(0.0, 0.0))
+        });
+        let bounds = Rect::from_w_h(scene.window.width as f32, scene.window.height as f32);
+        let panel = Panel::new(
+            scene.clone(),
+            args.scene.save_path("lightning_without_target.toml"),
+        );
+        let snapshots = Snapshots::new(&args.snapshot, "lightning_without_target_snapshot.bin");
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            vehicles,
+            flash_timers,
+            target,
+            setup: setup.clone(),
+            panel,
+            bounds,
+            rng,
+            snapshots,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
+    let args = Args::parse();
+    let scene = load_scene(&args);
+    let window = app
+        .new_window()
+        .size(scene.window.width, scene.window.height)
+        .view(view)
+        .key_pressed(key_pressed)
+        .raw_event(raw_window_event)
+        .build()
+        .unwrap();
+    let mut model = Model::new(&args, &scene);
+    model.panel.attach(&app.window(window).unwrap());
+    model
+}
 
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    let mut vehicles: Vec<Vehicle> = vec![];
-    for _i in 0..VEHICLE_COUNT {
-        vehicles.push(Vehicle::new());
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.panel.wants_keyboard() {
+        return;
     }
-    let target = vec2(0.0, 0.0);
-    Model { vehicles, target }
+    model.panel.key_pressed(key);
+    model
+        .snapshots
+        .key_pressed(key, || (&model.vehicles, &model.flash_timers, model.target));
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn raw_window_event(_app: &App, model: &mut Model, event: &WindowEvent) {
+    model.panel.raw_event(event);
+}
+
+fn step(model: &mut Model) {
     let vehicles = model.vehicles.clone();
-    if random_range(0, 1000) < 5 {
-        model.target = vec2(random_range(-300.0, 300.0), random_range(-300.0, 300.0));
+    let flash_timers = model.flash_timers.clone();
+    if model.rng.gen_range(0..1000) < 5 {
+        model.target = vec2(
+            model.rng.gen_range(-300.0..300.0),
+            model.rng.gen_range(-300.0..300.0),
+        );
     }
     let target = model.target;
-    for vehicle in model.vehicles.iter_mut() {
-        vehicle.update(&vehicles, target);
+    let bounds = model.bounds;
+    for (vehicle, flash_timer) in model.vehicles.iter_mut().zip(model.flash_timers.iter_mut()) {
+        update_vehicle(vehicle, &vehicles, target, model.setup.weights, bounds);
+        handle_flash(
+            flash_timer,
+            vehicle,
+            &vehicles,
+            &flash_timers,
+            model.setup.flash_threshold,
+            model.setup.max_flash_timer,
+            &mut model.rng,
+        );
     }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.panel.update(&update, &mut model.setup);
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    draw.background().color(DARKSLATEGRAY);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(DARKSLATEGRAY);
 
-    for vehicle in model.vehicles.iter() {
-        vehicle.display(&draw);
+    for (vehicle, &flash_timer) in model.vehicles.iter().zip(model.flash_timers.iter()) {
+        let color = flash_color(
+            flash_timer,
+            model.setup.flash_threshold,
+            model.setup.max_flash_timer,
+        );
+        vehicle.render(renderer, color);
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
+    model.panel.draw_to_frame(&frame);
+}
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+    model
+        .snapshots
+        .finish(|| (&model.vehicles, &model.flash_timers, model.target));
 }
@@ -1,86 +1,187 @@
+use clap::Parser;
 use nannou::prelude::*;
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::seed::{self, SeedArgs};
+use noc::snapshot::{SnapshotArgs, Snapshots};
+use rand::rngs::StdRng;
+use rand::Rng;
 
-fn main() {
-    nannou::app(model).update(update).run();
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 800;
+
+/// A one-dimensional elementary cellular automaton, one generation per row.
+#[derive(Parser)]
+struct Args {
+    /// New state for each neighbourhood, from 111 down to 000, as eight
+    /// comma-separated 0s and 1s.
+    #[arg(long, value_parser = parse_ruleset, default_value = "0,1,0,1,1,0,1,0")]
+    ruleset: [i32; 8],
+
+    /// Start from random cells instead of a single live cell in the middle.
+    #[arg(long)]
+    random_start: bool,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    #[command(flatten)]
+    record: RecordArgs,
 }
 
-const RULESET: [i32; 8] = [0, 1, 0, 1, 1, 0, 1, 0];
+fn parse_ruleset(s: &str) -> Result<[i32; 8], String> {
+    let values = s
+        .split(',')
+        .map(|v| match v.trim() {
+            "0" => Ok(0),
+            "1" => Ok(1),
+            other => Err(format!("expected 0 or 1, found `{}`", other)),
+        })
+        .collect::<Result<Vec<i32>, String>>()?;
+    values
+        .try_into()
+        .map_err(|values: Vec<i32>| format!("expected 8 values, found {}", values.len()))
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, nth| {
+                step(&mut model);
+                render(&model, canvas, nth);
+            },
+        );
+        model.snapshots.finish(|| (&model.cells, model.generation));
+        return;
+    }
+    nannou::app(model).update(update).exit(exit).run();
+}
 
 struct Model {
     cells: Vec<i32>,
     generation: i32,
+    ruleset: [i32; 8],
+    snapshots: Snapshots,
+    playback: Playback,
+    recorder: Recorder,
 }
 
-fn initialize_middle_cell(cells: &mut Vec<i32>) {
+fn initialize_middle_cell(cells: &mut [i32]) {
     let middle = cells.len() / 2;
     cells[middle] = 1;
 }
 
-fn initialize_random_cells(cells: &mut Vec<i32>) {
-    for i in 0..cells.len() {
-        if random_f32() < 0.5 {
-            cells[i] = 1;
-        } else {
-            cells[i] = 0;
+fn initialize_random_cells(cells: &mut [i32], rng: &mut StdRng) {
+    for cell in cells.iter_mut() {
+        *cell = if rng.gen::<f32>() < 0.5 { 1 } else { 0 };
+    }
+}
+
+impl Model {
+    fn new(args: &Args) -> Self {
+        let (cells, generation) = args.snapshot.resume().unwrap_or_else(|| {
+            let mut cells = vec![0; WIDTH as usize];
+            if args.random_start {
+                let mut rng = seed::rng(seed::resolve(args.seed.seed));
+                initialize_random_cells(&mut cells, &mut rng);
+            } else {
+                initialize_middle_cell(&mut cells);
+            }
+            (cells, 0)
+        });
+        let snapshots = Snapshots::new(&args.snapshot, "ca_1_snapshot.bin");
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+
+        Model {
+            cells,
+            generation,
+            ruleset: args.ruleset,
+            snapshots,
+            playback,
+            recorder,
         }
     }
 }
 
 fn model(app: &App) -> Model {
-    let w: u32 = 800;
-    let h: u32 = 800;
-
-    let _window = app.new_window().size(w, h).view(view).build().unwrap();
-    let mut cells = vec![0; 800 as usize];
-    initialize_middle_cell(&mut cells);
-    // initialize_random_cells(&mut cells);
-    let generation = 0;
+    let _window = app
+        .new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
+}
 
-    Model { cells, generation }
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model
+        .snapshots
+        .key_pressed(key, || (&model.cells, model.generation));
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn rules(a: i32, b: i32, c: i32) -> i32 {
+fn rules(ruleset: &[i32; 8], a: i32, b: i32, c: i32) -> i32 {
     // define the rules of the cellular automaton
     if a == 1 && b == 1 && c == 1 {
-        RULESET[0]
+        ruleset[0]
     } else if a == 1 && b == 1 && c == 0 {
-        RULESET[1]
+        ruleset[1]
     } else if a == 1 && b == 0 && c == 1 {
-        RULESET[2]
+        ruleset[2]
     } else if a == 1 && b == 0 && c == 0 {
-        RULESET[3]
+        ruleset[3]
     } else if a == 0 && b == 1 && c == 1 {
-        RULESET[4]
+        ruleset[4]
     } else if a == 0 && b == 1 && c == 0 {
-        RULESET[5]
+        ruleset[5]
     } else if a == 0 && b == 0 && c == 1 {
-        RULESET[6]
+        ruleset[6]
     } else if a == 0 && b == 0 && c == 0 {
-        RULESET[7]
+        ruleset[7]
     } else {
         0
     }
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
+fn step(model: &mut Model) {
     // update the cells based on the rules of the cellular automaton
     if model.generation == 0 {
         model.generation += 1;
         return;
     }
     let mut new_cells = model.cells.clone();
-    for i in 1..model.cells.len() - 1 {
-        new_cells[i] = rules(model.cells[i - 1], model.cells[i], model.cells[i + 1]);
+    for (i, window) in model.cells.windows(3).enumerate() {
+        new_cells[i + 1] = rules(&model.ruleset, window[0], window[1], window[2]);
     }
     model.cells = new_cells;
     model.generation += 1;
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn update(app: &App, model: &mut Model, _update: Update) {
+    model.recorder.capture_window(app);
+    model.playback.show_in_title(app);
+    for _ in 0..model.playback.ticks() {
+        step(model);
+    }
+}
 
-    if frame.nth() == 0 {
-        draw.background().color(DARKSLATEGREY);
+fn render<R: Renderer>(model: &Model, renderer: &mut R, nth: u64) {
+    if nth == 0 {
+        renderer.background(DARKSLATEGREY);
     }
 
     // draw a square for each cell
@@ -94,14 +195,27 @@ fn view(app: &App, model: &Model, frame: Frame) {
             continue;
         }
 
+        // eight copies rotated about the centre of the window
         for i in 0..8 {
-            draw.rotate(i as f32 * PI / 4.0)
-                .rect()
-                .x_y(x, y)
-                .w_h(cell_width, cell_width)
-                .color(ORANGERED);
+            let rotation = i as f32 * PI / 4.0;
+            renderer.rect(
+                pt2(x, y).rotate(rotation),
+                cell_width,
+                cell_width,
+                rotation,
+                Style::fill(ORANGERED),
+            );
         }
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw, frame.nth());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+    model.snapshots.finish(|| (&model.cells, model.generation));
+}
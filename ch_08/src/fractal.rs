@@ -0,0 +1,331 @@
+use clap::ValueEnum;
+
+/// How close Newton's method must come to a root to count as converged.
+const NEWTON_TOLERANCE: f64 = 1e-9;
+/// Newton's method converges in a few dozen steps if it converges at all,
+/// so more would only darken the image.
+const NEWTON_LIMIT: u32 = 64;
+
+/// An escape-time fractal: each point of the plane is colored by how many
+/// iterations of a map its orbit takes to escape, or for Newton fractals to
+/// settle on a root.
+pub trait Fractal: Send + Sync {
+    /// Iterations for the point `(a, b)`, up to `limit`, which marks a point
+    /// that never escaped.
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32;
+
+    /// The point zoomed into unless another is given, on the fractal's
+    /// boundary so that the zoom keeps finding detail.
+    fn center(&self) -> (f64, f64);
+
+    /// The zoom frame to start from unless another is given. Frame 0 shows
+    /// the plane 1 either side of the center.
+    fn starting_frame(&self) -> u32 {
+        0
+    }
+
+    /// For fractals whose points settle on one of several roots rather than
+    /// escaping, the root the point `(a, b)` settles on within `limit`
+    /// iterations.
+    fn root(&self, _a: f64, _b: f64, _limit: u32) -> Option<(f64, f64)> {
+        None
+    }
+
+    /// The most iterations worth running, however deep the zoom.
+    fn max_limit(&self) -> u32 {
+        u32::MAX
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FractalKind {
+    /// z² + c, starting from z = 0 with c the point.
+    Mandelbrot,
+    /// z² + c, starting from z at the point with c fixed by `--julia`.
+    Julia,
+    /// (|Re z| + i|Im z|)² + c.
+    BurningShip,
+    /// conj(z)² + c.
+    Tricorn,
+    /// zⁿ + c, for n given by `--power`.
+    Multibrot,
+    /// Newton's method on zⁿ - 1, for n given by `--power` rounded, starting
+    /// from the point.
+    Newton,
+}
+
+impl FractalKind {
+    pub fn fractal(self, julia: (f64, f64), power: f64) -> Box<dyn Fractal> {
+        match self {
+            FractalKind::Mandelbrot => Box::new(Mandelbrot),
+            FractalKind::Julia => Box::new(Julia { c: julia }),
+            FractalKind::BurningShip => Box::new(BurningShip),
+            FractalKind::Tricorn => Box::new(Tricorn),
+            FractalKind::Multibrot => Box::new(Multibrot { power }),
+            FractalKind::Newton => Box::new(Newton {
+                degree: (power.round() as i32).max(2),
+            }),
+        }
+    }
+}
+
+pub struct Mandelbrot;
+
+pub struct Julia {
+    pub c: (f64, f64),
+}
+
+pub struct BurningShip;
+
+pub struct Tricorn;
+
+pub struct Multibrot {
+    pub power: f64,
+}
+
+pub struct Newton {
+    pub degree: i32,
+}
+
+/// Iterations of `step` from `z` until it leaves the circle of radius 2, up
+/// to `limit`. Starting the maps of the form f(z) + c from c rather than 0
+/// saves an iteration.
+fn escape(mut z: (f64, f64), limit: u32, step: impl Fn(f64, f64) -> (f64, f64)) -> u32 {
+    let mut iteration = 0;
+    while z.0 * z.0 + z.1 * z.1 < 4.0 && iteration < limit {
+        z = step(z.0, z.1);
+        iteration += 1;
+    }
+    iteration
+}
+
+impl Fractal for Mandelbrot {
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32 {
+        escape((a, b), limit, |x, y| (x * x - y * y + a, 2.0 * x * y + b))
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (-0.743643887037151, 0.131825904205330)
+    }
+
+    // Deep into the seahorse valley, at the limit of `f64` without `--deep`
+    fn starting_frame(&self) -> u32 {
+        680
+    }
+}
+
+impl Fractal for Julia {
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32 {
+        let (cx, cy) = self.c;
+        escape((a, b), limit, |x, y| (x * x - y * y + cx, 2.0 * x * y + cy))
+    }
+
+    // The repelling fixed point (1 + √(1 - 4c)) / 2, which always lies on
+    // the Julia set
+    fn center(&self) -> (f64, f64) {
+        let (cx, cy) = self.c;
+        let (sx, sy) = sqrt(1.0 - 4.0 * cx, -4.0 * cy);
+        ((1.0 + sx) / 2.0, sy / 2.0)
+    }
+}
+
+impl Fractal for BurningShip {
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32 {
+        escape((a, b), limit, |x, y| {
+            (x * x - y * y + a, 2.0 * (x * y).abs() + b)
+        })
+    }
+
+    // The largest of the little ships along the antenna. The imaginary axis
+    // points down, so the ship sails upright
+    fn center(&self) -> (f64, f64) {
+        (-1.7621, -0.0281)
+    }
+}
+
+impl Fractal for Tricorn {
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32 {
+        escape((a, b), limit, |x, y| (x * x - y * y + a, -2.0 * x * y + b))
+    }
+
+    // Where the bulbs along the left-hand arm meet the main body
+    fn center(&self) -> (f64, f64) {
+        (-1.0, 0.1002502772)
+    }
+}
+
+impl Fractal for Multibrot {
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32 {
+        escape((a, b), limit, |x, y| {
+            // zⁿ in polar form, so that n needn't be a whole number
+            let r = (x * x + y * y).powf(self.power / 2.0);
+            let theta = y.atan2(x) * self.power;
+            (r * theta.cos() + a, r * theta.sin() + b)
+        })
+    }
+
+    // The cusp on the positive real axis, where the fixed point z = zⁿ + c
+    // stops attracting: there n zⁿ⁻¹ = 1, so c = z - zⁿ
+    fn center(&self) -> (f64, f64) {
+        if self.power <= 1.0 {
+            return (0.0, 0.0);
+        }
+        let z = self.power.powf(-1.0 / (self.power - 1.0));
+        (z * (1.0 - 1.0 / self.power), 0.0)
+    }
+}
+
+impl Newton {
+    /// Steps of Newton's method from `(a, b)` until it settles, up to
+    /// `limit`, and the root it settled on.
+    fn settle(&self, a: f64, b: f64, limit: u32) -> (u32, Option<(f64, f64)>) {
+        let n = self.degree;
+        let (mut x, mut y) = (a, b);
+        for iteration in 0..limit {
+            // z - (zⁿ - 1) / (n zⁿ⁻¹)
+            let (px, py) = power(x, y, n - 1);
+            let (fx, fy) = (px * x - py * y - 1.0, px * y + py * x);
+            let (dx, dy) = (n as f64 * px, n as f64 * py);
+            let norm = dx * dx + dy * dy;
+            if norm == 0.0 {
+                return (limit, None);
+            }
+            let step = ((fx * dx + fy * dy) / norm, (fy * dx - fx * dy) / norm);
+            x -= step.0;
+            y -= step.1;
+            if step.0 * step.0 + step.1 * step.1 < NEWTON_TOLERANCE * NEWTON_TOLERANCE {
+                return (iteration, Some((x, y)));
+            }
+        }
+        (limit, None)
+    }
+}
+
+impl Fractal for Newton {
+    fn iterations(&self, a: f64, b: f64, limit: u32) -> u32 {
+        self.settle(a, b, limit).0
+    }
+
+    fn root(&self, a: f64, b: f64, limit: u32) -> Option<(f64, f64)> {
+        self.settle(a, b, limit).1
+    }
+
+    // A point Newton's method sends straight to the pole at 0, where
+    // (n - 1) zⁿ = -1. Every basin of attraction meets around it
+    fn center(&self) -> (f64, f64) {
+        let n = self.degree as f64;
+        let r = (1.0 / (n - 1.0)).powf(1.0 / n);
+        let theta = std::f64::consts::PI / n;
+        (r * theta.cos(), r * theta.sin())
+    }
+
+    fn max_limit(&self) -> u32 {
+        NEWTON_LIMIT
+    }
+}
+
+/// The principal square root of `x + iy`.
+fn sqrt(x: f64, y: f64) -> (f64, f64) {
+    let r = x.hypot(y).sqrt();
+    let theta = y.atan2(x) / 2.0;
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// `(x + iy)ⁿ`, by repeated squaring.
+fn power(x: f64, y: f64, n: i32) -> (f64, f64) {
+    let (mut result, mut base, mut n) = ((1.0, 0.0), (x, y), n);
+    while n > 0 {
+        if n & 1 == 1 {
+            result = (
+                result.0 * base.0 - result.1 * base.1,
+                result.0 * base.1 + result.1 * base.0,
+            );
+        }
+        base = (base.0 * base.0 - base.1 * base.1, 2.0 * base.0 * base.1);
+        n >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: u32 = 500;
+    // Douady's rabbit, whose Julia set has an interior for the origin to lie in
+    const RABBIT: (f64, f64) = (-0.123, 0.745);
+
+    fn fractals() -> Vec<(&'static str, Box<dyn Fractal>)> {
+        [
+            ("mandelbrot", FractalKind::Mandelbrot),
+            ("julia", FractalKind::Julia),
+            ("burning ship", FractalKind::BurningShip),
+            ("tricorn", FractalKind::Tricorn),
+            ("multibrot", FractalKind::Multibrot),
+        ]
+        .into_iter()
+        .map(|(name, kind)| (name, kind.fractal(RABBIT, 3.0)))
+        .collect()
+    }
+
+    #[test]
+    fn interior_points_reach_the_limit() {
+        // The origin, whose orbit settles on a fixed point or, for the
+        // rabbit, a cycle of three
+        for (name, fractal) in fractals() {
+            assert_eq!(fractal.iterations(0.0, 0.0, LIMIT), LIMIT, "{}", name);
+        }
+    }
+
+    #[test]
+    fn far_points_escape_at_once() {
+        // The loop starts from z₁, so a point already outside counts 0
+        for (name, fractal) in fractals() {
+            assert_eq!(fractal.iterations(3.0, 3.0, LIMIT), 0, "{}", name);
+        }
+    }
+
+    #[test]
+    fn centers_lie_on_the_boundary() {
+        // Within a hair of the center, some points escape and some don't
+        for (name, fractal) in fractals() {
+            let (a, b) = fractal.center();
+            let near = |k: i32| k as f64 * 1e-3;
+            let escaped: Vec<bool> = (-10..=10)
+                .flat_map(|i| (-10..=10).map(move |j| (near(i), near(j))))
+                .map(|(da, db)| fractal.iterations(a + da, b + db, LIMIT) < LIMIT)
+                .collect();
+            assert!(escaped.contains(&true), "{}", name);
+            assert!(escaped.contains(&false), "{}", name);
+        }
+    }
+
+    #[test]
+    fn newton_settles_on_each_root_of_unity() {
+        for degree in 2..=5 {
+            let newton = Newton { degree };
+            for k in 0..degree {
+                let theta = 2.0 * std::f64::consts::PI * k as f64 / degree as f64;
+                let (x, y) = (theta.cos(), theta.sin());
+                let (a, b) = (x * 1.05 + 0.01, y * 1.05 - 0.01);
+                assert!(newton.iterations(a, b, NEWTON_LIMIT) < NEWTON_LIMIT);
+                let (rx, ry) = newton.root(a, b, NEWTON_LIMIT).unwrap();
+                assert!((rx - x).abs() < 1e-6 && (ry - y).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn newton_center_meets_every_basin() {
+        let newton = Newton { degree: 3 };
+        let (a, b) = newton.center();
+        let mut roots: Vec<(i64, i64)> = (-20..=20)
+            .flat_map(|i| (-20..=20).map(move |j| (i as f64 * 1e-3, j as f64 * 1e-3)))
+            .filter_map(|(da, db)| newton.root(a + da, b + db, NEWTON_LIMIT))
+            .map(|(x, y)| ((x * 1e3).round() as i64, (y * 1e3).round() as i64))
+            .collect();
+        roots.sort();
+        roots.dedup();
+        assert_eq!(roots.len(), 3);
+    }
+}
@@ -1,113 +1,288 @@
-use nannou::{
-    color::IntoLinSrgba,
-    image::{DynamicImage, GenericImage},
-    prelude::*,
-};
+mod fractal;
+mod perturbation;
+
+use clap::{CommandFactory, FromArgMatches, Parser};
+use fractal::{Fractal, FractalKind};
+use image::{Rgba, RgbaImage};
+use nannou::prelude::*;
+use noc::color;
+use noc::recorder::{RecordArgs, Recorder};
+use perturbation::ReferenceOrbit;
 use rayon::prelude::*;
 
 const INITIAL_CYCLE_LIMIT: u32 = 100;
 const WIDTH: u32 = 1000;
 const HEIGHT: u32 = 1000;
-const STARTING_FRAME: u32 = 680;
 const CYCLE_GROWTH_RATE: f64 = 2.0;
+const FRAMES: u32 = 320; // Frames rendered unless `--last-frame` is given
+const JULIA: &str = "-0.8,0.156";
+const POWER: f64 = 3.0;
+
+/// Renders a zoom into the Mandelbrot set, or another escape-time fractal,
+/// as a recorded frame sequence.
+#[derive(Parser)]
+struct Args {
+    /// Fractal to render.
+    #[arg(long, value_enum, default_value_t = FractalKind::Mandelbrot)]
+    fractal: FractalKind,
+
+    /// Constant c of the Julia set, as `re,im`.
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true, default_value = JULIA)]
+    julia: (f64, f64),
+
+    /// Exponent n of the Multibrot set, or degree of the polynomial the
+    /// Newton fractal finds the roots of.
+    #[arg(long, default_value_t = POWER)]
+    power: f64,
+
+    /// Point to zoom into, as `re,im`. Defaults to a point of interest on
+    /// the edge of the fractal. With `--deep` every digit given is used, so
+    /// a zoom to 1e-100 needs a hundred or so.
+    #[arg(long, value_parser = parse_exact_point, allow_hyphen_values = true)]
+    center: Option<(String, String)>,
+
+    /// Zoom past the limits of `f64` by perturbation: only the center's
+    /// orbit is iterated to full precision, and each pixel's orbit as a
+    /// small `f64` offset from it, down to a scale of around 1e-300.
+    /// Mandelbrot only.
+    #[arg(long)]
+    deep: bool,
+
+    /// Zoom frame to start rendering from; each frame zooms in by 5%.
+    /// Defaults to where the fractal's zoom gets going: frame 680 for the
+    /// Mandelbrot set and frame 0 for the rest.
+    #[arg(long)]
+    starting_frame: Option<u32>,
+
+    /// Zoom frame to stop rendering at (exclusive). Defaults to 320 frames
+    /// after the first.
+    #[arg(long)]
+    last_frame: Option<u32>,
+
+    /// Iteration limit at frame 1, before it grows with the zoom.
+    #[arg(long, default_value_t = INITIAL_CYCLE_LIMIT)]
+    initial_cycle_limit: u32,
+
+    /// Exponent of the frame number the iteration limit grows with.
+    #[arg(long, default_value_t = CYCLE_GROWTH_RATE)]
+    cycle_growth_rate: f64,
+
+    /// `--headless` limits the number of frames rendered; the zoom always
+    /// runs offscreen. No animation is made unless asked for, as every frame
+    /// is a 1000x1000 image.
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+impl Args {
+    /// The arguments, with the zoom's own recording defaults.
+    fn parse_with_defaults() -> Self {
+        let command = Args::command().mut_arg("animation", |arg| arg.default_value("none"));
+        Args::from_arg_matches(&command.get_matches()).unwrap_or_else(|err| err.exit())
+    }
+}
+
+/// A point of the complex plane written `re,im`.
+fn parse_point(s: &str) -> Result<(f64, f64), String> {
+    let (re, im) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected `re,im`, found `{}`", s))?;
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("invalid number `{}`", v))
+    };
+    Ok((parse(re)?, parse(im)?))
+}
+
+/// A point written `re,im`, kept as written so that no digits are lost.
+fn parse_exact_point(s: &str) -> Result<(String, String), String> {
+    parse_point(s)?;
+    let (re, im) = s.split_once(',').unwrap();
+    Ok((re.trim().to_string(), im.trim().to_string()))
+}
 
 fn main() {
-    let mut model = Model::new();
+    let args = Args::parse_with_defaults();
+    if args.deep && args.fractal != FractalKind::Mandelbrot {
+        eprintln!("--deep only zooms into the Mandelbrot set");
+        std::process::exit(1);
+    }
+    let fractal = args.fractal.fractal(args.julia, args.power);
+    let first = args
+        .starting_frame
+        .unwrap_or_else(|| fractal.starting_frame());
+    let last = args.last_frame.unwrap_or(first + FRAMES);
+    let frames = args
+        .record
+        .headless
+        .map_or(last, |frames| first + frames as u32)
+        .min(last);
+    let mut model = Model::new(&args, fractal, first);
+    let mut recorder = Recorder::new(&args.record).frame_names("mandelbrot", first as u64);
+    if let Err(err) = recorder.start() {
+        eprintln!(
+            "failed to create {}: {}",
+            args.record.record_dir.display(),
+            err
+        );
+        std::process::exit(1);
+    }
 
-    for n in STARTING_FRAME..1000 {
+    for n in first..frames {
         model.scale *= 0.95;
         model.update_pixels(n);
-        create_image(&model, n);
+        if let Err(err) = recorder.capture(&create_image(&model, n)) {
+            eprintln!("failed to save frame {}: {}", n, err);
+        }
+    }
+    if let Err(err) = recorder.stop() {
+        eprintln!("failed to write animation: {}", err);
     }
 }
 
 struct Model {
+    fractal: Box<dyn Fractal>,
     pixels: Vec<Vec<u32>>,
+    roots: Vec<Vec<Option<(f64, f64)>>>, // The root each pixel settled on, for Newton fractals
     scale: f64,
     offset: (f64, f64),
+    center: (String, String), // The offset to every digit given
+    deep: bool,
+    reference: Option<ReferenceOrbit>, // The center's orbit, kept between deep frames
+    initial_cycle_limit: u32,
+    cycle_growth_rate: f64,
 }
 
 impl Model {
-    fn new() -> Self {
+    fn new(args: &Args, fractal: Box<dyn Fractal>, starting_frame: u32) -> Self {
         let w: usize = WIDTH as usize;
         let h: usize = HEIGHT as usize;
+        let center = args.center.clone().unwrap_or_else(|| {
+            let (re, im) = fractal.center();
+            (re.to_string(), im.to_string())
+        });
+        let offset = parse_point(&format!("{},{}", center.0, center.1)).unwrap();
         Model {
+            fractal,
             pixels: vec![vec![0; w]; h],
-            scale: 1.0 * 0.95.pow(STARTING_FRAME as f64),
-            offset: (-0.743643887037151, 0.131825904205330),
+            roots: vec![vec![None; w]; h],
+            scale: 1.0 * 0.95.pow(starting_frame as f64),
+            offset,
+            center,
+            deep: args.deep,
+            reference: None,
+            initial_cycle_limit: args.initial_cycle_limit,
+            cycle_growth_rate: args.cycle_growth_rate,
         }
     }
 
+    /// The iteration limit for a frame, growing as the zoom deepens.
+    fn cycle_limit(&self, frame_number: u32) -> f64 {
+        let limit =
+            self.initial_cycle_limit as f64 * (frame_number as f64).powf(self.cycle_growth_rate);
+        limit
+            .max(self.initial_cycle_limit as f64) // Frame 0 would get none
+            .min(self.fractal.max_limit() as f64)
+    }
+
     fn update_pixels(&mut self, frame_number: u32) {
         let h = self.pixels.len();
-        let limit = ( INITIAL_CYCLE_LIMIT as f64 * (frame_number as f64).powf(CYCLE_GROWTH_RATE)) as u32;
+        let limit = self.cycle_limit(frame_number) as u32;
+        if self.deep {
+            self.update_pixels_deep(limit);
+            return;
+        }
+        let rows = self.pixels.par_iter_mut().zip(self.roots.par_iter_mut());
+        rows.enumerate().for_each(|(i, (row, roots))| {
+            let w = row.len();
+            row.iter_mut()
+                .zip(roots)
+                .enumerate()
+                .for_each(|(j, (pixel, root))| {
+                    let a = map_range(
+                        i,
+                        0,
+                        h,
+                        -self.scale + self.offset.0,
+                        self.scale + self.offset.0,
+                    );
+                    let b = map_range(
+                        j,
+                        0,
+                        w,
+                        -self.scale + self.offset.1,
+                        self.scale + self.offset.1,
+                    );
+
+                    *pixel = self.fractal.iterations(a, b, limit);
+                    *root = self.fractal.root(a, b, limit);
+                });
+        });
+    }
+
+    // Each pixel as an offset from the center, which stays small enough for
+    // `f64` at any zoom
+    fn update_pixels_deep(&mut self, limit: u32) {
+        // The center stays put, so its orbit only needs carrying on as the
+        // limit grows, until the zoom outruns its precision
+        match &mut self.reference {
+            Some(orbit) if orbit.resolves(self.scale) => orbit.zoom(self.scale, limit),
+            _ => {
+                let center = (self.center.0.as_str(), self.center.1.as_str());
+                match ReferenceOrbit::new(center, self.scale, limit) {
+                    Ok(orbit) => self.reference = Some(orbit),
+                    Err(err) => {
+                        eprintln!("invalid center: {}", err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        let orbit = self.reference.as_ref().unwrap();
+        let h = self.pixels.len();
         self.pixels.par_iter_mut().enumerate().for_each(|(i, row)| {
             let w = row.len();
             row.iter_mut().enumerate().for_each(|(j, pixel)| {
-                let a = map_range(
-                    i,
-                    0,
-                    h,
-                    -self.scale + self.offset.0,
-                    self.scale + self.offset.0,
-                );
-                let b = map_range(
-                    j,
-                    0,
-                    w,
-                    -self.scale + self.offset.1,
-                    self.scale + self.offset.1,
-                );
-
-                *pixel = mandlebrot(a, b, limit);
+                let da = map_range(i, 0, h, -self.scale, self.scale);
+                let db = map_range(j, 0, w, -self.scale, self.scale);
+                *pixel = orbit.iterations(da, db, limit);
             });
         });
     }
 }
 
-fn mandlebrot(a: f64, b: f64, limit: u32) -> u32 {
-    let mut x = a.clone();
-    let mut y = b.clone();
-    let mut iteration = 0;
-    while x * x + y * y < 4.0 && iteration < limit{
-        let xtemp = x * x - y * y + a;
-        y = 2.0 * x * y + b;
-        x = xtemp;
-        iteration += 1;
-    }
-    iteration
-}
-
-fn create_image(model: &Model, frame_number: u32) {
-    let mut image: DynamicImage = DynamicImage::new_rgba8(WIDTH, HEIGHT);
-    let limit = INITIAL_CYCLE_LIMIT as f64 * (frame_number as f64).powf(CYCLE_GROWTH_RATE);
+fn create_image(model: &Model, frame_number: u32) -> RgbaImage {
+    let mut image = RgbaImage::new(WIDTH, HEIGHT);
+    let limit = model.cycle_limit(frame_number);
     for (i, row) in model.pixels.iter().enumerate() {
         for (j, &value) in row.iter().enumerate() {
             if value == limit as u32 {
                 continue;
             }
 
-            let color = hsl(
-                (((value as f64 / limit * 360.0).powf(1.5) % 360.0) / 360.0) as f32,
-                0.5,
-                (value as f64 / limit) as f32,
-            );
-            let color = color.into_lin_srgba();
-            let color = rgba(
-                (color.red * 255.0) as u8,
-                (color.green * 255.0) as u8,
-                (color.blue * 255.0) as u8,
-                (color.alpha * 255.0) as u8,
-            );
+            // Newton fractals take the hue of the root each point settles on,
+            // darker the longer it takes
+            let (r, g, b) = match model.roots[i][j] {
+                Some((x, y)) => color::hsl_to_rgb(
+                    y.atan2(x).to_degrees().rem_euclid(360.0) as f32,
+                    0.6,
+                    (0.6 * (1.0 - value as f64 / limit)) as f32,
+                ),
+                None => color::hsl_to_rgb(
+                    ((value as f64 / limit * 360.0).powf(1.5) % 360.0) as f32,
+                    0.5,
+                    (value as f64 / limit) as f32,
+                ),
+            };
+            let to_byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
 
             image.put_pixel(
                 i as u32,
                 j as u32,
-                nannou::image::Rgba([color.red, color.green, color.blue, color.alpha]),
+                Rgba([to_byte(r), to_byte(g), to_byte(b), 255]),
             );
         }
     }
     image
-        .save(format!("mandelbrot_{}.png", frame_number))
-        .unwrap();
 }
@@ -0,0 +1,216 @@
+// Generations per second of the image GA at its default population size,
+// and iterations per second of each optimizer trying as many candidates per
+// iteration. Criterion reports the throughput as generations/s.
+//
+// The `-before` generations score with copies of the CIE76 and SSIM metrics
+// as they were before the target's Lab and luma values were cached, so the
+// speedup can be measured on any machine.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ga::annealing::{Annealing, Schedule, COOLING, TEMPERATURE};
+use ga::cma_es::{CmaEs, STEP_SIZE};
+use ga::evolution::{Evolution, POPULATION_SIZE};
+use ga::genome::{Pixels, MUTATION_RATE};
+use ga::metric::{FitnessMetric, MetricKind, Target};
+use ga::operators::Operators;
+use ga::optimizer::Optimizer;
+use ga::strategy::Strategy;
+use noc::color::{delta_e, rgb_to_xyz};
+use noc::simulation::Simulation;
+
+const SIZE: u32 = 64;
+
+/// A smooth color gradient to evolve towards.
+fn target() -> Vec<u8> {
+    (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % SIZE, i / SIZE);
+            [(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255]
+        })
+        .collect()
+}
+
+/// CIE76 converting both images to Lab on every evaluation, with `powf`
+/// for the sRGB curve and the cube root, and square rooting each Delta E
+/// only to square it again.
+struct Cie76Before;
+
+fn lab_before(pixel: &[u8]) -> (f32, f32, f32) {
+    let [r, g, b] = [0, 1, 2].map(|i| pixel[i] as f32 / 255.0);
+    let (x, y, z) = rgb_to_xyz(r, g, b);
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.powf(1.0 / 3.0)
+        } else {
+            (7.787 * t) + (16.0 / 116.0)
+        }
+    };
+    let (x, y, z) = (f(x / 95.047), f(y / 100.0), f(z / 108.883));
+    ((116.0 * y) - 16.0, 500.0 * (x - y), 200.0 * (y - z))
+}
+
+impl FitnessMetric for Cie76Before {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        target
+            .pixels
+            .chunks(4)
+            .zip(candidate.chunks(4))
+            .map(|(t, c)| (delta_e(lab_before(t), lab_before(c)) / 100.0).powi(2))
+            .sum::<f32>()
+            / (target.pixels.len() / 4) as f32
+    }
+}
+
+/// SSIM working out the target's luma on every evaluation.
+struct SsimBefore;
+
+fn luma_before(image: &[u8]) -> Vec<f32> {
+    image
+        .chunks(4)
+        .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0)
+        .collect()
+}
+
+fn window_starts(length: usize, window: usize, stride: usize) -> Vec<usize> {
+    let last = length - window;
+    let mut starts: Vec<usize> = (0..=last).step_by(stride).collect();
+    if starts.last() != Some(&last) {
+        starts.push(last);
+    }
+    starts
+}
+
+impl FitnessMetric for SsimBefore {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        const WINDOW: usize = 8;
+        const STRIDE: usize = 4;
+        const C1: f32 = 0.01 * 0.01;
+        const C2: f32 = 0.03 * 0.03;
+
+        let (width, height) = (target.dimensions.0 as usize, target.dimensions.1 as usize);
+        let (window_w, window_h) = (WINDOW.min(width), WINDOW.min(height));
+        let target = luma_before(&target.pixels);
+        let candidate = luma_before(candidate);
+
+        let mut total = 0.0;
+        let mut windows = 0;
+        for y0 in window_starts(height, window_h, STRIDE) {
+            for x0 in window_starts(width, window_w, STRIDE) {
+                let pixels = || {
+                    (y0..y0 + window_h)
+                        .flat_map(move |y| (x0..x0 + window_w).map(move |x| y * width + x))
+                };
+                let n = (window_w * window_h) as f32;
+                let mean_t = pixels().map(|i| target[i]).sum::<f32>() / n;
+                let mean_c = pixels().map(|i| candidate[i]).sum::<f32>() / n;
+                let (mut var_t, mut var_c, mut covariance) = (0.0, 0.0, 0.0);
+                for i in pixels() {
+                    let (dt, dc) = (target[i] - mean_t, candidate[i] - mean_c);
+                    var_t += dt * dt;
+                    var_c += dc * dc;
+                    covariance += dt * dc;
+                }
+                let (var_t, var_c, covariance) = (var_t / n, var_c / n, covariance / n);
+
+                total += ((2.0 * mean_t * mean_c + C1) * (2.0 * covariance + C2))
+                    / ((mean_t.powi(2) + mean_c.powi(2) + C1) * (var_t + var_c + C2));
+                windows += 1;
+            }
+        }
+        (1.0 - total / windows as f32) / 2.0
+    }
+}
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(10);
+    let metrics: [(&str, Box<dyn FitnessMetric>); 4] = [
+        ("cie76-before", Box::new(Cie76Before)),
+        ("cie76", MetricKind::Cie76.metric()),
+        ("ssim-before", Box::new(SsimBefore)),
+        ("ssim", MetricKind::Ssim.metric()),
+    ];
+    for (name, metric) in metrics {
+        let mut evolution = Evolution::<Pixels>::new(
+            Target::new(target(), (SIZE, SIZE)),
+            POPULATION_SIZE,
+            MUTATION_RATE,
+            Default::default(),
+            Operators::default(),
+            metric,
+            1,
+        );
+        group.bench_function(name, |b| {
+            b.iter(|| Simulation::step(&mut evolution, 1.0 / 60.0))
+        });
+    }
+    group.finish();
+}
+
+fn optimizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("optimizer");
+    group.throughput(Throughput::Elements(1));
+    group.sample_size(10);
+    let target = || Target::new(target(), (SIZE, SIZE));
+    let metric = || MetricKind::Cie76.metric();
+    let schedule = Schedule {
+        temperature: TEMPERATURE,
+        cooling: COOLING,
+    };
+    let optimizers: Vec<(&str, Box<dyn Optimizer<Pixels>>)> = vec![
+        (
+            "ga",
+            Box::new(Evolution::new(
+                target(),
+                POPULATION_SIZE,
+                MUTATION_RATE,
+                Default::default(),
+                Operators::default(),
+                metric(),
+                1,
+            )),
+        ),
+        (
+            "es",
+            Box::new(Strategy::new(
+                target(),
+                POPULATION_SIZE,
+                MUTATION_RATE,
+                Default::default(),
+                metric(),
+                1,
+            )),
+        ),
+        (
+            "cma-es",
+            Box::new(CmaEs::new(
+                target(),
+                Some(POPULATION_SIZE),
+                STEP_SIZE,
+                Default::default(),
+                metric(),
+                1,
+            )),
+        ),
+        (
+            "anneal",
+            Box::new(Annealing::new(
+                target(),
+                POPULATION_SIZE,
+                MUTATION_RATE,
+                schedule,
+                Default::default(),
+                metric(),
+                1,
+            )),
+        ),
+    ];
+    for (name, mut optimizer) in optimizers {
+        group.bench_function(name, |b| b.iter(|| optimizer.step()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation, optimizer);
+criterion_main!(benches);
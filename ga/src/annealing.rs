@@ -0,0 +1,145 @@
+use std::time::Instant;
+
+use noc::seed;
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::genome::Genome;
+use crate::metric::{FitnessMetric, Target};
+use crate::optimizer::{fitness, Optimizer};
+use crate::stats::GenerationStats;
+
+pub const TEMPERATURE: f32 = 0.001;
+pub const COOLING: f32 = 0.99;
+
+/// How hot annealing starts and how quickly it cools.
+#[derive(Clone, Copy)]
+pub struct Schedule {
+    pub temperature: f32,
+    pub cooling: f32, // Factor the temperature falls by after each iteration
+}
+
+impl Schedule {
+    /// The schedule of hill climbing, which never accepts a worse genome.
+    pub fn frozen() -> Self {
+        Schedule {
+            temperature: 0.0,
+            cooling: 1.0,
+        }
+    }
+}
+
+/// Simulated annealing: each iteration tries `proposals` mutations of the
+/// current genome, one after another, accepting every one that is at least
+/// as fit and each worse one with a chance of exp(-Δ/T), where Δ is how much
+/// worse it is. The temperature T falls after every iteration. At zero
+/// temperature this is hill climbing.
+pub struct Annealing<G: Genome> {
+    target: Target,
+    metric: Box<dyn FitnessMetric>,
+    settings: G::Settings,
+    current: G,
+    current_fitness: f32,
+    best: G, // The fittest genome accepted so far
+    best_fitness: f32,
+    proposals: usize,
+    mutation_rate: f32,
+    schedule: Schedule, // The current temperature and its cooling
+    generation: usize,
+    stats: Vec<GenerationStats>,
+    rng: StdRng,
+}
+
+impl<G: Genome> Annealing<G> {
+    pub fn new(
+        target: Target,
+        proposals: usize,
+        mutation_rate: f32,
+        schedule: Schedule,
+        settings: G::Settings,
+        metric: Box<dyn FitnessMetric>,
+        seed: u64,
+    ) -> Self {
+        let mut rng = seed::rng(seed);
+        let current = G::random(&settings, target.dimensions, &mut rng);
+        let current_fitness = fitness(metric.as_ref(), &target, &current);
+        Annealing {
+            target,
+            metric,
+            settings,
+            best: current.clone(),
+            best_fitness: current_fitness,
+            current,
+            current_fitness,
+            proposals: proposals.max(1),
+            mutation_rate,
+            schedule,
+            generation: 0,
+            stats: Vec::new(),
+            rng,
+        }
+    }
+}
+
+impl<G: Genome> Optimizer<G> for Annealing<G> {
+    fn step(&mut self) {
+        let start = Instant::now();
+        let mut scores = Vec::with_capacity(self.proposals + 1);
+
+        for _ in 0..self.proposals {
+            let mut candidate = self.current.clone();
+            candidate.mutate(&self.settings, self.mutation_rate, &mut self.rng);
+            let score = fitness(self.metric.as_ref(), &self.target, &candidate);
+            scores.push(score);
+
+            let worsening = score - self.current_fitness;
+            let temperature = self.schedule.temperature;
+            let accept = worsening <= 0.0
+                || (temperature > 0.0 && self.rng.gen::<f32>() < (-worsening / temperature).exp());
+            if accept {
+                self.current = candidate;
+                self.current_fitness = score;
+                if score < self.best_fitness {
+                    self.best = self.current.clone();
+                    self.best_fitness = score;
+                }
+            }
+        }
+        self.schedule.temperature *= self.schedule.cooling;
+
+        // A single genome has no diversity to speak of
+        scores.push(self.best_fitness);
+        self.stats.push(GenerationStats::new(
+            self.generation,
+            &scores,
+            0.0,
+            start.elapsed().as_secs_f64(),
+        ));
+        self.generation += 1;
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn best(&self) -> Option<&G> {
+        Some(&self.best)
+    }
+
+    fn stats(&self) -> &[GenerationStats] {
+        &self.stats
+    }
+
+    fn rescale(&mut self, target: Target) {
+        let (from, to) = (self.target.dimensions, target.dimensions);
+        self.target = target;
+        self.current = self.current.resize(from, to);
+        self.current_fitness = fitness(self.metric.as_ref(), &self.target, &self.current);
+        self.best = self.best.resize(from, to);
+        self.best_fitness = fitness(self.metric.as_ref(), &self.target, &self.best);
+    }
+}
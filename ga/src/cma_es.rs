@@ -0,0 +1,319 @@
+use std::time::Instant;
+
+use nalgebra::{DMatrix, DVector, SymmetricEigen};
+use noc::seed;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand_distr::StandardNormal;
+use rayon::prelude::*;
+
+use crate::diversity;
+use crate::genome::Continuous;
+use crate::metric::{FitnessMetric, Target};
+use crate::optimizer::{fitness, Optimizer};
+use crate::stats::GenerationStats;
+
+pub const STEP_SIZE: f32 = 0.2;
+
+/// Parameters beyond which only the variance of each one is adapted, as in
+/// sep-CMA-ES, since the full covariance matrix grows with their square.
+const FULL_COVARIANCE_LIMIT: usize = 200;
+
+/// The shape of the search distribution.
+enum Covariance {
+    /// A full matrix `c`, kept decomposed as `b * diag(d)^2 * b^T`.
+    Full {
+        c: DMatrix<f64>,
+        b: DMatrix<f64>,
+        d: DVector<f64>,
+        decomposed: usize, // Generation of the last decomposition
+    },
+    /// Only the diagonal of the matrix.
+    Diagonal(DVector<f64>),
+}
+
+/// The learning rates of CMA-ES, which depend only on the number of
+/// parameters and the population size.
+struct Rates {
+    weights: Vec<f64>, // Recombination weights of the fittest half, fittest first
+    mu_eff: f64,
+    cc: f64,
+    cs: f64,
+    c1: f64,
+    cmu: f64,
+    damps: f64,
+    chi_n: f64, // Expected length of a standard normal vector
+}
+
+impl Rates {
+    fn new(n: usize, lambda: usize, diagonal: bool) -> Self {
+        let nf = n as f64;
+        let mu = lambda / 2;
+        let raw: Vec<f64> = (0..mu)
+            .map(|i| (mu as f64 + 0.5).ln() - (i as f64 + 1.0).ln())
+            .collect();
+        let total: f64 = raw.iter().sum();
+        let weights: Vec<f64> = raw.iter().map(|w| w / total).collect();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let cc = (4.0 + mu_eff / nf) / (nf + 4.0 + 2.0 * mu_eff / nf);
+        let cs = (mu_eff + 2.0) / (nf + mu_eff + 5.0);
+        let mut c1 = 2.0 / ((nf + 1.3).powi(2) + mu_eff);
+        let mut cmu =
+            (2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((nf + 2.0).powi(2) + mu_eff)).min(1.0 - c1);
+        if diagonal {
+            // A diagonal matrix has fewer entries to learn, so learns faster
+            c1 = (c1 * (nf + 2.0) / 3.0).min(1.0);
+            cmu = (cmu * (nf + 2.0) / 3.0).min(1.0 - c1);
+        }
+        let damps = 1.0 + 2.0 * (((mu_eff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+        let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+        Rates {
+            weights,
+            mu_eff,
+            cc,
+            cs,
+            c1,
+            cmu,
+            damps,
+            chi_n,
+        }
+    }
+}
+
+/// The covariance matrix adaptation evolution strategy. Each iteration
+/// samples `lambda` parameter vectors from a multivariate normal
+/// distribution, writes them into copies of a template genome, and moves
+/// and reshapes the distribution towards the fittest half of them.
+///
+/// Only a genome's continuous parameters are searched: a painting keeps the
+/// shapes it started with.
+pub struct CmaEs<G: Continuous> {
+    target: Target,
+    metric: Box<dyn FitnessMetric>,
+    template: G, // The genome each sample's parameters are written into
+    best: G,     // The fittest genome sampled so far
+    best_fitness: f32,
+    lambda: usize,
+    rates: Rates,
+    mean: DVector<f64>,
+    sigma: f64, // Overall step size
+    covariance: Covariance,
+    path_c: DVector<f64>,     // Evolution path of the covariance
+    path_sigma: DVector<f64>, // Evolution path of the step size
+    generation: usize,
+    stats: Vec<GenerationStats>,
+    rng: StdRng,
+}
+
+impl<G: Continuous> CmaEs<G> {
+    /// A search starting from a random genome. `lambda` defaults to
+    /// 4 + 3 ln n for n parameters.
+    pub fn new(
+        target: Target,
+        lambda: Option<usize>,
+        step_size: f32,
+        settings: G::Settings,
+        metric: Box<dyn FitnessMetric>,
+        seed: u64,
+    ) -> Self {
+        let mut rng = seed::rng(seed);
+        let template = G::random(&settings, target.dimensions, &mut rng);
+        let best_fitness = fitness(metric.as_ref(), &target, &template);
+        let n = template.parameters().len().max(1);
+        let lambda = lambda
+            .unwrap_or(4 + (3.0 * (n as f64).ln()) as usize)
+            .max(4);
+        let mut cma = CmaEs {
+            target,
+            metric,
+            best: template.clone(),
+            template,
+            best_fitness,
+            lambda,
+            rates: Rates::new(n, lambda, n > FULL_COVARIANCE_LIMIT),
+            mean: DVector::zeros(n),
+            sigma: step_size as f64,
+            covariance: Covariance::Diagonal(DVector::zeros(n)),
+            path_c: DVector::zeros(n),
+            path_sigma: DVector::zeros(n),
+            generation: 0,
+            stats: Vec::new(),
+            rng,
+        };
+        cma.restart();
+        cma
+    }
+
+    // Centre a fresh distribution on the best genome so far, keeping the
+    // step size
+    fn restart(&mut self) {
+        self.template = self.best.clone();
+        let parameters = self.template.parameters();
+        let n = parameters.len().max(1);
+        self.mean = DVector::from_iterator(n, parameters.iter().map(|&p| p as f64));
+        self.rates = Rates::new(n, self.lambda, n > FULL_COVARIANCE_LIMIT);
+        self.covariance = if n > FULL_COVARIANCE_LIMIT {
+            Covariance::Diagonal(DVector::from_element(n, 1.0))
+        } else {
+            Covariance::Full {
+                c: DMatrix::identity(n, n),
+                b: DMatrix::identity(n, n),
+                d: DVector::from_element(n, 1.0),
+                decomposed: self.generation,
+            }
+        };
+        self.path_c = DVector::zeros(n);
+        self.path_sigma = DVector::zeros(n);
+    }
+
+    // A step from the mean drawn from the current distribution, before
+    // scaling by sigma
+    fn sample(&mut self) -> DVector<f64> {
+        let n = self.mean.len();
+        let z = DVector::from_fn(n, |_, _| self.rng.sample::<f64, _>(StandardNormal));
+        match &self.covariance {
+            Covariance::Full { b, d, .. } => b * z.component_mul(d),
+            Covariance::Diagonal(c) => z.component_mul(&c.map(f64::sqrt)),
+        }
+    }
+
+    // The covariance's inverse square root applied to `y`
+    fn whiten(&self, y: &DVector<f64>) -> DVector<f64> {
+        match &self.covariance {
+            Covariance::Full { b, d, .. } => b * (b.transpose() * y).component_div(d),
+            Covariance::Diagonal(c) => y.component_div(&c.map(f64::sqrt)),
+        }
+    }
+}
+
+impl<G: Continuous> Optimizer<G> for CmaEs<G> {
+    fn step(&mut self) {
+        let start = Instant::now();
+        let n = self.mean.len();
+
+        // Sample and score the offspring, scoring them in parallel
+        let steps: Vec<DVector<f64>> = (0..self.lambda).map(|_| self.sample()).collect();
+        let candidates: Vec<G> = steps
+            .par_iter()
+            .map(|y| {
+                let x = &self.mean + y * self.sigma;
+                let parameters: Vec<f32> = x.iter().map(|&v| v as f32).collect();
+                self.template.with_parameters(&parameters)
+            })
+            .collect();
+        let scores: Vec<f32> = candidates
+            .par_iter()
+            .map(|candidate| fitness(self.metric.as_ref(), &self.target, candidate))
+            .collect();
+        let diversity = diversity::diversity(&candidates, self.target.dimensions);
+
+        let mut order: Vec<usize> = (0..self.lambda).collect();
+        order.sort_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap());
+        if scores[order[0]] < self.best_fitness {
+            self.best = candidates[order[0]].clone();
+            self.best_fitness = scores[order[0]];
+        }
+
+        // Move the mean towards the weighted fittest half
+        let rates = &self.rates;
+        let mut y_w = DVector::zeros(n);
+        for (w, &i) in rates.weights.iter().zip(&order) {
+            y_w += &steps[i] * *w;
+        }
+        self.mean += &y_w * self.sigma;
+
+        // Update the evolution paths
+        let whitened = self.whiten(&y_w);
+        let rates = &self.rates;
+        self.path_sigma = &self.path_sigma * (1.0 - rates.cs)
+            + whitened * (rates.cs * (2.0 - rates.cs) * rates.mu_eff).sqrt();
+        let generations = (self.generation + 1) as f64;
+        let stalled = self.path_sigma.norm()
+            / (1.0 - (1.0 - rates.cs).powf(2.0 * generations)).sqrt()
+            / rates.chi_n
+            >= 1.4 + 2.0 / (n as f64 + 1.0);
+        let h_sigma = if stalled { 0.0 } else { 1.0 };
+        self.path_c = &self.path_c * (1.0 - rates.cc)
+            + &y_w * (h_sigma * (rates.cc * (2.0 - rates.cc) * rates.mu_eff).sqrt());
+
+        // Adapt the covariance from the path and the fittest steps
+        let decay =
+            1.0 - rates.c1 - rates.cmu + rates.c1 * (1.0 - h_sigma) * rates.cc * (2.0 - rates.cc);
+        match &mut self.covariance {
+            Covariance::Full {
+                c,
+                b,
+                d,
+                decomposed,
+            } => {
+                let mut rank_mu = DMatrix::zeros(n, n);
+                for (w, &i) in rates.weights.iter().zip(&order) {
+                    rank_mu += &steps[i] * steps[i].transpose() * *w;
+                }
+                *c = &*c * decay
+                    + &self.path_c * self.path_c.transpose() * rates.c1
+                    + rank_mu * rates.cmu;
+
+                // Decompose only every so often, as it costs O(n^3)
+                let interval = (1.0 / (rates.c1 + rates.cmu) / n as f64 / 10.0) as usize;
+                if self.generation - *decomposed >= interval {
+                    *c = (&*c + c.transpose()) * 0.5;
+                    let eigen = SymmetricEigen::new(c.clone());
+                    *b = eigen.eigenvectors;
+                    *d = eigen.eigenvalues.map(|v| v.max(1e-20).sqrt());
+                    *decomposed = self.generation;
+                }
+            }
+            Covariance::Diagonal(c) => {
+                let mut rank_mu = DVector::zeros(n);
+                for (w, &i) in rates.weights.iter().zip(&order) {
+                    rank_mu += steps[i].component_mul(&steps[i]) * *w;
+                }
+                *c = &*c * decay
+                    + self.path_c.component_mul(&self.path_c) * rates.c1
+                    + rank_mu * rates.cmu;
+            }
+        }
+
+        // Lengthen the step size when the path is longer than a random walk
+        // would be, and shorten it when shorter
+        self.sigma *=
+            ((rates.cs / rates.damps) * (self.path_sigma.norm() / rates.chi_n - 1.0)).exp();
+        self.sigma = self.sigma.clamp(1e-10, 1e3);
+
+        let mut scores = scores;
+        scores.push(self.best_fitness);
+        self.stats.push(GenerationStats::new(
+            self.generation,
+            &scores,
+            diversity,
+            start.elapsed().as_secs_f64(),
+        ));
+        self.generation += 1;
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn best(&self) -> Option<&G> {
+        Some(&self.best)
+    }
+
+    fn stats(&self) -> &[GenerationStats] {
+        &self.stats
+    }
+
+    fn rescale(&mut self, target: Target) {
+        self.best = self.best.resize(self.target.dimensions, target.dimensions);
+        self.target = target;
+        self.best_fitness = fitness(self.metric.as_ref(), &self.target, &self.best);
+        // A pixel genome gains parameters, so the distribution starts over
+        self.restart();
+    }
+}
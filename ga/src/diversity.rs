@@ -0,0 +1,190 @@
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::genome::Genome;
+
+// Genomes drawn to measure how varied a population is
+const DIVERSITY_SAMPLE: usize = 32;
+// Cells along each side of a signature's grid
+const SIGNATURE_SIZE: u32 = 8;
+
+/// How much the images of a population differ: the mean distance between
+/// every pair of an even sample of them, from 0 when they are all the same
+/// to 1 when every channel is as far apart as it can be.
+pub fn diversity<G: Genome>(population: &[G], dimensions: (u32, u32)) -> f32 {
+    let step = population.len().div_ceil(DIVERSITY_SAMPLE).max(1);
+    let sample: Vec<_> = population
+        .iter()
+        .step_by(step)
+        .map(|genome| genome.render(dimensions))
+        .collect();
+    let pairs: Vec<(usize, usize)> = (0..sample.len())
+        .flat_map(|i| (i + 1..sample.len()).map(move |j| (i, j)))
+        .collect();
+    if pairs.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = pairs
+        .par_iter()
+        .map(|&(i, j)| pixel_distance(&sample[i], &sample[j]))
+        .sum();
+    (total / pairs.len() as f64) as f32
+}
+
+// Mean absolute difference of the color channels of two images, scaled to
+// 0..1. Alpha is not compared.
+fn pixel_distance(a: &[u8], b: &[u8]) -> f64 {
+    let total: u64 = a
+        .chunks_exact(4)
+        .zip(b.chunks_exact(4))
+        .map(|(p, q)| (0..3).map(|c| p[c].abs_diff(q[c]) as u64).sum::<u64>())
+        .sum();
+    total as f64 / (a.len() / 4 * 3).max(1) as f64 / 255.0
+}
+
+/// A thumbnail of an image for comparing genomes cheaply: the mean color of
+/// each cell of a small grid over it, scaled to 0..1.
+pub fn signature(pixels: &[u8], dimensions: (u32, u32)) -> Vec<f32> {
+    let (width, height) = dimensions;
+    let (columns, rows) = (SIGNATURE_SIZE.min(width), SIGNATURE_SIZE.min(height));
+    let mut cells = vec![[0.0f32; 4]; (columns * rows) as usize];
+    for (i, pixel) in pixels.chunks_exact(4).enumerate() {
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let cell = &mut cells[((y * rows / height) * columns + x * columns / width) as usize];
+        for c in 0..3 {
+            cell[c] += pixel[c] as f32;
+        }
+        cell[3] += 1.0; // Pixels in the cell
+    }
+    cells
+        .iter()
+        .flat_map(|cell| (0..3).map(|c| cell[c] / (cell[3].max(1.0) * 255.0)))
+        .collect()
+}
+
+/// Root mean square difference between two signatures, 0..1.
+pub fn distance(a: &[f32], b: &[f32]) -> f32 {
+    let total: f32 = a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum();
+    (total / a.len().max(1) as f32).sqrt()
+}
+
+/// Fitness scores worsened by how crowded each individual's niche is: each
+/// is multiplied by the sum, over the population, of how close the others
+/// lie within `radius`, counting itself as 1.
+pub fn shared_scores(fitness_scores: &[f32], signatures: &[Vec<f32>], radius: f32) -> Vec<f32> {
+    let radius = radius.max(f32::EPSILON);
+    fitness_scores
+        .par_iter()
+        .zip(signatures)
+        .map(|(&fitness, own)| {
+            let niche_count: f32 = signatures
+                .iter()
+                .map(|other| (1.0 - distance(own, other) / radius).max(0.0))
+                .sum();
+            fitness * niche_count
+        })
+        .collect()
+}
+
+/// Restricted tournament replacement: each child, with its fitness and
+/// signature, replaces the most similar of `window` members of `population`
+/// drawn at random, if the child is fitter.
+pub fn crowd<G, R: Rng>(
+    population: &mut [G],
+    fitness_scores: &mut [f32],
+    signatures: &mut [Vec<f32>],
+    children: Vec<(G, f32, Vec<f32>)>,
+    window: usize,
+    rng: &mut R,
+) {
+    for (child, fitness, signature) in children {
+        let nearest = (0..window.max(1))
+            .map(|_| rng.gen_range(0..population.len()))
+            .min_by(|&a, &b| {
+                let (da, db) = (
+                    distance(&signature, &signatures[a]),
+                    distance(&signature, &signatures[b]),
+                );
+                da.partial_cmp(&db).unwrap()
+            })
+            .unwrap();
+        if fitness < fitness_scores[nearest] {
+            population[nearest] = child;
+            fitness_scores[nearest] = fitness;
+            signatures[nearest] = signature;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::Pixels;
+
+    const DIMENSIONS: (u32, u32) = (4, 4);
+
+    fn solid(value: u8) -> Pixels {
+        [value, value, value, 255].repeat(16)
+    }
+
+    #[test]
+    fn clones_have_no_diversity() {
+        assert_eq!(diversity(&vec![solid(90); 5], DIMENSIONS), 0.0);
+        assert_eq!(diversity(&[solid(0), solid(255)], DIMENSIONS), 1.0);
+    }
+
+    #[test]
+    fn signatures_are_mean_colors_of_cells() {
+        let signature = signature(&solid(255), DIMENSIONS);
+        assert_eq!(signature.len(), 4 * 4 * 3);
+        assert!(signature.iter().all(|&c| c == 1.0));
+        assert_eq!(distance(&signature, &signature), 0.0);
+    }
+
+    #[test]
+    fn shared_scores_penalise_clones() {
+        let signatures: Vec<_> = [solid(10), solid(10), solid(10), solid(240)]
+            .iter()
+            .map(|pixels| signature(pixels, DIMENSIONS))
+            .collect();
+        let shared = shared_scores(&[1.0; 4], &signatures, 0.1);
+        assert_eq!(shared, vec![3.0, 3.0, 3.0, 1.0]);
+    }
+
+    #[test]
+    fn crowding_replaces_the_nearest_only_when_fitter() {
+        let mut rng = noc::seed::rng(3);
+        let mut population = vec![solid(0), solid(255)];
+        let mut scores = vec![1.0, 1.0];
+        let mut signatures: Vec<_> = population
+            .iter()
+            .map(|pixels| signature(pixels, DIMENSIONS))
+            .collect();
+        let child = |value, fitness| {
+            let pixels = solid(value);
+            let signature = signature(&pixels, DIMENSIONS);
+            (pixels, fitness, signature)
+        };
+
+        crowd(
+            &mut population,
+            &mut scores,
+            &mut signatures,
+            vec![child(250, 2.0)],
+            16,
+            &mut rng,
+        );
+        assert_eq!(population, vec![solid(0), solid(255)]);
+        crowd(
+            &mut population,
+            &mut scores,
+            &mut signatures,
+            vec![child(250, 0.5)],
+            16,
+            &mut rng,
+        );
+        assert_eq!(population, vec![solid(0), solid(250)]);
+        assert_eq!(scores, vec![1.0, 0.5]);
+    }
+}
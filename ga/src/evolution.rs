@@ -0,0 +1,428 @@
+use crate::diversity::{self, crowd, shared_scores, signature};
+use crate::genome::Genome;
+use crate::metric::{FitnessMetric, Target};
+use crate::operators::{migrate, select_parents, Niching, Operators};
+use crate::stats::GenerationStats;
+use noc::seed;
+use noc::simulation::Simulation;
+use rand::{rngs::StdRng, Rng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+pub const POPULATION_SIZE: usize = 1000;
+
+/// The GA state, stepped one generation at a time.
+pub struct Evolution<G: Genome> {
+    pub target: Target,     // Store target image and its cached Lab values
+    pub population: Vec<G>, // Store population as genomes, one island after another
+    pub generation: usize,
+    pub fitness_history: Vec<f32>, // Track best fitness over generations
+    pub island_history: Vec<Vec<f32>>, // Best fitness of each island over generations
+    pub stats: Vec<GenerationStats>, // Fitness spread, diversity and timing over generations
+    pub cataclysms: Vec<usize>,    // Generations after which the population was reseeded
+    pub mutation_rate: f32,        // Chance of each gene being changed
+    pub settings: G::Settings,     // Options shared by every genome
+    pub operators: Operators,      // Selection, elitism and mutation rate adaptation
+    pub metric: Box<dyn FitnessMetric>, // Scores each genome against the target
+    best: Option<(G, f32)>,        // Fittest genome of the last generation and its fitness
+    rng: StdRng,                   // Seeds each generation's parallel tasks
+}
+
+impl<G: Genome> Evolution<G> {
+    pub fn new(
+        target: Target,
+        population_size: usize,
+        mutation_rate: f32,
+        settings: G::Settings,
+        operators: Operators,
+        metric: Box<dyn FitnessMetric>,
+        seed: u64,
+    ) -> Self {
+        let mut rng = seed::rng(seed);
+
+        // Initialize the population with random genomes in parallel
+        let population_seed: u64 = rng.gen();
+        let population: Vec<G> = (0..population_size)
+            .into_par_iter()
+            .map(|i| {
+                let mut task_rng = seed::task_rng(population_seed, i as u64);
+                G::random(&settings, target.dimensions, &mut task_rng)
+            })
+            .collect();
+        let islands = operators.islands.ranges(population_size).len();
+
+        Evolution {
+            target,
+            population,
+            generation: 0,
+            fitness_history: Vec::new(), // Initialize an empty fitness history
+            island_history: vec![Vec::new(); islands],
+            stats: Vec::new(),
+            cataclysms: Vec::new(),
+            mutation_rate,
+            settings,
+            operators,
+            metric,
+            best: None,
+            rng,
+        }
+    }
+
+    /// Draw `genome` and score it against the target. Lower is better.
+    pub fn fitness(&self, genome: &G) -> f32 {
+        self.metric
+            .fitness(&self.target, &genome.render(self.target.dimensions))
+    }
+
+    /// Swap in a target of different dimensions, resizing every genome to
+    /// match.
+    pub fn rescale(&mut self, target: Target) {
+        let (from, to) = (self.target.dimensions, target.dimensions);
+        self.population
+            .par_iter_mut()
+            .for_each(|genome| *genome = genome.resize(from, to));
+        if let Some((genome, _)) = &mut self.best {
+            *genome = genome.resize(from, to);
+        }
+        self.target = target;
+    }
+
+    /// The fittest genome of the last generation, or the first genome before
+    /// any has been scored.
+    pub fn best(&self) -> Option<&G> {
+        self.best
+            .as_ref()
+            .map(|(genome, _)| genome)
+            .or(self.population.first())
+    }
+}
+
+/// Everything needed to pick a run up again later, apart from the target
+/// image and the fitness metric.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")] // Genome already requires what serde needs
+pub struct Checkpoint<G: Genome> {
+    pub target_dimensions: (u32, u32),
+    pub population: Vec<G>,
+    pub generation: usize,
+    pub fitness_history: Vec<f32>,
+    pub island_history: Vec<Vec<f32>>,
+    pub stats: Vec<GenerationStats>,
+    pub cataclysms: Vec<usize>,
+    pub mutation_rate: f32,
+    pub settings: G::Settings,
+    pub operators: Operators,
+    #[serde(default)] // Absent from checkpoints saved before it was tracked
+    pub best: Option<(G, f32)>,
+    pub seed: u64, // Seeds the generations that follow
+}
+
+impl<G: Genome> Evolution<G> {
+    /// Capture the run so far. The generator is reseeded from the saved seed,
+    /// so carrying on from here matches resuming from the checkpoint.
+    pub fn checkpoint(&mut self) -> Checkpoint<G> {
+        let seed = self.rng.gen();
+        self.rng = seed::rng(seed);
+        Checkpoint {
+            target_dimensions: self.target.dimensions,
+            population: self.population.clone(),
+            generation: self.generation,
+            fitness_history: self.fitness_history.clone(),
+            island_history: self.island_history.clone(),
+            stats: self.stats.clone(),
+            cataclysms: self.cataclysms.clone(),
+            mutation_rate: self.mutation_rate,
+            settings: self.settings.clone(),
+            operators: self.operators,
+            best: self.best.clone(),
+            seed,
+        }
+    }
+
+    pub fn resume(
+        target: Target,
+        checkpoint: Checkpoint<G>,
+        metric: Box<dyn FitnessMetric>,
+    ) -> Self {
+        Evolution {
+            target,
+            population: checkpoint.population,
+            generation: checkpoint.generation,
+            fitness_history: checkpoint.fitness_history,
+            island_history: checkpoint.island_history,
+            stats: checkpoint.stats,
+            cataclysms: checkpoint.cataclysms,
+            mutation_rate: checkpoint.mutation_rate,
+            settings: checkpoint.settings,
+            operators: checkpoint.operators,
+            metric,
+            best: checkpoint.best,
+            rng: seed::rng(checkpoint.seed),
+        }
+    }
+}
+
+impl<G: Genome> Evolution<G> {
+    // Select parents from an island, sorted fittest first along with its
+    // fitness scores, and create its next generation through crossover and
+    // mutation
+    fn breed(
+        &self,
+        mut population: Vec<G>,
+        mut fitness_scores: Vec<f32>,
+        selection_seed: u64,
+        offspring_seed: u64,
+    ) -> Vec<G> {
+        let dimensions = self.target.dimensions;
+        let signatures = |population: &[G]| -> Vec<Vec<f32>> {
+            population
+                .par_iter()
+                .map(|genome| signature(&genome.render(dimensions), dimensions))
+                .collect()
+        };
+        let next_generation = |parents, elite_count| {
+            create_next_generation(
+                parents,
+                &population,
+                elite_count,
+                self.mutation_rate,
+                &self.settings,
+                dimensions,
+                offspring_seed,
+            )
+        };
+        let selection = self.operators.selection;
+        let elite_count = self.operators.elite_count(population.len());
+
+        match self.operators.niching {
+            None => {
+                let parents =
+                    select_parents(&population, &fitness_scores, selection, selection_seed);
+                next_generation(parents, elite_count)
+            }
+            Some(Niching::Sharing { radius }) => {
+                let shared = shared_scores(&fitness_scores, &signatures(&population), radius);
+                let parents = select_parents(&population, &shared, selection, selection_seed);
+                next_generation(parents, elite_count)
+            }
+            Some(Niching::Crowding { window }) => {
+                // Score the children straight away so they can compete with
+                // the individuals they resemble
+                let parents =
+                    select_parents(&population, &fitness_scores, selection, selection_seed);
+                let children: Vec<(G, f32, Vec<f32>)> = next_generation(parents, 0)
+                    .into_par_iter()
+                    .map(|child| {
+                        let (fitness, child_signature) = {
+                            let pixels = child.render(dimensions);
+                            (
+                                self.metric.fitness(&self.target, &pixels),
+                                signature(&pixels, dimensions),
+                            )
+                        };
+                        (child, fitness, child_signature)
+                    })
+                    .collect();
+                let mut signatures = signatures(&population);
+                let mut rng = seed::task_rng(offspring_seed, population.len() as u64);
+                crowd(
+                    &mut population,
+                    &mut fitness_scores,
+                    &mut signatures,
+                    children,
+                    window,
+                    &mut rng,
+                );
+                population
+            }
+        }
+    }
+
+    // Keep the fittest `survivors` share of an island, sorted fittest first,
+    // and fill the rest of it with random genomes
+    fn reseed(&self, mut population: Vec<G>, survivors: f32, seed: u64) -> Vec<G> {
+        let size = population.len();
+        let keep = ((size as f32 * survivors) as usize).clamp(1, size);
+        population.truncate(keep);
+        population.par_extend((keep..size).into_par_iter().map(|i| {
+            let mut rng = seed::task_rng(seed, i as u64);
+            G::random(&self.settings, self.target.dimensions, &mut rng)
+        }));
+        population
+    }
+}
+
+impl<G: Genome> Simulation for Evolution<G> {
+    /// The current population, island by island, each with its elites
+    /// first.
+    type State = Vec<G>;
+
+    fn step(&mut self, _dt: f32) {
+        let start = Instant::now();
+
+        // Draw each genome and evaluate its fitness in parallel
+        let fitness_scores: Vec<f32> = self
+            .population
+            .par_iter()
+            .map(|candidate| self.fitness(candidate))
+            .collect();
+
+        let diversity = diversity::diversity(&self.population, self.target.dimensions);
+
+        // Sort each island by fitness (lower is better)
+        let mut islands: Vec<(Vec<G>, Vec<f32>)> = self
+            .operators
+            .islands
+            .ranges(self.population.len())
+            .into_iter()
+            .map(|range| {
+                let mut order: Vec<usize> = range.collect();
+                order.sort_by(|&a, &b| fitness_scores[a].partial_cmp(&fitness_scores[b]).unwrap());
+                (
+                    order.iter().map(|&i| self.population[i].clone()).collect(),
+                    order.iter().map(|&i| fitness_scores[i]).collect(),
+                )
+            })
+            .collect();
+
+        // Track the best fitness value of each island and overall, adapting
+        // the mutation rate to whether it improved
+        for (history, (_, scores)) in self.island_history.iter_mut().zip(&islands) {
+            history.push(scores[0]);
+        }
+        let (best_island, best_fitness) = islands
+            .iter()
+            .map(|(_, scores)| scores[0])
+            .enumerate()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .unwrap();
+        self.best = Some((islands[best_island].0[0].clone(), best_fitness));
+        let improved = self
+            .fitness_history
+            .last()
+            .is_none_or(|&previous| best_fitness < previous);
+        self.fitness_history.push(best_fitness);
+        self.mutation_rate = self.operators.adapt(self.mutation_rate, improved);
+
+        // Send the fittest of each island to its neighbours every so often
+        if self.operators.islands.migrates(self.generation) {
+            migrate(&mut islands, &self.operators.islands);
+        }
+
+        let next_generations: Vec<Vec<G>> = match self.operators.cataclysm {
+            // Start most of the population afresh once it has collapsed
+            Some(cataclysm) if diversity < cataclysm.threshold => {
+                self.cataclysms.push(self.generation);
+                let seeds: Vec<u64> = islands.iter().map(|_| self.rng.gen()).collect();
+                islands
+                    .into_par_iter()
+                    .zip(seeds)
+                    .map(|((population, _), seed)| {
+                        self.reseed(population, cataclysm.survivors, seed)
+                    })
+                    .collect()
+            }
+            // Otherwise breed each island's next generation, the islands in
+            // parallel
+            _ => {
+                let seeds: Vec<(u64, u64)> = islands
+                    .iter()
+                    .map(|_| (self.rng.gen(), self.rng.gen()))
+                    .collect();
+                islands
+                    .into_par_iter()
+                    .zip(seeds)
+                    .map(|((population, scores), (selection_seed, offspring_seed))| {
+                        self.breed(population, scores, selection_seed, offspring_seed)
+                    })
+                    .collect()
+            }
+        };
+        self.population = next_generations.concat();
+        self.stats.push(GenerationStats::new(
+            self.generation,
+            &fitness_scores,
+            diversity,
+            start.elapsed().as_secs_f64(),
+        ));
+        self.generation += 1;
+    }
+
+    fn state(&self) -> &Vec<G> {
+        &self.population
+    }
+}
+
+// Create the next generation through crossover and mutation
+// The population must be sorted with the fittest first.
+pub fn create_next_generation<G: Genome>(
+    parents: Vec<&G>,
+    population: &[G],
+    elite_count: usize,
+    mutation_rate: f32,
+    settings: &G::Settings,
+    dimensions: (u32, u32),
+    seed: u64,
+) -> Vec<G> {
+    let population_size = population.len();
+    let mut next_generation = Vec::with_capacity(population_size);
+
+    // Add elites (best individuals) to the next generation
+    next_generation.extend_from_slice(&population[..elite_count]);
+
+    // Fill the rest of the population with offspring
+    let offspring_count = population_size - elite_count;
+    let offspring: Vec<G> = (0..offspring_count)
+        .into_par_iter()
+        .map(|i| {
+            let mut local_rng = seed::task_rng(seed, i as u64);
+            let parent1 = parents[local_rng.gen_range(0..parents.len())];
+            let parent2 = parents[local_rng.gen_range(0..parents.len())];
+
+            let mut child = parent1.crossover(parent2, settings, dimensions, &mut local_rng);
+
+            // Perform mutation
+            child.mutate(settings, mutation_rate, &mut local_rng);
+
+            child
+        })
+        .collect();
+
+    next_generation.extend(offspring);
+    next_generation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::{Pixels, MUTATION_RATE};
+    use crate::metric::MetricKind;
+
+    #[test]
+    fn best_is_the_fittest_without_elites() {
+        let size = 8;
+        let target = Target::new(vec![128; size * size * 4], (size as u32, size as u32));
+        let operators = Operators {
+            elite_fraction: 0.0,
+            ..Default::default()
+        };
+        let mut evolution = Evolution::<Pixels>::new(
+            target,
+            20,
+            MUTATION_RATE,
+            Default::default(),
+            operators,
+            MetricKind::Cie76.metric(),
+            1,
+        );
+        for _ in 0..3 {
+            evolution.step(1.0);
+            let best = evolution.best().unwrap();
+            assert_eq!(
+                evolution.fitness(best),
+                *evolution.fitness_history.last().unwrap()
+            );
+        }
+    }
+}
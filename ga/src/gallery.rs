@@ -0,0 +1,317 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nannou::image::{DynamicImage, RgbaImage};
+use nannou::prelude::*;
+use noc::playback::Playback;
+use noc::seed;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::genome::Genome;
+
+pub const GRID: usize = 3;
+pub const CELL_SIZE: u32 = 192;
+
+const GAP: f32 = 12.0; // Space around each candidate in the window
+const HEADER: f32 = 48.0; // Space above the grid for the instructions
+const REPLAY_TICKS: usize = 30; // Ticks each step of a replay is shown for
+
+/// Everything needed to replay an interactive session: how it started and
+/// which candidates were picked in each round.
+#[derive(Serialize, Deserialize)]
+pub struct History<S> {
+    pub seed: u64,
+    pub candidates: usize,
+    pub mutation_rate: f32,
+    pub dimensions: (u32, u32),
+    pub settings: S,
+    pub rounds: Vec<Vec<usize>>, // Positions in the grid picked in each round
+}
+
+impl<S: Serialize + DeserializeOwned> History<S> {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Evolution steered by hand: the fitness of a candidate is whether the
+/// user picks it. Each round the picked candidates survive unchanged and
+/// the rest of the next round are their mutated children, or all fresh
+/// random genomes if none were picked. Every random choice follows from the
+/// seed, so replaying the picks rebuilds the session exactly.
+pub struct Session<G: Genome> {
+    candidates: Vec<G>,
+    images: Vec<Vec<u8>>, // Each candidate drawn as raw RGBA pixel data
+    picked: Vec<bool>,
+    history: History<G::Settings>,
+    rng: StdRng,
+}
+
+impl<G: Genome> Session<G> {
+    /// A session starting from the first round of `history`. Its rounds are
+    /// not replayed.
+    pub fn new(mut history: History<G::Settings>) -> Self {
+        history.rounds.clear();
+        history.candidates = history.candidates.max(1);
+        let mut rng = seed::rng(history.seed);
+        let candidates = (0..history.candidates)
+            .map(|_| G::random(&history.settings, history.dimensions, &mut rng))
+            .collect();
+        let mut session = Session {
+            candidates,
+            images: Vec::new(),
+            picked: vec![false; history.candidates],
+            history,
+            rng,
+        };
+        session.render();
+        session
+    }
+
+    fn render(&mut self) {
+        let dimensions = self.history.dimensions;
+        self.images = self
+            .candidates
+            .par_iter()
+            .map(|candidate| candidate.render(dimensions).into_owned())
+            .collect();
+    }
+
+    /// Rounds bred so far.
+    pub fn round(&self) -> usize {
+        self.history.rounds.len()
+    }
+
+    pub fn is_picked(&self, index: usize) -> bool {
+        self.picked[index]
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(picked) = self.picked.get_mut(index) {
+            *picked = !*picked;
+        }
+    }
+
+    fn pick(&mut self, picks: &[usize]) {
+        self.picked.fill(false);
+        for &index in picks {
+            self.toggle(index);
+        }
+    }
+
+    /// Positions of the picked candidates.
+    pub fn picks(&self) -> Vec<usize> {
+        (0..self.picked.len()).filter(|&i| self.picked[i]).collect()
+    }
+
+    /// Replace the grid with the next round, bred from the picks.
+    pub fn breed(&mut self) {
+        let picks = self.picks();
+        let parents: Vec<G> = picks.iter().map(|&i| self.candidates[i].clone()).collect();
+        let History {
+            candidates,
+            mutation_rate,
+            dimensions,
+            settings,
+            ..
+        } = &self.history;
+
+        let mut next = parents.clone();
+        while next.len() < *candidates {
+            let child = if parents.is_empty() {
+                G::random(settings, *dimensions, &mut self.rng)
+            } else {
+                let a = &parents[self.rng.gen_range(0..parents.len())];
+                let b = &parents[self.rng.gen_range(0..parents.len())];
+                let mut child = a.crossover(b, settings, *dimensions, &mut self.rng);
+                child.mutate(settings, *mutation_rate, &mut self.rng);
+                child
+            };
+            next.push(child);
+        }
+
+        self.history.rounds.push(picks);
+        self.candidates = next;
+        self.picked.fill(false);
+        self.render();
+    }
+}
+
+/// The window of an interactive session: a grid of candidates to click,
+/// replaying a saved session first if there is one.
+pub struct Gallery<G: Genome> {
+    session: Session<G>,
+    history_path: PathBuf,        // Saved after every round
+    replay: VecDeque<Vec<usize>>, // Rounds still to replay
+    replay_ticks: usize,          // Ticks the current replay step has been shown for
+    textures: Vec<wgpu::Texture>, // Drawn from the session's images when they change
+    stale: bool,                  // Whether the textures are out of date
+    pub playback: Playback,
+}
+
+impl<G: Genome> Gallery<G> {
+    /// A gallery for `session` that first replays `rounds` of picks.
+    pub fn new(session: Session<G>, rounds: Vec<Vec<usize>>, history_path: PathBuf) -> Self {
+        Gallery {
+            session,
+            history_path,
+            replay: rounds.into(),
+            replay_ticks: 0,
+            textures: Vec::new(),
+            stale: true,
+            playback: Playback::default(),
+        }
+    }
+
+    /// The size of window the grid fits in.
+    pub fn window_size(grid: usize, cell_size: u32) -> (u32, u32) {
+        let side = grid as f32 * (cell_size as f32 + GAP) + GAP;
+        (side as u32, (side + HEADER) as u32)
+    }
+
+    fn grid(&self) -> usize {
+        (self.session.history.candidates as f32).sqrt().ceil() as usize
+    }
+
+    // The center of the `index`th candidate in the window
+    fn center(&self, index: usize, window: Rect) -> Point2 {
+        let grid = self.grid();
+        let cell = self.session.history.dimensions.0 as f32 + GAP;
+        let (column, row) = ((index % grid) as f32, (index / grid) as f32);
+        let middle = (grid as f32 - 1.0) / 2.0;
+        pt2(
+            (column - middle) * cell,
+            (middle - row) * cell - HEADER / 2.0 + window.y(),
+        )
+    }
+
+    fn breed(&mut self) {
+        self.session.breed();
+        self.stale = true;
+        if let Err(err) = self.session.history.save(&self.history_path) {
+            eprintln!("failed to save {}: {}", self.history_path.display(), err);
+        }
+    }
+
+    /// Pick or unpick the candidate under `position`, unless a replay is
+    /// running.
+    pub fn mouse_pressed(&mut self, position: Point2, window: Rect) {
+        if !self.replay.is_empty() {
+            return;
+        }
+        let (width, height) = self.session.history.dimensions;
+        let hit = (0..self.session.images.len()).find(|&i| {
+            let offset = position - self.center(i, window);
+            offset.x.abs() <= width as f32 / 2.0 && offset.y.abs() <= height as f32 / 2.0
+        });
+        if let Some(index) = hit {
+            self.session.toggle(index);
+        }
+    }
+
+    /// Return breeds the next round, and Space saves the picked candidates.
+    pub fn key_pressed(&mut self, key: Key) {
+        self.playback.key_pressed(key);
+        if !self.replay.is_empty() {
+            return;
+        }
+        match key {
+            Key::Return => self.breed(),
+            Key::Space => {
+                let (width, height) = self.session.history.dimensions;
+                for index in self.session.picks() {
+                    let path = format!("pick_{}_{}.png", self.session.round(), index);
+                    let image = self.session.images[index].clone();
+                    let saved = RgbaImage::from_raw(width, height, image)
+                        .unwrap()
+                        .save(&path);
+                    match saved {
+                        Ok(()) => println!("saved {}", path),
+                        Err(err) => eprintln!("failed to save {}: {}", path, err),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Advance any replay, showing each round's picks before breeding from
+    /// them, and redraw the textures if the grid changed.
+    pub fn update(&mut self, app: &App) {
+        for _ in 0..self.playback.ticks() {
+            let Some(picks) = self.replay.front() else {
+                break;
+            };
+            self.replay_ticks += 1;
+            if self.replay_ticks == REPLAY_TICKS {
+                let picks = picks.clone();
+                self.session.pick(&picks);
+            } else if self.replay_ticks >= 2 * REPLAY_TICKS {
+                self.replay.pop_front();
+                self.replay_ticks = 0;
+                self.breed();
+            }
+        }
+
+        if self.stale {
+            let (width, height) = self.session.history.dimensions;
+            self.textures = self
+                .session
+                .images
+                .iter()
+                .map(|image| {
+                    let image = RgbaImage::from_raw(width, height, image.clone()).unwrap();
+                    wgpu::Texture::from_image(app, &DynamicImage::ImageRgba8(image))
+                })
+                .collect();
+            self.stale = false;
+        }
+    }
+
+    pub fn view(&self, draw: &Draw, window: Rect) {
+        let status = if self.replay.is_empty() {
+            format!(
+                "Round {}: click to pick, Return to breed, Space to save picks",
+                self.session.round()
+            )
+        } else {
+            format!(
+                "Replaying round {} ({} to go)",
+                self.session.round(),
+                self.replay.len()
+            )
+        };
+        draw.text(&status)
+            .x_y(0.0, window.top() - HEADER / 2.0)
+            .w(window.w())
+            .color(BLACK)
+            .font_size(18);
+
+        let (width, height) = self.session.history.dimensions;
+        for (index, texture) in self.textures.iter().enumerate() {
+            let center = self.center(index, window);
+            draw.texture(texture)
+                .xy(center)
+                .w_h(width as f32, height as f32);
+            if self.session.is_picked(index) {
+                draw.rect()
+                    .xy(center)
+                    .w_h(width as f32 + GAP / 2.0, height as f32 + GAP / 2.0)
+                    .no_fill()
+                    .stroke(ORANGE)
+                    .stroke_weight(GAP / 3.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub const MUTATION_RATE: f32 = 0.001;
+pub const BLOCK_SIZE: u32 = 8;
+pub const MUTATION_SIGMA: f32 = 0.1;
+
+/// A way of encoding a candidate image that the GA can breed. Every genome
+/// is drawn to raw RGBA pixels of the target's size before its fitness is
+/// measured.
+pub trait Genome: Clone + Send + Sync + Serialize + DeserializeOwned {
+    /// Options shared by the whole population, such as which shapes may be
+    /// used.
+    type Settings: Clone + Send + Sync + Serialize + DeserializeOwned;
+
+    /// The default chance of each gene changing when a child is mutated.
+    const MUTATION_RATE: f32;
+
+    fn random<G: Rng>(settings: &Self::Settings, dimensions: (u32, u32), rng: &mut G) -> Self;
+
+    /// The genome drawn as raw RGBA pixel data.
+    fn render(&self, dimensions: (u32, u32)) -> Cow<'_, [u8]>;
+
+    fn crossover<G: Rng>(
+        &self,
+        other: &Self,
+        settings: &Self::Settings,
+        dimensions: (u32, u32),
+        rng: &mut G,
+    ) -> Self;
+
+    /// Change each gene with a chance of `rate`.
+    fn mutate<G: Rng>(&mut self, settings: &Self::Settings, rate: f32, rng: &mut G);
+
+    /// The genome refitted from images of `from` to images of `to`, as the
+    /// GA moves up a resolution pyramid. Genomes drawn independently of the
+    /// image size are unchanged.
+    fn resize(&self, _from: (u32, u32), _to: (u32, u32)) -> Self {
+        self.clone()
+    }
+}
+
+/// A genome whose genes can be read and written as real numbers, for the
+/// optimizers that search a continuous space. Each parameter lies in 0..1
+/// or close to it.
+pub trait Continuous: Genome {
+    fn parameters(&self) -> Vec<f32>;
+
+    /// A copy of the genome with its parameters replaced, in the order
+    /// `parameters` gives them. Values out of range are clamped.
+    fn with_parameters(&self, parameters: &[f32]) -> Self;
+}
+
+/// The original genome: the image itself as raw RGBA pixel data, with each
+/// byte a gene.
+pub type Pixels = Vec<u8>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum PixelCrossover {
+    /// Pixels before a random point come from one parent, the rest from the
+    /// other.
+    Split,
+    /// Each pixel comes from either parent.
+    Uniform,
+    /// Each square block of pixels comes from either parent.
+    Block,
+    /// Each row of pixels comes from either parent.
+    Row,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum PixelMutation {
+    /// A mutated byte is replaced with a random value.
+    Uniform,
+    /// A mutated color channel is nudged by Gaussian noise.
+    Gaussian,
+}
+
+/// The operators a pixel genome is bred with.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PixelSettings {
+    pub crossover: PixelCrossover,
+    pub block_size: u32, // Side of the blocks swapped by block crossover
+    pub mutation: PixelMutation,
+    pub sigma: f32, // Standard deviation of Gaussian mutation, as a fraction of a channel's range
+}
+
+impl Default for PixelSettings {
+    fn default() -> Self {
+        PixelSettings {
+            crossover: PixelCrossover::Split,
+            block_size: BLOCK_SIZE,
+            mutation: PixelMutation::Uniform,
+            sigma: MUTATION_SIGMA,
+        }
+    }
+}
+
+impl Genome for Pixels {
+    type Settings = PixelSettings;
+
+    const MUTATION_RATE: f32 = MUTATION_RATE;
+
+    fn random<G: Rng>(_settings: &PixelSettings, dimensions: (u32, u32), rng: &mut G) -> Self {
+        random_image(dimensions.0, dimensions.1, rng)
+    }
+
+    fn render(&self, _dimensions: (u32, u32)) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+
+    fn crossover<G: Rng>(
+        &self,
+        other: &Self,
+        settings: &PixelSettings,
+        dimensions: (u32, u32),
+        rng: &mut G,
+    ) -> Self {
+        let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+        let mut child_data = self.clone();
+        // Copy the pixels at `range` over from the other parent
+        let mut take_other = |range: std::ops::Range<usize>| {
+            child_data[range.start * 4..range.end * 4]
+                .copy_from_slice(&other[range.start * 4..range.end * 4])
+        };
+
+        match settings.crossover {
+            PixelCrossover::Split => take_other(rng.gen_range(0..=width * height)..width * height),
+            PixelCrossover::Uniform => {
+                for i in 0..width * height {
+                    if rng.gen() {
+                        take_other(i..i + 1);
+                    }
+                }
+            }
+            PixelCrossover::Block => {
+                let size = settings.block_size.max(1) as usize;
+                for y in (0..height).step_by(size) {
+                    for x in (0..width).step_by(size) {
+                        if rng.gen() {
+                            for row in y..(y + size).min(height) {
+                                let start = row * width + x;
+                                take_other(start..start + size.min(width - x));
+                            }
+                        }
+                    }
+                }
+            }
+            PixelCrossover::Row => {
+                for y in 0..height {
+                    if rng.gen() {
+                        take_other(y * width..(y + 1) * width);
+                    }
+                }
+            }
+        }
+        child_data
+    }
+
+    fn mutate<G: Rng>(&mut self, settings: &PixelSettings, rate: f32, rng: &mut G) {
+        match settings.mutation {
+            PixelMutation::Uniform => {
+                for byte in self.iter_mut() {
+                    if rng.gen::<f32>() < rate {
+                        *byte = rng.gen();
+                    }
+                }
+            }
+            PixelMutation::Gaussian => {
+                let noise = Normal::new(0.0, settings.sigma.max(0.0) * 255.0).unwrap();
+                // Alpha stays opaque
+                for channel in self.iter_mut().enumerate().filter(|(i, _)| i % 4 != 3) {
+                    if rng.gen::<f32>() < rate {
+                        let value = *channel.1 as f32 + noise.sample(rng);
+                        *channel.1 = value.round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            }
+        }
+    }
+
+    fn resize(&self, from: (u32, u32), to: (u32, u32)) -> Self {
+        if from == to {
+            return self.clone();
+        }
+        let image = RgbaImage::from_raw(from.0, from.1, self.clone()).expect("Invalid image data");
+        imageops::resize(&image, to.0, to.1, FilterType::Triangle).into_raw()
+    }
+}
+
+/// The color channels of each pixel, scaled to 0..1. Alpha stays opaque.
+impl Continuous for Pixels {
+    fn parameters(&self) -> Vec<f32> {
+        self.iter()
+            .enumerate()
+            .filter(|(i, _)| i % 4 != 3)
+            .map(|(_, &byte)| byte as f32 / 255.0)
+            .collect()
+    }
+
+    fn with_parameters(&self, parameters: &[f32]) -> Self {
+        let mut pixels = self.clone();
+        for (channel, &value) in pixels
+            .iter_mut()
+            .enumerate()
+            .filter(|(i, _)| i % 4 != 3)
+            .map(|(_, channel)| channel)
+            .zip(parameters)
+        {
+            *channel = (value * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+        pixels
+    }
+}
+
+// Generate a random image as a vector of pixel data
+pub fn random_image<G: Rng>(width: u32, height: u32, rng: &mut G) -> Vec<u8> {
+    let block_size = 2; // Define the size of each square block (e.g., 16x16 pixels)
+    let mut image_data = vec![0; (width * height * 4) as usize]; // Resize to the required size
+
+    for y in (0..height).step_by(block_size as usize) {
+        for x in (0..width).step_by(block_size as usize) {
+            // Generate a random RGBA value for the block
+            let r = rng.gen::<u8>();
+            let g = rng.gen::<u8>();
+            let b = rng.gen::<u8>();
+            let a = 255; // Fully opaque
+
+            // Fill the block with the same RGBA value
+            for block_y in 0..block_size {
+                for block_x in 0..block_size {
+                    let pixel_x = x + block_x;
+                    let pixel_y = y + block_y;
+
+                    // Ensure we don't go out of bounds
+                    if pixel_x < width && pixel_y < height {
+                        let index = ((pixel_y * width + pixel_x) * 4) as usize;
+                        image_data[index..index + 4].copy_from_slice(&[r, g, b, a]);
+                    }
+                }
+            }
+        }
+    }
+
+    image_data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIMENSIONS: (u32, u32) = (12, 10);
+
+    fn parents() -> (Pixels, Pixels) {
+        let settings = PixelSettings::default();
+        let mut rng = noc::seed::rng(1);
+        (
+            Pixels::random(&settings, DIMENSIONS, &mut rng),
+            Pixels::random(&settings, DIMENSIONS, &mut rng),
+        )
+    }
+
+    #[test]
+    fn crossover_takes_each_pixel_from_a_parent() {
+        let (a, b) = parents();
+        let modes = [
+            PixelCrossover::Split,
+            PixelCrossover::Uniform,
+            PixelCrossover::Block,
+            PixelCrossover::Row,
+        ];
+        for crossover in modes {
+            let settings = PixelSettings {
+                crossover,
+                block_size: 5, // Doesn't divide the image, leaving partial blocks
+                ..Default::default()
+            };
+            let mut rng = noc::seed::rng(2);
+            let child = a.crossover(&b, &settings, DIMENSIONS, &mut rng);
+            assert_eq!(child.len(), a.len());
+            let from_parent = child
+                .chunks(4)
+                .zip(a.chunks(4).zip(b.chunks(4)))
+                .all(|(c, (a, b))| c == a || c == b);
+            assert!(from_parent);
+        }
+    }
+
+    #[test]
+    fn mutation_rate_bounds_the_changes() {
+        let (original, _) = parents();
+        for mutation in [PixelMutation::Uniform, PixelMutation::Gaussian] {
+            let settings = PixelSettings {
+                mutation,
+                ..Default::default()
+            };
+            let mut rng = noc::seed::rng(3);
+            let mut unchanged = original.clone();
+            unchanged.mutate(&settings, 0.0, &mut rng);
+            assert_eq!(unchanged, original);
+
+            let mut mutated = original.clone();
+            mutated.mutate(&settings, 1.0, &mut rng);
+            assert_eq!(mutated.len(), original.len());
+            assert_ne!(mutated, original);
+        }
+    }
+
+    #[test]
+    fn gaussian_mutation_keeps_alpha_opaque() {
+        let (mut pixels, _) = parents();
+        let settings = PixelSettings {
+            mutation: PixelMutation::Gaussian,
+            sigma: 1.0,
+            ..Default::default()
+        };
+        pixels.mutate(&settings, 1.0, &mut noc::seed::rng(4));
+        assert!(pixels.chunks(4).all(|p| p[3] == 255));
+    }
+
+    #[test]
+    fn resize_fits_the_new_dimensions() {
+        let (pixels, _) = parents();
+        let resized = pixels.resize(DIMENSIONS, (6, 5));
+        assert_eq!(resized.len(), 6 * 5 * 4);
+        assert_eq!(pixels.resize(DIMENSIONS, DIMENSIONS), pixels);
+    }
+
+    #[test]
+    fn parameters_round_trip() {
+        let (pixels, _) = parents();
+        let parameters = pixels.parameters();
+        assert_eq!(parameters.len(), pixels.len() / 4 * 3);
+        assert_eq!(pixels.with_parameters(&parameters), pixels);
+    }
+}
@@ -1,20 +1,467 @@
-mod color_utils;
-
-use color_utils::{delta_e, rgb_to_lab};
-use nannou::image::{DynamicImage, GenericImageView, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use ga::annealing::{Annealing, Schedule, COOLING, TEMPERATURE};
+use ga::cma_es::{CmaEs, STEP_SIZE};
+use ga::evolution::{Checkpoint, Evolution, POPULATION_SIZE};
+use ga::gallery::{Gallery, History, Session, CELL_SIZE, GRID};
+use ga::genome::{
+    Continuous, Genome, PixelCrossover, PixelMutation, PixelSettings, Pixels, BLOCK_SIZE,
+    MUTATION_SIGMA,
+};
+use ga::metric::{MetricTerm, Target};
+use ga::operators::{
+    Adaptation, Cataclysm, Islands, Niching, NichingKind, Operators, Selection, SelectionKind,
+    Topology, CATACLYSM_SURVIVORS, CROWDING_WINDOW, ELITE_FRACTION, MIGRANTS, MIGRATION_INTERVAL,
+    SHARING_RADIUS, TOURNAMENT_SIZE,
+};
+use ga::optimizer::{Optimizer, OptimizerKind};
+use ga::pattern::{Pattern, PatternSettings, PALETTE_SIZE};
+use ga::plot::plot_stats;
+use ga::pyramid::{Pyramid, LEVEL_GENERATIONS};
+use ga::shapes::{Painting, PaintingSettings, ShapeKind, MAX_SHAPES, SHAPE_COUNT};
+use ga::stats::GenerationStats;
+use ga::strategy::Strategy;
+use ga::{input, metric, stats};
+use nannou::color::named::{BLACK, WHITE}; // Explicitly import WHITE from nannou
+use nannou::image::{DynamicImage, RgbaImage};
 use nannou::prelude::*;
-use plotters::prelude::*;
-use nannou::color::named::{WHITE, BLACK}; // Explicitly import WHITE from nannou
-use rand::thread_rng;
-use rand::{rngs::StdRng, Rng, SeedableRng};
-use rayon::prelude::*;
-
-const POPULATION_SIZE: usize = 1000;
-const MUTATION_RATE: f32 = 0.001;
+use noc::playback::Playback;
+use noc::recorder::{RecordArgs, Recorder};
+use noc::seed::{self, SeedArgs};
+use noc::snapshot::{SnapshotArgs, Snapshots};
+
 const TARGET_IMAGE_NAME: &str = "scaled_girl.jpg"; // Path to the target image
+const PLOT_NAME: &str = "fitness_plot.png"; // Where the fitness history is plotted
+const STATS_NAME: &str = "stats.csv"; // Where a headless run logs its statistics
+const PLOT_EVERY: usize = 10; // Generations between updates of the plot
+const RECORD_EVERY: &str = "10"; // Generations between saved best images
+const HISTORY_NAME: &str = "image_gen_history.json"; // Where `--interactive` saves its picks
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum GenomeKind {
+    /// Every pixel of the image is evolved directly.
+    Pixels,
+    /// The image is painted with translucent shapes.
+    Shapes,
+    /// A field of warped noise colored by a palette, for `--interactive`
+    /// art more than for matching a target.
+    Pattern,
+}
+
+/// Evolves a population of images towards a target image.
+#[derive(Parser)]
+struct Args {
+    /// Image the population evolves towards. Transparent pixels count less
+    /// towards the fitness, and fully transparent ones not at all.
+    #[arg(long, value_name = "FILE", default_value = TARGET_IMAGE_NAME)]
+    target: PathBuf,
+
+    /// Shrink the target, keeping its aspect ratio, so that neither side is
+    /// longer than this many pixels.
+    #[arg(long, value_name = "PIXELS")]
+    max_size: Option<u32>,
+
+    /// Grayscale image weighting how much each pixel of the target counts
+    /// towards the fitness, such as white over a face and grey elsewhere.
+    /// Black pixels are ignored. It is stretched to the target's size.
+    #[arg(long, value_name = "FILE")]
+    mask: Option<PathBuf>,
+
+    /// Number of images in each generation, at least 2.
+    #[arg(long, default_value_t = POPULATION_SIZE, value_parser = population_size)]
+    population_size: usize,
+
+    /// How each image is scored against the target: one of rgb-mse, cie76,
+    /// ciede2000, ssim or gradient, or a comma-separated weighted combination
+    /// such as `ssim:0.7,gradient:0.3`.
+    #[arg(long, value_delimiter = ',', default_value = "cie76")]
+    metric: Vec<MetricTerm>,
+
+    /// How each image is encoded.
+    #[arg(long, value_enum, default_value_t = GenomeKind::Pixels)]
+    genome: GenomeKind,
+
+    /// How the images are searched for. Only the GA uses the population,
+    /// selection, island, niching and cataclysm options, and only it can
+    /// save and resume snapshots.
+    #[arg(long, value_enum, default_value_t = OptimizerKind::Ga)]
+    optimizer: OptimizerKind,
+
+    /// Candidates tried each iteration by the non-GA optimizers. Defaults to
+    /// `--population-size`, or to 4 + 3 ln n for CMA-ES over n parameters.
+    #[arg(long)]
+    offspring: Option<usize>,
+
+    /// Starting temperature of `--optimizer anneal`: how much worse, in
+    /// fitness, a mutant can be and still be accepted about a third of the
+    /// time.
+    #[arg(long, default_value_t = TEMPERATURE)]
+    temperature: f32,
+
+    /// Factor the annealing temperature falls by after each iteration.
+    #[arg(long, default_value_t = COOLING)]
+    cooling: f32,
+
+    /// Starting standard deviation of CMA-ES samples, in units of the
+    /// genome's parameters, which mostly range over 0..1.
+    #[arg(long, default_value_t = STEP_SIZE)]
+    step_size: f32,
+
+    /// Chance of each gene of a child changing: a byte of a pixel genome or
+    /// a shape of a shape genome. Defaults to 0.001 for pixels and 0.02 for
+    /// shapes.
+    #[arg(long)]
+    mutation_rate: Option<f32>,
+
+    /// Shrink the mutation rate by 10% after each generation that improves
+    /// on the best fitness and grow it by 10% after each that doesn't,
+    /// staying within a tenth and ten times the starting rate.
+    #[arg(long)]
+    adaptive_mutation: bool,
+
+    /// How parents are picked from each generation.
+    #[arg(long, value_enum, default_value_t = SelectionKind::Tournament)]
+    selection: SelectionKind,
+
+    /// Individuals drawn for each tournament.
+    #[arg(long, default_value_t = TOURNAMENT_SIZE)]
+    tournament_size: usize,
+
+    /// Share of each generation, fittest first, copied into the next one
+    /// unchanged.
+    #[arg(long, default_value_t = ELITE_FRACTION)]
+    elite_fraction: f32,
+
+    /// Number of islands the population is split into. Each evolves on its
+    /// own apart from the migrants it receives.
+    #[arg(long, default_value_t = 1)]
+    islands: usize,
+
+    /// Generations between migrations, or 0 to keep the islands apart.
+    #[arg(long, default_value_t = MIGRATION_INTERVAL)]
+    migration_interval: usize,
+
+    /// Fittest individuals each island sends along each link of the
+    /// topology, replacing the least fit of the island they arrive on.
+    #[arg(long, default_value_t = MIGRANTS)]
+    migrants: usize,
+
+    /// Which islands send migrants to which.
+    #[arg(long, value_enum, default_value_t = Topology::Ring)]
+    topology: Topology,
+
+    /// Keep the population varied: `sharing` worsens the fitness of
+    /// individuals, for selection, by how many similar ones there are, and
+    /// `crowding` has each child replace the most similar of a few random
+    /// individuals if it is fitter.
+    #[arg(long, value_enum)]
+    niching: Option<NichingKind>,
+
+    /// Distance within which `--niching sharing` counts individuals as
+    /// similar, from 0 to 1.
+    #[arg(long, default_value_t = SHARING_RADIUS)]
+    sharing_radius: f32,
+
+    /// Individuals each child is compared against with `--niching crowding`.
+    #[arg(long, default_value_t = CROWDING_WINDOW)]
+    crowding_window: usize,
+
+    /// Reseed the population with random genomes whenever its diversity, as
+    /// logged in the stats, falls below this value.
+    #[arg(long, value_name = "THRESHOLD")]
+    cataclysm: Option<f32>,
+
+    /// Share of each island, fittest first, that survives a cataclysm.
+    #[arg(long, default_value_t = CATACLYSM_SURVIVORS)]
+    cataclysm_survivors: f32,
+
+    /// How a pixel genome's parents are combined.
+    #[arg(long, value_enum, default_value_t = PixelCrossover::Split)]
+    crossover: PixelCrossover,
+
+    /// Side in pixels of the blocks `--crossover block` swaps.
+    #[arg(long, default_value_t = BLOCK_SIZE)]
+    block_size: u32,
+
+    /// How a pixel genome's genes change when mutated.
+    #[arg(long, value_enum, default_value_t = PixelMutation::Uniform)]
+    mutation: PixelMutation,
+
+    /// Standard deviation of `--mutation gaussian`, as a fraction of a
+    /// channel's range.
+    #[arg(long, default_value_t = MUTATION_SIGMA)]
+    mutation_sigma: f32,
+
+    /// Shapes a shape genome may be painted with.
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [ShapeKind::Triangle, ShapeKind::Ellipse, ShapeKind::Stroke])]
+    shapes: Vec<ShapeKind>,
+
+    /// Shapes in each image of the first generation of a shape genome.
+    #[arg(long, default_value_t = SHAPE_COUNT)]
+    shape_count: usize,
+
+    /// Most shapes a shape genome can grow to.
+    #[arg(long, default_value_t = MAX_SHAPES)]
+    max_shapes: usize,
+
+    /// Colors in the palette of a pattern genome.
+    #[arg(long, default_value_t = PALETTE_SIZE)]
+    palette_size: usize,
+
+    /// Levels of the resolution pyramid, each half the size of the next. The
+    /// GA starts on the smallest copy of the target and the population is
+    /// upsampled to each level in turn; 1 evolves at full size throughout.
+    #[arg(long, default_value_t = 1)]
+    levels: usize,
+
+    /// Generations spent on each level of the pyramid below the full-size
+    /// one.
+    #[arg(long, default_value_t = LEVEL_GENERATIONS)]
+    level_generations: usize,
+
+    /// With `--headless`, stop early once the best fitness is at or below
+    /// this value at full size. Lower is better; 0 is a perfect match.
+    #[arg(long)]
+    target_fitness: Option<f32>,
+
+    /// Log the best, mean, median and worst fitness, diversity and timing
+    /// of every generation to this file, as JSON for `.json` files and CSV
+    /// otherwise. Headless runs default to `stats.csv` in `--record-dir`.
+    #[arg(long, value_name = "FILE")]
+    stats: Option<PathBuf>,
+
+    /// Stats files of earlier runs to overlay on the plot for comparison,
+    /// separated by commas.
+    #[arg(long, value_name = "FILE", value_delimiter = ',')]
+    compare: Vec<PathBuf>,
+
+    /// Evolve by eye instead of towards the target: a grid of candidates is
+    /// shown, and clicking picks the ones to breed the next grid from.
+    /// Return breeds, with no picks giving a fresh random grid, and Space
+    /// saves the picked candidates.
+    #[arg(long)]
+    interactive: bool,
+
+    /// Candidates along each side of the `--interactive` grid.
+    #[arg(long, default_value_t = GRID)]
+    grid: usize,
+
+    /// Side in pixels of each candidate in the `--interactive` grid.
+    #[arg(long, default_value_t = CELL_SIZE)]
+    cell_size: u32,
+
+    /// Where `--interactive` saves the seed and picks of every round, as
+    /// JSON, so the session can be replayed.
+    #[arg(long, value_name = "FILE", default_value = HISTORY_NAME)]
+    history: PathBuf,
+
+    /// Replay the `--interactive` session saved in this history file, then
+    /// carry on from where it ended. It must use the same `--genome`; the
+    /// seed, grid and mutation rate are taken from the file.
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    #[command(flatten)]
+    seed: SeedArgs,
+
+    /// A resumed run carries on with the population, generation count,
+    /// fitness history, mutation rate, operators and islands it was saved with, and
+    /// must use the same `--genome`.
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    /// With `--headless`, runs that many generations without a window,
+    /// saving the best image of every `--record-every`th one and the fitness
+    /// plot to `--record-dir`, then prints a summary. Unlike the sketches,
+    /// only every tenth generation is recorded and no animation is made
+    /// unless asked for, as runs are often thousands of generations long.
+    #[command(flatten)]
+    record: RecordArgs,
+}
+
+/// Parse `--population-size`, which needs two genomes to breed from.
+fn population_size(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(size) if size >= 2 => Ok(size),
+        Ok(_) => Err("the population needs at least 2 images".to_string()),
+        Err(_) => Err(format!("invalid population size '{}'", s)),
+    }
+}
+
+impl Args {
+    /// The arguments, with the GA's own recording defaults.
+    fn parse_with_defaults() -> Self {
+        let command = Args::command()
+            .mut_arg("record_every", |arg| arg.default_value(RECORD_EVERY))
+            .mut_arg("animation", |arg| arg.default_value("none"));
+        let args = Args::from_arg_matches(&command.get_matches()).unwrap_or_else(|err| err.exit());
+        if args.shapes.is_empty() {
+            Args::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    "`--shapes` needs at least one kind of shape",
+                )
+                .exit();
+        }
+        args
+    }
+}
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args = Args::parse_with_defaults();
+    match args.genome {
+        GenomeKind::Pixels => run::<Pixels>(&args),
+        GenomeKind::Shapes => run::<Painting>(&args),
+        GenomeKind::Pattern => run::<Pattern>(&args),
+    }
+}
+
+fn run<G: FromArgs>(args: &Args) {
+    if args.interactive {
+        if args.record.headless.is_some() {
+            eprintln!("`--interactive` needs a window, so can't run `--headless`");
+            std::process::exit(1);
+        }
+        nannou::app(gallery::<G>).update(update_gallery::<G>).run();
+        return;
+    }
+    if let Some(generations) = args.record.headless {
+        run_headless::<G>(args, generations);
+        return;
+    }
+    nannou::app(model::<G>)
+        .update(update::<G>)
+        .exit(exit::<G>)
+        .run();
+}
+
+/// A genome whose settings can be read from the command line.
+trait FromArgs: Continuous + 'static {
+    fn settings(args: &Args) -> Self::Settings;
+}
+
+impl FromArgs for Pixels {
+    fn settings(args: &Args) -> PixelSettings {
+        PixelSettings {
+            crossover: args.crossover,
+            block_size: args.block_size,
+            mutation: args.mutation,
+            sigma: args.mutation_sigma,
+        }
+    }
+}
+
+impl FromArgs for Painting {
+    fn settings(args: &Args) -> PaintingSettings {
+        PaintingSettings {
+            kinds: args.shapes.clone(),
+            shape_count: args.shape_count,
+            max_shapes: args.max_shapes.max(1),
+        }
+    }
+}
+
+impl FromArgs for Pattern {
+    fn settings(args: &Args) -> PatternSettings {
+        PatternSettings {
+            palette_size: args.palette_size.max(1),
+        }
+    }
+}
+
+/// The genome independent operators, for a run starting at `mutation_rate`.
+fn operators(args: &Args, mutation_rate: f32) -> Operators {
+    let selection = match args.selection {
+        SelectionKind::Tournament => Selection::Tournament {
+            size: args.tournament_size,
+        },
+        SelectionKind::Roulette => Selection::Roulette,
+        SelectionKind::Rank => Selection::Rank,
+    };
+    let adaptation = args.adaptive_mutation.then(|| Adaptation {
+        min_rate: mutation_rate / 10.0,
+        max_rate: (mutation_rate * 10.0).min(1.0),
+    });
+    Operators {
+        selection,
+        elite_fraction: args.elite_fraction.clamp(0.0, 1.0),
+        adaptation,
+        islands: Islands {
+            count: args.islands.max(1),
+            interval: args.migration_interval,
+            migrants: args.migrants,
+            topology: args.topology,
+        },
+        niching: args.niching.map(|kind| match kind {
+            NichingKind::Sharing => Niching::Sharing {
+                radius: args.sharing_radius,
+            },
+            NichingKind::Crowding => Niching::Crowding {
+                window: args.crowding_window,
+            },
+        }),
+        cataclysm: args.cataclysm.map(|threshold| Cataclysm {
+            threshold,
+            survivors: args.cataclysm_survivors.clamp(0.0, 1.0),
+        }),
+    }
+}
+
+/// A fresh optimizer of the chosen kind, searching towards `target`.
+fn optimizer<G: FromArgs>(args: &Args, target: Target) -> Box<dyn Optimizer<G>> {
+    let mutation_rate = args.mutation_rate.unwrap_or(G::MUTATION_RATE);
+    let offspring = args.offspring.unwrap_or(args.population_size);
+    let settings = G::settings(args);
+    let metric = metric::build(&args.metric);
+    let seed = seed::resolve(args.seed.seed);
+    match args.optimizer {
+        OptimizerKind::Ga => Box::new(Evolution::new(
+            target,
+            args.population_size,
+            mutation_rate,
+            settings,
+            operators(args, mutation_rate),
+            metric,
+            seed,
+        )),
+        OptimizerKind::Es => Box::new(Strategy::new(
+            target,
+            offspring,
+            mutation_rate,
+            settings,
+            metric,
+            seed,
+        )),
+        OptimizerKind::CmaEs => Box::new(CmaEs::new(
+            target,
+            args.offspring,
+            args.step_size,
+            settings,
+            metric,
+            seed,
+        )),
+        OptimizerKind::Anneal | OptimizerKind::HillClimb => {
+            let schedule = match args.optimizer {
+                OptimizerKind::Anneal => Schedule {
+                    temperature: args.temperature,
+                    cooling: args.cooling,
+                },
+                _ => Schedule::frozen(),
+            };
+            Box::new(Annealing::new(
+                target,
+                offspring,
+                mutation_rate,
+                schedule,
+                settings,
+                metric,
+                seed,
+            ))
+        }
+    }
 }
 
 // Calculate the variance of a pixel (example implementation)
@@ -27,125 +474,348 @@ fn main() {
 //         / pixel.len() as f32
 // }
 
-struct Model {
-    target_image: Vec<u8>,         // Store target image as raw pixel data
-    target_dimensions: (u32, u32), // Store dimensions of the target image
-    population: Vec<Vec<u8>>,      // Store population as raw pixel data
-    generation: usize,
-    fitness_history: Vec<f32>,     // Track best fitness over generations
+struct Model<G: Genome> {
+    optimizer: Box<dyn Optimizer<G>>,
+    pyramid: Pyramid,
+    stats_path: Option<PathBuf>,
+    overlays: Vec<(String, Vec<GenerationStats>)>, // Earlier runs to compare against
+    snapshots: Snapshots,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl<G: FromArgs> Model<G> {
+    fn new(args: &Args) -> Self {
+        // Load the target image, and the mask weighting its pixels
+        let target_image = input::load_target(&args.target, args.max_size).unwrap_or_else(|err| {
+            eprintln!(
+                "failed to load target image {}: {}",
+                args.target.display(),
+                err
+            );
+            std::process::exit(1);
+        });
+        let mask = args.mask.as_ref().map(|path| {
+            input::load_mask(path, target_image.dimensions()).unwrap_or_else(|err| {
+                eprintln!("failed to load mask {}: {}", path.display(), err);
+                std::process::exit(1);
+            })
+        });
+        let pyramid = Pyramid::new(
+            &target_image,
+            mask.as_ref(),
+            args.levels,
+            args.level_generations,
+        );
+
+        if args.optimizer != OptimizerKind::Ga
+            && (args.snapshot.resume.is_some() || args.snapshot.snapshot.is_some())
+        {
+            eprintln!("only `--optimizer ga` can save and resume snapshots");
+            std::process::exit(1);
+        }
+        let optimizer: Box<dyn Optimizer<G>> = match args.snapshot.resume::<Checkpoint<G>>() {
+            Some(checkpoint) => {
+                // Pick up on the level the run had reached
+                let target = pyramid.target(pyramid.level(checkpoint.generation)).clone();
+                let target_dimensions = target.dimensions;
+                if checkpoint.target_dimensions != target_dimensions {
+                    eprintln!(
+                        "the snapshot is of {:?} images but {} is evolved at {:?}",
+                        checkpoint.target_dimensions,
+                        args.target.display(),
+                        target_dimensions
+                    );
+                    std::process::exit(1);
+                }
+                Box::new(Evolution::resume(
+                    target,
+                    checkpoint,
+                    metric::build(&args.metric),
+                ))
+            }
+            None => optimizer(args, pyramid.target(0).clone()),
+        };
+
+        let overlays = args
+            .compare
+            .iter()
+            .map(|path| match stats::load(path) {
+                Ok(run) => {
+                    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                    (name.into_owned(), run)
+                }
+                Err(err) => {
+                    eprintln!("failed to read {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            })
+            .collect();
+        let stats_path = args.stats.clone().or_else(|| {
+            args.record
+                .headless
+                .map(|_| args.record.record_dir.join(STATS_NAME))
+        });
+
+        Model {
+            optimizer,
+            pyramid,
+            stats_path,
+            overlays,
+            snapshots: Snapshots::new(&args.snapshot, "image_gen_snapshot.bin"),
+            playback: Playback::default(),
+            recorder: Recorder::new(&args.record),
+        }
+    }
+
+    /// Evolve one generation, moving up the pyramid once the next one is
+    /// due on a higher level. Returns whether it moved.
+    fn step(&mut self) -> bool {
+        self.optimizer.step();
+        let target = self
+            .pyramid
+            .target(self.pyramid.level(self.optimizer.generation()));
+        if target.dimensions == self.optimizer.target().dimensions {
+            return false;
+        }
+        self.optimizer.rescale(target.clone());
+        true
+    }
+
+    /// Plot the run so far to `plot_path` and write its statistics log.
+    fn save_stats(&self, plot_path: &Path) {
+        let island_history = self
+            .optimizer
+            .as_evolution()
+            .map_or(&[][..], |evolution| &evolution.island_history);
+        if let Err(err) = plot_stats(
+            self.optimizer.stats(),
+            island_history,
+            &self.overlays,
+            plot_path,
+        ) {
+            eprintln!("failed to plot {}: {}", plot_path.display(), err);
+        }
+        if let Some(path) = &self.stats_path {
+            if let Err(err) = stats::save(path, self.optimizer.stats()) {
+                eprintln!("failed to write {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    /// Best fitness of the last generation.
+    fn best_fitness(&self) -> Option<f32> {
+        self.optimizer.stats().last().map(|stats| stats.best)
+    }
+
+    // Generations at which the GA's population was reseeded
+    fn cataclysms(&self) -> &[usize] {
+        self.optimizer
+            .as_evolution()
+            .map_or(&[][..], |evolution| &evolution.cataclysms)
+    }
+
+    /// Whether the last generation was scored against the full-size target.
+    fn scored_at_full_size(&self) -> bool {
+        let generation = self.optimizer.generation().saturating_sub(1);
+        self.pyramid.is_top(self.pyramid.level(generation))
+    }
+
+    /// The best image of the current generation at the target's full size,
+    /// as raw RGBA pixel data.
+    fn best_pixels(&self) -> Option<Vec<u8>> {
+        let (from, to) = (
+            self.optimizer.target().dimensions,
+            self.pyramid.full().dimensions,
+        );
+        self.optimizer
+            .best()
+            .map(|genome| genome.resize(from, to).render(to).into_owned())
+    }
+
+    /// The best image of the current generation as an `image` crate buffer,
+    /// ready for the recorder.
+    fn best_frame(&self) -> Option<image::RgbaImage> {
+        let dimensions = self.pyramid.full().dimensions;
+        self.best_pixels()
+            .and_then(|data| image::RgbaImage::from_raw(dimensions.0, dimensions.1, data))
+    }
 }
 
-fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+fn run_headless<G: FromArgs>(args: &Args, generations: u64) {
+    let mut model = Model::<G>::new(args);
+    if let Err(err) = model.recorder.start() {
+        eprintln!(
+            "failed to create {}: {}",
+            args.record.record_dir.display(),
+            err
+        );
+        std::process::exit(1);
+    }
+    let plot_path = args.record.record_dir.join(PLOT_NAME);
+    let first_generation = model.optimizer.generation();
+    let start = Instant::now();
+    let mut reached_target = false;
+
+    for _ in 0..generations {
+        if model.step() {
+            let (width, height) = model.optimizer.target().dimensions;
+            println!(
+                "generation {}: moving up to {}x{}",
+                model.optimizer.generation(),
+                width,
+                height
+            );
+        }
+        let generation = model.optimizer.generation();
+        if model.cataclysms().last() == Some(&(generation - 1)) {
+            println!("generation {}: diversity collapsed, reseeding", generation);
+        }
+        if let Some(best) = model.best_frame() {
+            if let Err(err) = model.recorder.capture(&best) {
+                eprintln!("failed to save generation {}: {}", generation, err);
+            }
+        }
+
+        let best_fitness = model.best_fitness().unwrap();
+        if generation.is_multiple_of(PLOT_EVERY) {
+            println!(
+                "generation {}: best fitness {:.6}",
+                generation, best_fitness
+            );
+            if let Some(evolution) = model
+                .optimizer
+                .as_evolution()
+                .filter(|evolution| evolution.island_history.len() > 1)
+            {
+                let islands: Vec<String> = evolution
+                    .island_history
+                    .iter()
+                    .map(|history| format!("{:.6}", history.last().unwrap()))
+                    .collect();
+                println!("  islands: {}", islands.join(" "));
+            }
+            model.save_stats(&plot_path);
+        }
+        if model.scored_at_full_size()
+            && args
+                .target_fitness
+                .is_some_and(|target| best_fitness <= target)
+        {
+            reached_target = true;
+            break;
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    if let Err(err) = model.recorder.stop() {
+        eprintln!("failed to write animation: {}", err);
+    }
+    model.save_stats(&plot_path);
+    let best_path = args.record.record_dir.join("best.png");
+    if let Some(best) = model.best_frame() {
+        if let Err(err) = best.save(&best_path) {
+            eprintln!("failed to save {}: {}", best_path.display(), err);
+        }
+    }
+    if let Some(evolution) = model.optimizer.as_evolution_mut() {
+        model.snapshots.finish(|| evolution.checkpoint());
+    }
+
+    // Summarise the run
+    let run = model.optimizer.generation() - first_generation;
+    println!(
+        "ran {} generations in {:.1}s ({:.2} generations/s)",
+        run,
+        elapsed,
+        run as f64 / elapsed.max(f64::EPSILON)
+    );
+    match args.target_fitness {
+        Some(target) if reached_target => println!("reached target fitness {}", target),
+        Some(target) => println!("stopped before reaching target fitness {}", target),
+        None => {}
+    }
+    if !model.cataclysms().is_empty() {
+        println!("{} cataclysms", model.cataclysms().len());
+    }
+    if let Some(best_fitness) = model.best_fitness() {
+        println!(
+            "best fitness {:.6} after {} generations",
+            best_fitness,
+            model.optimizer.generation()
+        );
+    }
+    println!("best image: {}", best_path.display());
+    println!("fitness plot: {}", plot_path.display());
+    if let Some(path) = &model.stats_path {
+        println!("stats: {}", path.display());
+    }
+}
+
+fn key_pressed<G: FromArgs>(app: &App, model: &mut Model<G>, key: Key) {
     if key == Key::Space {
         // Save the best image of the current generation
-        if let Some(best_image_data) = model.population.first() {
-            let best_image = vec_to_rgba_image(
-                best_image_data,
-                model.target_dimensions.0,
-                model.target_dimensions.1,
-            );
-            let path = format!("output_{}.png", model.generation);
+        if let Some(best_pixels) = model.best_pixels() {
+            let (width, height) = model.pyramid.full().dimensions;
+            let best_image = vec_to_rgba_image(&best_pixels, width, height);
+            let path = format!("output_{}.png", model.optimizer.generation());
             best_image.save(path).unwrap();
         }
     }
+    if let Some(evolution) = model.optimizer.as_evolution_mut() {
+        model.snapshots.key_pressed(key, || evolution.checkpoint());
+    }
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
 }
 
-fn model(app: &App) -> Model {
+fn model<G: FromArgs>(app: &App) -> Model<G> {
     app.new_window()
-        .view(view)
-        .key_pressed(key_pressed)
+        .view(view::<G>)
+        .key_pressed(key_pressed::<G>)
         .build()
         .unwrap();
 
     // Uncap the frame rate
     app.set_loop_mode(LoopMode::rate_fps(1000.0));
 
-    // Load the target image and convert it to raw pixel data
-    let target_image =
-        nannou::image::open(TARGET_IMAGE_NAME).expect("Failed to load target image");
-    let target_dimensions = target_image.dimensions();
-    let target_image_data = target_image.to_rgba8().into_raw();
-
-    // Initialize the population with random images in parallel
-    let population: Vec<Vec<u8>> = (0..POPULATION_SIZE)
-        .into_par_iter()
-        .map(|_| random_image(target_dimensions.0, target_dimensions.1))
-        .collect();
-
-    Model {
-        target_image: target_image_data,
-        target_dimensions,
-        population,
-        generation: 0,
-        fitness_history: Vec::new(), // Initialize an empty fitness history
-    }
+    Model::new(&Args::parse_with_defaults())
 }
 
-fn update(_app: &App, model: &mut Model, _update: Update) {
-    // Evaluate fitness of each image in the population in parallel
-    let mut fitness_scores: Vec<(usize, f32)> = model
-        .population
-        .par_iter()
-        .enumerate()
-        .map(|(i, candidate)| (i, fitness(&model.target_image, candidate)))
-        .collect();
-
-    // Sort by fitness (lower is better)
-    fitness_scores.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-
-    // Reorder the population based on sorted fitness scores
-    model.population = fitness_scores
-        .iter()
-        .map(|&(i, _)| model.population[i].clone())
-        .collect();
+fn update<G: FromArgs>(app: &App, model: &mut Model<G>, _update: Update) {
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        model.step();
 
-    // Track the best fitness value
-    let best_fitness = fitness_scores.first().unwrap().1;
-    model.fitness_history.push(best_fitness);
-
-    // Select parents based on fitness
-    let binding = fitness_scores.iter().map(|&(_, f)| f).collect::<Vec<_>>();
-    let parents = select_parents(&model.population, &binding);
-
-    // Create the next generation through crossover and mutation
-    model.population = create_next_generation(parents, model.target_dimensions, &fitness_scores, &model.population);
-    model.generation += 1;
-
-    // Plot the fitness history every 10 generations
-    if model.generation % 10 == 0 {
-        plot_fitness_history(&model.fitness_history, model.generation);
+        // Plot the run's statistics every few generations
+        if model.optimizer.generation().is_multiple_of(PLOT_EVERY) {
+            model.save_stats(Path::new(PLOT_NAME));
+        }
     }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+fn view<G: FromArgs>(app: &App, model: &Model<G>, frame: Frame) {
+    let mut draw = app.draw();
     draw.background().color(WHITE);
 
-    if let Some(best_image_data) = model.population.first() {
-        let best_image = vec_to_rgba_image(
-            best_image_data,
-            model.target_dimensions.0,
-            model.target_dimensions.1,
-        );
+    if let Some(best_pixels) = model.best_pixels() {
+        let (width, height) = model.pyramid.full().dimensions;
+        let best_image = vec_to_rgba_image(&best_pixels, width, height);
         let dynamic_image = DynamicImage::ImageRgba8(best_image);
         let texture = wgpu::Texture::from_image(app, &dynamic_image);
-        draw.texture(&texture).w_h(
-            model.target_dimensions.0 as f32,
-            model.target_dimensions.1 as f32,
-        );
+        draw.texture(&texture).w_h(width as f32, height as f32);
     }
 
-    draw.text(&format!("Generation: {}", model.generation))
+    draw.text(&format!("Generation: {}", model.optimizer.generation()))
         .x_y(
             -app.window_rect().w() / 2.0 + 100.0,
             app.window_rect().h() / 2.0 - 24.0,
         )
         .color(BLACK)
         .font_size(32);
-    
+
     // display the fitness of the best image
-    if let Some(best_fitness) = model.population.first() {
-        let fitness_value = fitness(&model.target_image, best_fitness);
+    if let Some(fitness_value) = model.best_fitness() {
         draw.text(&format!("Best Fitness: {:.2}", fitness_value))
             .x_y(
                 -app.window_rect().w() / 2.0 + 100.0,
@@ -155,158 +825,73 @@ fn view(app: &App, model: &Model, frame: Frame) {
             .font_size(32);
     }
 
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
 
-// Generate a random image as a vector of pixel data
-fn random_image(width: u32, height: u32) -> Vec<u8> {
-    let mut rng = thread_rng(); // Use thread-local RNG for better randomness
-    let block_size = 2; // Define the size of each square block (e.g., 16x16 pixels)
-    let mut image_data = vec![0; (width * height * 4) as usize]; // Resize to the required size
-
-    for y in (0..height).step_by(block_size as usize) {
-        for x in (0..width).step_by(block_size as usize) {
-            // Generate a random RGBA value for the block
-            let r = rng.gen::<u8>();
-            let g = rng.gen::<u8>();
-            let b = rng.gen::<u8>();
-            let a = 255; // Fully opaque
-
-            // Fill the block with the same RGBA value
-            for block_y in 0..block_size {
-                for block_x in 0..block_size {
-                    let pixel_x = x + block_x;
-                    let pixel_y = y + block_y;
-
-                    // Ensure we don't go out of bounds
-                    if pixel_x < width && pixel_y < height {
-                        let index = ((pixel_y * width + pixel_x) * 4) as usize;
-                        image_data[index..index + 4].copy_from_slice(&[r, g, b, a]);
-                    }
-                }
-            }
-        }
+fn exit<G: FromArgs>(app: &App, mut model: Model<G>) {
+    model.recorder.finish(app);
+    model.save_stats(Path::new(PLOT_NAME));
+    if let Some(evolution) = model.optimizer.as_evolution_mut() {
+        model.snapshots.finish(|| evolution.checkpoint());
     }
-
-    image_data
-}
-
-// Convert a vector of pixel data back to an RgbaImage
-fn vec_to_rgba_image(data: &[u8], width: u32, height: u32) -> RgbaImage {
-    RgbaImage::from_raw(width, height, data.to_vec()).expect("Invalid image data")
-}
-
-// Calculate the fitness of a candidate image
-fn fitness(target: &[u8], candidate: &[u8]) -> f32 {
-    const MAX_DELTA_E: f32 = 100.0; // Maximum possible Delta E value for normalization
-
-    target
-        .chunks(4) // Each pixel has 4 channels (RGBA)
-        .zip(candidate.chunks(4))
-        .par_bridge() // Use parallel iterator for processing
-        .map(|(t, c)| {
-            let (t_r, t_g, t_b) = (t[0] as f32 / 255.0, t[1] as f32 / 255.0, t[2] as f32 / 255.0);
-            let (c_r, c_g, c_b) = (c[0] as f32 / 255.0, c[1] as f32 / 255.0, c[2] as f32 / 255.0);
-            
-            // Convert RGB to Lab
-            let t_lab = rgb_to_lab(t_r, t_g, t_b);
-            let c_lab = rgb_to_lab(c_r, c_g, c_b);
-
-            // Calculate normalized Delta E
-            let delta = delta_e(t_lab, c_lab) / MAX_DELTA_E;
-
-            // Amplify differences by squaring the normalized Delta E
-            delta.powi(2)
-        })
-        .sum::<f32>()
-        / (target.len() / 4) as f32 // Normalize by the number of pixels
-        // + candidate.chunks(4).map(|pixel| pixel_variance(pixel)).sum::<f32>()
-}
-
-// Select parents based on fitness (tournament selection)
-fn select_parents<'a>(population: &'a [Vec<u8>], fitness_scores: &'a [f32]) -> Vec<&'a Vec<u8>> {
-    let tournament_size = 100;
-
-    (0..POPULATION_SIZE)
-        .into_par_iter() // Parallelize the parent selection
-        .map(|_| {
-            let selected = (0..tournament_size)
-                .map(|_| {
-                    let idx = thread_rng().gen_range(0..population.len());
-                    (idx, fitness_scores[idx])
-                })
-                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-                .unwrap();
-            &population[selected.0]
-        })
-        .collect()
 }
 
-// Create the next generation through crossover and mutation
-fn create_next_generation(parents: Vec<&Vec<u8>>, dimensions: (u32, u32), fitness_scores: &[(usize, f32)], population: &[Vec<u8>]) -> Vec<Vec<u8>> {
-    let elite_count = (POPULATION_SIZE as f32 * 0.05) as usize; // Top 5% of the population
-    let mut next_generation = Vec::with_capacity(POPULATION_SIZE);
-
-    // Add elites (best individuals) to the next generation
-    let elites: Vec<Vec<u8>> = fitness_scores
-        .iter()
-        .take(elite_count)
-        .map(|&(idx, _)| population[idx].clone())
-        .collect();
-    next_generation.extend(elites);
-
-    // Fill the rest of the population with offspring
-    let offspring_count = POPULATION_SIZE - elite_count;
-    let offspring: Vec<Vec<u8>> = (0..offspring_count)
-        .into_par_iter()
-        .map(|_| {
-            let mut local_rng = StdRng::from_entropy();
-            let parent1 = parents[local_rng.gen_range(0..parents.len())];
-            let parent2 = parents[local_rng.gen_range(0..parents.len())];
-
-            let mut child_data = vec![0; (dimensions.0 * dimensions.1 * 4) as usize];
-            let split_point = (dimensions.0 * dimensions.1 * local_rng.gen_range(0..4)) as usize;
-
-            child_data[..split_point].copy_from_slice(&parent1[..split_point]);
-            child_data[split_point..].copy_from_slice(&parent2[split_point..]);
-
-            // Perform mutation
-            for byte in child_data.iter_mut() {
-                if local_rng.gen::<f32>() < MUTATION_RATE {
-                    *byte = local_rng.gen();
-                }
+fn gallery<G: FromArgs>(app: &App) -> Gallery<G> {
+    let args = Args::parse_with_defaults();
+    let (history, rounds) = match &args.replay {
+        Some(path) => match History::load(path) {
+            Ok(history) => {
+                let rounds = history.rounds.clone();
+                (history, rounds)
+            }
+            Err(err) => {
+                eprintln!("failed to read {}: {}", path.display(), err);
+                std::process::exit(1);
             }
+        },
+        None => {
+            let cell_size = args.cell_size.max(1);
+            let history = History {
+                seed: seed::resolve(args.seed.seed),
+                candidates: args.grid.max(1).pow(2),
+                mutation_rate: args.mutation_rate.unwrap_or(G::MUTATION_RATE),
+                dimensions: (cell_size, cell_size),
+                settings: G::settings(&args),
+                rounds: Vec::new(),
+            };
+            (history, Vec::new())
+        }
+    };
 
-            child_data
+    let grid = (history.candidates as f32).sqrt().ceil() as usize;
+    let (width, height) = Gallery::<G>::window_size(grid, history.dimensions.0);
+    app.new_window()
+        .size(width, height)
+        .view(view_gallery::<G>)
+        .key_pressed(|_app, gallery: &mut Gallery<G>, key| gallery.key_pressed(key))
+        .mouse_pressed(|app, gallery: &mut Gallery<G>, _button| {
+            gallery.mouse_pressed(app.mouse.position(), app.window_rect())
         })
-        .collect();
+        .build()
+        .unwrap();
 
-    next_generation.extend(offspring);
-    next_generation
+    Gallery::new(Session::new(history), rounds, args.history)
 }
 
-// Function to plot the fitness history
-fn plot_fitness_history(fitness_history: &[f32], generation: usize) {
-    let root_area = BitMapBackend::new("fitness_plot.png", (800, 600))
-        .into_drawing_area();
-    root_area.fill(&plotters::style::WHITE).unwrap();
-
-    let mut chart = ChartBuilder::on(&root_area)
-        .caption("Fitness Over Generations", ("sans-serif", 30))
-        .margin(10)
-        .x_label_area_size(40)
-        .y_label_area_size(40)
-        .build_cartesian_2d(0f64..generation as f64, 0.0..1.0)
-        .unwrap();
-
-    chart.configure_mesh().draw().unwrap();
+fn update_gallery<G: FromArgs>(app: &App, gallery: &mut Gallery<G>, _update: Update) {
+    gallery.update(app);
+}
 
-    chart
-        .draw_series(LineSeries::new(
-            fitness_history.iter().enumerate().map(|(i, &f)| (i as f64, f as f64)),
-            &plotters::style::RED,
-        ))
-        .unwrap();
+fn view_gallery<G: FromArgs>(app: &App, gallery: &Gallery<G>, frame: Frame) {
+    let mut draw = app.draw();
+    draw.background().color(WHITE);
+    gallery.view(&draw, app.window_rect());
+    gallery.playback.render(&mut draw, app.window_rect());
+    draw.to_frame(app, &frame).unwrap();
+}
 
-    chart.configure_series_labels().background_style(&plotters::style::WHITE).draw().unwrap();
+// Convert a vector of pixel data back to an RgbaImage
+fn vec_to_rgba_image(data: &[u8], width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_raw(width, height, data.to_vec()).expect("Invalid image data")
 }
@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+
+/// The image at `path` as RGBA, shrunk if need be so that neither side is
+/// longer than `max_size`, keeping its aspect ratio.
+pub fn load_target(path: &Path, max_size: Option<u32>) -> Result<RgbaImage, Box<dyn Error>> {
+    let image = image::open(path)?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let longest = width.max(height);
+    match max_size {
+        Some(max_size) if longest > max_size => {
+            let scale = max_size.max(1) as f64 / longest as f64;
+            let scaled = |side: u32| ((side as f64 * scale).round() as u32).max(1);
+            Ok(imageops::resize(
+                &image,
+                scaled(width),
+                scaled(height),
+                FilterType::Lanczos3,
+            ))
+        }
+        _ => Ok(image),
+    }
+}
+
+/// The luma of the image at `path` as an importance mask, stretched to
+/// `dimensions`. White pixels count fully towards the fitness and black ones
+/// not at all.
+pub fn load_mask(path: &Path, dimensions: (u32, u32)) -> Result<GrayImage, Box<dyn Error>> {
+    let mask = image::open(path)?.to_luma8();
+    if mask.dimensions() == dimensions {
+        return Ok(mask);
+    }
+    Ok(imageops::resize(
+        &mask,
+        dimensions.0,
+        dimensions.1,
+        FilterType::Triangle,
+    ))
+}
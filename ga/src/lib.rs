@@ -0,0 +1,16 @@
+pub mod annealing;
+pub mod cma_es;
+pub mod diversity;
+pub mod evolution;
+pub mod gallery;
+pub mod genome;
+pub mod input;
+pub mod metric;
+pub mod operators;
+pub mod optimizer;
+pub mod pattern;
+pub mod plot;
+pub mod pyramid;
+pub mod shapes;
+pub mod stats;
+pub mod strategy;
@@ -0,0 +1,479 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+
+use noc::color::{ciede2000, rgb8_to_lab};
+
+/// Pixels converted and compared as a batch, so the arithmetic can be
+/// vectorised and the running totals stay small enough to be accurate.
+const CHUNK: usize = 64;
+
+type Lab = (f32, f32, f32);
+
+/// The target image, along with the Lab and luma values the metrics compare
+/// against, worked out once rather than on every evaluation.
+///
+/// Each pixel counts towards the fitness in proportion to its weight: its
+/// alpha, times its value in the importance mask if there is one.
+#[derive(Clone)]
+pub struct Target {
+    pub pixels: Vec<u8>, // Raw RGBA pixel data
+    pub dimensions: (u32, u32),
+    lab: Vec<Lab>,
+    luma: Vec<f32>,
+    weights: Option<Vec<f32>>, // None when every pixel counts fully
+    total_weight: f32,
+}
+
+impl Target {
+    pub fn new(pixels: Vec<u8>, dimensions: (u32, u32)) -> Self {
+        let lab = pixels
+            .chunks(4)
+            .map(|p| rgb8_to_lab(p[0], p[1], p[2]))
+            .collect();
+        let luma = luma(&pixels);
+        let weights = pixels
+            .chunks(4)
+            .any(|p| p[3] < u8::MAX)
+            .then(|| pixels.chunks(4).map(|p| p[3] as f32 / 255.0).collect());
+        let mut target = Target {
+            pixels,
+            dimensions,
+            lab,
+            luma,
+            weights: None,
+            total_weight: 0.0,
+        };
+        target.set_weights(weights);
+        target
+    }
+
+    /// The target with each pixel's weight scaled by `mask`, one value from
+    /// 0 to 1 per pixel.
+    pub fn with_mask(mut self, mask: &[f32]) -> Self {
+        let weights = match self.weights.take() {
+            Some(weights) => weights.iter().zip(mask).map(|(w, m)| w * m).collect(),
+            None => mask.to_vec(),
+        };
+        self.set_weights(Some(weights));
+        self
+    }
+
+    fn set_weights(&mut self, weights: Option<Vec<f32>>) {
+        self.total_weight = match &weights {
+            Some(weights) => weights.iter().sum::<f32>().max(f32::EPSILON),
+            None => self.lab.len() as f32,
+        };
+        self.weights = weights;
+    }
+
+    /// How much pixel `i` counts towards the fitness.
+    fn weight(&self, i: usize) -> f32 {
+        self.weights.as_ref().map_or(1.0, |weights| weights[i])
+    }
+}
+
+/// How far a candidate image is from the target. The candidate is raw RGBA
+/// pixel data of the target's size; lower is better and 0 is a perfect
+/// match. Every metric scales its result to roughly 0..1 so they can be
+/// weighted against each other.
+pub trait FitnessMetric: Send + Sync {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32;
+}
+
+/// Mean squared error of the RGB channels.
+pub struct RgbMse;
+
+/// Squared CIE76 Delta E in Lab, the GA's original metric.
+pub struct Cie76;
+
+/// Squared CIEDE2000 difference in Lab.
+pub struct Ciede2000;
+
+/// Structural similarity of the luma of the two images, compared over
+/// overlapping windows.
+pub struct Ssim;
+
+/// Difference between the luma gradients of the two images, which rewards
+/// matching edges over matching flat color.
+pub struct Gradient;
+
+/// A weighted average of other metrics.
+pub struct Weighted {
+    terms: Vec<(f32, Box<dyn FitnessMetric>)>,
+}
+
+impl FitnessMetric for RgbMse {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        if let Some(weights) = &target.weights {
+            let total: f32 = target
+                .pixels
+                .chunks_exact(4)
+                .zip(candidate.chunks_exact(4))
+                .zip(weights)
+                .map(|((t, c), weight)| {
+                    let error: i32 = (0..3).map(|i| (t[i] as i32 - c[i] as i32).pow(2)).sum();
+                    error as f32 * weight
+                })
+                .sum();
+            return total / (255.0 * 255.0 * 3.0 * target.total_weight);
+        }
+        let total: u64 = target
+            .pixels
+            .chunks(CHUNK * 4)
+            .zip(candidate.chunks(CHUNK * 4))
+            .map(|(t, c)| {
+                // Integer differences, skipping every alpha channel
+                t.iter()
+                    .zip(c)
+                    .enumerate()
+                    .filter(|(i, _)| i % 4 != 3)
+                    .map(|(_, (&t, &c))| (t as i32 - c as i32).pow(2) as u64)
+                    .sum::<u64>()
+            })
+            .sum();
+        total as f32 / (255.0 * 255.0 * 3.0 * target.lab.len() as f32)
+    }
+}
+
+const MAX_DELTA_E: f32 = 100.0; // Maximum possible Delta E value for normalization
+
+/// The weighted mean over all pixels of `error`, given each pixel's Lab
+/// value in the target and the candidate.
+fn lab_error(target: &Target, candidate: &[u8], error: impl Fn(Lab, Lab) -> f32) -> f32 {
+    let total: f32 = target
+        .lab
+        .chunks(CHUNK)
+        .zip(candidate.chunks(CHUNK * 4)) // Each pixel has 4 channels (RGBA)
+        .enumerate()
+        .map(|(chunk, (t, c))| {
+            // Convert a batch of the candidate to Lab, then compare it
+            let mut lab = [(0.0, 0.0, 0.0); CHUNK];
+            for (lab, p) in lab.iter_mut().zip(c.chunks_exact(4)) {
+                *lab = rgb8_to_lab(p[0], p[1], p[2]);
+            }
+            t.iter()
+                .zip(&lab)
+                .enumerate()
+                .map(|(i, (&t, &c))| error(t, c) * target.weight(chunk * CHUNK + i))
+                .sum::<f32>()
+        })
+        .sum();
+    total / target.total_weight // Normalize by the pixels' total weight
+}
+
+impl FitnessMetric for Cie76 {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        // The squared Delta E needs no square root
+        lab_error(target, candidate, |t, c| {
+            ((t.0 - c.0).powi(2) + (t.1 - c.1).powi(2) + (t.2 - c.2).powi(2))
+                / (MAX_DELTA_E * MAX_DELTA_E)
+        })
+    }
+}
+
+impl FitnessMetric for Ciede2000 {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        lab_error(target, candidate, |t, c| {
+            (ciede2000(t, c) / MAX_DELTA_E).powi(2)
+        })
+    }
+}
+
+/// Rec. 601 luma of each pixel, from 0 to 1.
+fn luma(image: &[u8]) -> Vec<f32> {
+    image
+        .chunks(4)
+        .map(|p| (0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32) / 255.0)
+        .collect()
+}
+
+/// Start positions of `window` sized windows `stride` apart along an axis of
+/// `length`, with the last one flush against the end.
+fn window_starts(length: usize, window: usize, stride: usize) -> Vec<usize> {
+    let last = length - window;
+    let mut starts: Vec<usize> = (0..=last).step_by(stride).collect();
+    if starts.last() != Some(&last) {
+        starts.push(last);
+    }
+    starts
+}
+
+impl FitnessMetric for Ssim {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        const WINDOW: usize = 8;
+        const STRIDE: usize = 4;
+        // Stabilise the division for flat windows, for a dynamic range of 1
+        const C1: f32 = 0.01 * 0.01;
+        const C2: f32 = 0.03 * 0.03;
+
+        let (width, height) = (target.dimensions.0 as usize, target.dimensions.1 as usize);
+        let (window_w, window_h) = (WINDOW.min(width), WINDOW.min(height));
+        let weights = target.weights.as_deref();
+        let target = &target.luma;
+        let candidate = luma(candidate);
+
+        // Each window counts by the mean weight of its pixels
+        let mut total = 0.0;
+        let mut total_weight = 0.0;
+        for y0 in window_starts(height, window_h, STRIDE) {
+            for x0 in window_starts(width, window_w, STRIDE) {
+                let pixels = || {
+                    (y0..y0 + window_h)
+                        .flat_map(move |y| (x0..x0 + window_w).map(move |x| y * width + x))
+                };
+                let n = (window_w * window_h) as f32;
+                let (sum_t, sum_c) =
+                    pixels().fold((0.0, 0.0), |(t, c), i| (t + target[i], c + candidate[i]));
+                let (mean_t, mean_c) = (sum_t / n, sum_c / n);
+                let (mut var_t, mut var_c, mut covariance) = (0.0, 0.0, 0.0);
+                for i in pixels() {
+                    let (dt, dc) = (target[i] - mean_t, candidate[i] - mean_c);
+                    var_t += dt * dt;
+                    var_c += dc * dc;
+                    covariance += dt * dc;
+                }
+                let (var_t, var_c, covariance) = (var_t / n, var_c / n, covariance / n);
+
+                // Unweighted targets skip the extra pass over the window
+                let window_weight =
+                    weights.map_or(1.0, |weights| pixels().map(|i| weights[i]).sum::<f32>() / n);
+                total += window_weight * ((2.0 * mean_t * mean_c + C1) * (2.0 * covariance + C2))
+                    / ((mean_t.powi(2) + mean_c.powi(2) + C1) * (var_t + var_c + C2));
+                total_weight += window_weight;
+            }
+        }
+
+        // SSIM runs from -1 to 1, with 1 for identical images
+        (1.0 - total / total_weight.max(f32::EPSILON)) / 2.0
+    }
+}
+
+impl FitnessMetric for Gradient {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        let (width, height) = (target.dimensions.0 as usize, target.dimensions.1 as usize);
+        let weight = |i, j| (target.weight(i) + target.weight(j)) / 2.0;
+        let target = &target.luma;
+        let candidate = luma(candidate);
+
+        // Forward differences, compared horizontally and vertically
+        let difference = |i: usize, j: usize| {
+            let t = target[j] - target[i];
+            let c = candidate[j] - candidate[i];
+            // Each gradient lies in -1..1, so their difference is at most 2
+            ((t - c) / 2.0).powi(2) * weight(i, j)
+        };
+        let mut total = 0.0;
+        let mut total_weight = 0.0;
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                if x + 1 < width {
+                    total += difference(i, i + 1);
+                    total_weight += weight(i, i + 1);
+                }
+                if y + 1 < height {
+                    total += difference(i, i + width);
+                    total_weight += weight(i, i + width);
+                }
+            }
+        }
+        total / total_weight.max(f32::EPSILON)
+    }
+}
+
+impl Weighted {
+    pub fn new(terms: Vec<(f32, Box<dyn FitnessMetric>)>) -> Self {
+        Weighted { terms }
+    }
+}
+
+impl FitnessMetric for Weighted {
+    fn fitness(&self, target: &Target, candidate: &[u8]) -> f32 {
+        let total_weight: f32 = self.terms.iter().map(|(weight, _)| weight).sum();
+        self.terms
+            .iter()
+            .map(|(weight, metric)| weight * metric.fitness(target, candidate))
+            .sum::<f32>()
+            / total_weight
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MetricKind {
+    RgbMse,
+    Cie76,
+    Ciede2000,
+    Ssim,
+    Gradient,
+}
+
+impl MetricKind {
+    pub fn metric(self) -> Box<dyn FitnessMetric> {
+        match self {
+            MetricKind::RgbMse => Box::new(RgbMse),
+            MetricKind::Cie76 => Box::new(Cie76),
+            MetricKind::Ciede2000 => Box::new(Ciede2000),
+            MetricKind::Ssim => Box::new(Ssim),
+            MetricKind::Gradient => Box::new(Gradient),
+        }
+    }
+}
+
+/// One metric of a combination and its weight, written `name` or
+/// `name:weight`.
+#[derive(Clone, Copy)]
+pub struct MetricTerm {
+    pub kind: MetricKind,
+    pub weight: f32,
+}
+
+impl FromStr for MetricTerm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (name, weight) = match s.split_once(':') {
+            Some((name, weight)) => {
+                let weight: f32 = weight
+                    .parse()
+                    .map_err(|_| format!("invalid weight '{}'", weight))?;
+                if !weight.is_finite() || weight <= 0.0 {
+                    return Err(format!("weight '{}' must be positive", weight));
+                }
+                (name, weight)
+            }
+            None => (s, 1.0),
+        };
+        let kind = MetricKind::from_str(name, true)?;
+        Ok(MetricTerm { kind, weight })
+    }
+}
+
+/// The metric a list of terms describes: a single metric on its own, or a
+/// weighted combination of several.
+pub fn build(terms: &[MetricTerm]) -> Box<dyn FitnessMetric> {
+    match terms {
+        [term] => term.kind.metric(),
+        _ => Box::new(Weighted::new(
+            terms
+                .iter()
+                .map(|term| (term.weight, term.kind.metric()))
+                .collect(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 16;
+    const KINDS: [MetricKind; 5] = [
+        MetricKind::RgbMse,
+        MetricKind::Cie76,
+        MetricKind::Ciede2000,
+        MetricKind::Ssim,
+        MetricKind::Gradient,
+    ];
+
+    /// Colour gradients with a square in the middle, so there is structure
+    /// and edges to compare.
+    fn image() -> Vec<u8> {
+        (0..SIZE * SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % SIZE, i / SIZE);
+                let inside = (4..12).contains(&x) && (4..12).contains(&y);
+                let r = (x * 255 / SIZE) as u8;
+                let g = (y * 255 / SIZE) as u8;
+                let b = if inside { 200 } else { 40 };
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+
+    /// `image` with noise of up to `amount` added to the pixels whose column
+    /// is in `columns`.
+    fn distort(image: &[u8], amount: i32, columns: std::ops::Range<usize>) -> Vec<u8> {
+        let mut image = image.to_vec();
+        for (i, pixel) in image.chunks_mut(4).enumerate() {
+            if !columns.contains(&(i % SIZE)) {
+                continue;
+            }
+            for (channel, value) in pixel[..3].iter_mut().enumerate() {
+                let noise = ((i * 7 + channel * 13) % 11) as i32 - 5;
+                *value = (*value as i32 + noise * amount / 5).clamp(0, 255) as u8;
+            }
+        }
+        image
+    }
+
+    fn target() -> Target {
+        Target::new(image(), (SIZE as u32, SIZE as u32))
+    }
+
+    #[test]
+    fn identical_images_score_zero() {
+        let target = target();
+        for kind in KINDS {
+            let fitness = kind.metric().fitness(&target, &target.pixels);
+            assert!(fitness.abs() < 1e-5, "{}", fitness);
+        }
+    }
+
+    #[test]
+    fn more_distortion_scores_worse() {
+        let target = target();
+        let slight = distort(&target.pixels, 10, 0..SIZE);
+        let heavy = distort(&target.pixels, 60, 0..SIZE);
+        for kind in KINDS {
+            let metric = kind.metric();
+            let (slight, heavy) = (
+                metric.fitness(&target, &slight),
+                metric.fitness(&target, &heavy),
+            );
+            assert!(0.0 < slight && slight < heavy, "{} !< {}", slight, heavy);
+        }
+    }
+
+    #[test]
+    fn masked_out_pixels_do_not_count() {
+        // Differences only in the first four columns, with the first eight
+        // masked out so no SSIM window or gradient reaches them
+        let mask: Vec<f32> = (0..SIZE * SIZE)
+            .map(|i| if i % SIZE < 8 { 0.0 } else { 1.0 })
+            .collect();
+        let target = target();
+        let masked = target.clone().with_mask(&mask);
+        let candidate = distort(&target.pixels, 60, 0..4);
+        for kind in KINDS {
+            let metric = kind.metric();
+            assert!(metric.fitness(&target, &candidate) > 0.0);
+            assert!(metric.fitness(&masked, &candidate).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn weighted_averages_its_terms() {
+        let target = target();
+        let candidate = distort(&target.pixels, 30, 0..SIZE);
+        let terms: Vec<MetricTerm> = ["ssim:3", "rgb-mse"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let ssim = Ssim.fitness(&target, &candidate);
+        let mse = RgbMse.fitness(&target, &candidate);
+        let weighted = build(&terms).fitness(&target, &candidate);
+        assert!((weighted - (3.0 * ssim + mse) / 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn parses_metric_terms() {
+        let term: MetricTerm = "ssim:0.5".parse().unwrap();
+        assert!(term.kind == MetricKind::Ssim && term.weight == 0.5);
+        let term: MetricTerm = "CIE76".parse().unwrap();
+        assert!(term.kind == MetricKind::Cie76 && term.weight == 1.0);
+        for invalid in ["ssim:0", "ssim:-1", "ssim:abc", "ssim:inf", "psnr"] {
+            assert!(invalid.parse::<MetricTerm>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::ops::Range;
+
+use clap::ValueEnum;
+use noc::seed;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const TOURNAMENT_SIZE: usize = 100;
+pub const ELITE_FRACTION: f32 = 0.05;
+pub const MIGRATION_INTERVAL: usize = 20;
+pub const MIGRANTS: usize = 5;
+pub const SHARING_RADIUS: f32 = 0.1;
+pub const CROWDING_WINDOW: usize = 20;
+pub const CATACLYSM_SURVIVORS: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SelectionKind {
+    Tournament,
+    Roulette,
+    Rank,
+}
+
+/// How parents are picked from a generation.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Selection {
+    /// The fittest of `size` individuals drawn at random.
+    Tournament { size: usize },
+    /// Drawn with a chance proportional to the inverse of their fitness.
+    Roulette,
+    /// Drawn with a chance proportional to their rank, so the gap between
+    /// fitness values doesn't matter.
+    Rank,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NichingKind {
+    Sharing,
+    Crowding,
+}
+
+/// Ways of keeping a population from collapsing onto copies of its fittest
+/// member. Similarity is measured on small thumbnails of each image.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Niching {
+    /// Each individual's fitness is worsened, for selection only, by how
+    /// many others lie within `radius` of it.
+    Sharing { radius: f32 },
+    /// Each child replaces the most similar of `window` individuals drawn
+    /// at random, if it is fitter. Children are scored as they are bred,
+    /// so each generation is evaluated twice.
+    Crowding { window: usize },
+}
+
+/// Reseed all but the fittest `survivors` share of the population with
+/// random genomes whenever its diversity falls below `threshold`.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Cataclysm {
+    pub threshold: f32,
+    pub survivors: f32,
+}
+
+/// Which islands send their migrants to which.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum Topology {
+    /// Each island sends to the next, the last wrapping round to the first.
+    Ring,
+    /// Every island sends to every other.
+    Full,
+}
+
+impl Topology {
+    /// Whether island `from` sends migrants to island `to`, out of `count`.
+    pub fn connects(self, from: usize, to: usize, count: usize) -> bool {
+        match self {
+            Topology::Ring => to == (from + 1) % count,
+            Topology::Full => from != to,
+        }
+    }
+}
+
+/// Sub-populations evolved apart, swapping their fittest individuals every
+/// so often.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Islands {
+    pub count: usize,
+    pub interval: usize, // Generations between migrations, 0 for none
+    pub migrants: usize, // Individuals each island sends along each link
+    pub topology: Topology,
+}
+
+impl Default for Islands {
+    fn default() -> Self {
+        Islands {
+            count: 1,
+            interval: MIGRATION_INTERVAL,
+            migrants: MIGRANTS,
+            topology: Topology::Ring,
+        }
+    }
+}
+
+impl Islands {
+    /// Where each island lies in a population of `population_size`, as even
+    /// as the size allows.
+    pub fn ranges(&self, population_size: usize) -> Vec<Range<usize>> {
+        let count = self.count.clamp(1, population_size.max(1));
+        let (size, extra) = (population_size / count, population_size % count);
+        (0..count)
+            .scan(0, |start, i| {
+                let end = *start + size + usize::from(i < extra);
+                let range = *start..end;
+                *start = end;
+                Some(range)
+            })
+            .collect()
+    }
+
+    /// Whether migration follows the evaluation of `generation`.
+    pub fn migrates(&self, generation: usize) -> bool {
+        self.count > 1 && self.interval > 0 && (generation + 1).is_multiple_of(self.interval)
+    }
+}
+
+/// Bounds for a mutation rate that shrinks while the GA is improving and
+/// grows while it is stuck.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Adaptation {
+    pub min_rate: f32,
+    pub max_rate: f32,
+}
+
+/// The parts of the GA that don't depend on the genome.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Operators {
+    pub selection: Selection,
+    pub elite_fraction: f32, // Share of each generation copied over unchanged
+    pub adaptation: Option<Adaptation>,
+    pub islands: Islands,
+    pub niching: Option<Niching>,
+    pub cataclysm: Option<Cataclysm>,
+}
+
+impl Default for Operators {
+    fn default() -> Self {
+        Operators {
+            selection: Selection::Tournament {
+                size: TOURNAMENT_SIZE,
+            },
+            elite_fraction: ELITE_FRACTION,
+            adaptation: None,
+            islands: Islands::default(),
+            niching: None,
+            cataclysm: None,
+        }
+    }
+}
+
+impl Operators {
+    /// How many of `population_size` individuals survive unchanged.
+    pub fn elite_count(&self, population_size: usize) -> usize {
+        ((population_size as f32 * self.elite_fraction) as usize).min(population_size)
+    }
+
+    /// The mutation rate for the next generation, given whether the best
+    /// fitness just improved.
+    pub fn adapt(&self, mutation_rate: f32, improved: bool) -> f32 {
+        match self.adaptation {
+            Some(Adaptation { min_rate, max_rate }) => {
+                let factor = if improved { 0.9 } else { 1.1 };
+                (mutation_rate * factor).clamp(min_rate, max_rate)
+            }
+            None => mutation_rate,
+        }
+    }
+}
+
+// Select parents based on fitness, `fitness_scores` matching `population`
+pub fn select_parents<'a, G: Sync>(
+    population: &'a [G],
+    fitness_scores: &[f32],
+    selection: Selection,
+    seed: u64,
+) -> Vec<&'a G> {
+    // Running totals of each individual's chance of being drawn
+    let cumulative: Vec<f64> = match selection {
+        Selection::Tournament { .. } => Vec::new(),
+        Selection::Roulette => fitness_scores
+            .iter()
+            .scan(0.0, |total, &f| {
+                *total += 1.0 / (f as f64 + 1e-6); // Lower is better
+                Some(*total)
+            })
+            .collect(),
+        Selection::Rank => {
+            let mut order: Vec<usize> = (0..population.len()).collect();
+            order.sort_by(|&a, &b| fitness_scores[a].partial_cmp(&fitness_scores[b]).unwrap());
+            let mut weights = vec![0.0; population.len()];
+            for (rank, &i) in order.iter().enumerate() {
+                weights[i] = (population.len() - rank) as f64;
+            }
+            weights
+                .iter()
+                .scan(0.0, |total, &weight| {
+                    *total += weight;
+                    Some(*total)
+                })
+                .collect()
+        }
+    };
+
+    (0..population.len())
+        .into_par_iter() // Parallelize the parent selection
+        .map(|i| {
+            // Each draw gets its own generator so the result doesn't depend
+            // on thread scheduling
+            let mut rng = seed::task_rng(seed, i as u64);
+            let index = match selection {
+                Selection::Tournament { size } => {
+                    (0..size.max(1))
+                        .map(|_| {
+                            let idx = rng.gen_range(0..population.len());
+                            (idx, fitness_scores[idx])
+                        })
+                        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                        .unwrap()
+                        .0
+                }
+                Selection::Roulette | Selection::Rank => {
+                    let pick = rng.gen::<f64>() * cumulative.last().unwrap();
+                    cumulative
+                        .partition_point(|&total| total <= pick)
+                        .min(population.len() - 1)
+                }
+            };
+            &population[index]
+        })
+        .collect()
+}
+
+// Copy the fittest few of each island over the least fit of the islands it
+// sends to. Each island is its population and their fitness scores, sorted
+// fittest first, and stays sorted.
+pub fn migrate<G: Clone>(islands: &mut [(Vec<G>, Vec<f32>)], settings: &Islands) {
+    let count = islands.len();
+
+    // Gather every island's migrants before any of them arrive
+    let migrants: Vec<Vec<(G, f32)>> = islands
+        .iter()
+        .map(|(population, fitness_scores)| {
+            population
+                .iter()
+                .cloned()
+                .zip(fitness_scores.iter().copied())
+                .take(settings.migrants)
+                .collect()
+        })
+        .collect();
+
+    for (to, (population, fitness_scores)) in islands.iter_mut().enumerate() {
+        let mut arrivals: Vec<(G, f32)> = (0..count)
+            .filter(|&from| settings.topology.connects(from, to, count))
+            .flat_map(|from| migrants[from].iter().cloned())
+            .collect();
+        arrivals.truncate(population.len());
+
+        // The arrivals replace the least fit residents
+        let residents = population.len() - arrivals.len();
+        let mut merged: Vec<(G, f32)> = population
+            .drain(..)
+            .zip(fitness_scores.drain(..))
+            .take(residents)
+            .chain(arrivals)
+            .collect();
+        merged.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        (*population, *fitness_scores) = merged.into_iter().unzip();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The mean score of the parents drawn from a population scored 0 to
+    /// 99, whose mean is 49.5.
+    fn mean_parent_score(selection: Selection) -> f32 {
+        let population: Vec<usize> = (0..100).collect();
+        let scores: Vec<f32> = population.iter().map(|&i| i as f32).collect();
+        let parents = select_parents(&population, &scores, selection, 1);
+        assert_eq!(parents.len(), population.len());
+        parents.iter().map(|&&i| scores[i]).sum::<f32>() / parents.len() as f32
+    }
+
+    #[test]
+    fn selection_favours_lower_scores() {
+        let tournament = mean_parent_score(Selection::Tournament { size: 5 });
+        let roulette = mean_parent_score(Selection::Roulette);
+        let rank = mean_parent_score(Selection::Rank);
+        assert!(tournament < 30.0, "{}", tournament);
+        assert!(roulette < 30.0, "{}", roulette);
+        // Rank weights from 100 down to 1 give a mean of 33
+        assert!(rank < 40.0, "{}", rank);
+    }
+
+    #[test]
+    fn elite_count_stays_within_the_population() {
+        let mut operators = Operators::default();
+        assert_eq!(operators.elite_count(100), 5);
+        operators.elite_fraction = 2.0;
+        assert_eq!(operators.elite_count(100), 100);
+    }
+
+    #[test]
+    fn adaptation_stays_within_its_bounds() {
+        let operators = Operators {
+            adaptation: Some(Adaptation {
+                min_rate: 0.01,
+                max_rate: 0.1,
+            }),
+            ..Default::default()
+        };
+        assert!(operators.adapt(0.05, true) < 0.05);
+        assert!(operators.adapt(0.05, false) > 0.05);
+        assert_eq!(operators.adapt(0.01, true), 0.01);
+        assert_eq!(operators.adapt(0.1, false), 0.1);
+        assert_eq!(Operators::default().adapt(0.05, false), 0.05);
+    }
+
+    fn islands(count: usize, migrants: usize, topology: Topology) -> Islands {
+        Islands {
+            count,
+            interval: 10,
+            migrants,
+            topology,
+        }
+    }
+
+    #[test]
+    fn island_ranges_split_the_population_evenly() {
+        let ranges = islands(3, 1, Topology::Ring).ranges(10);
+        assert_eq!(ranges, vec![0..4, 4..7, 7..10]);
+        // More islands than individuals leaves one each
+        assert_eq!(islands(5, 1, Topology::Ring).ranges(2), vec![0..1, 1..2]);
+    }
+
+    #[test]
+    fn islands_migrate_every_interval() {
+        let settings = islands(2, 1, Topology::Ring);
+        let generations: Vec<usize> = (0..30).filter(|&g| settings.migrates(g)).collect();
+        assert_eq!(generations, vec![9, 19, 29]);
+        assert!(!islands(1, 1, Topology::Ring).migrates(9));
+    }
+
+    #[test]
+    fn topologies_connect_the_right_islands() {
+        assert!(Topology::Ring.connects(2, 0, 3));
+        assert!(!Topology::Ring.connects(0, 2, 3));
+        assert!(Topology::Full.connects(0, 2, 3));
+        assert!(!Topology::Full.connects(1, 1, 3));
+    }
+
+    #[test]
+    fn migration_moves_the_requested_count() {
+        // Island i holds individuals 10i to 10i + 4, scored by their own value
+        let island = |i: usize| {
+            let population: Vec<usize> = (10 * i..10 * i + 5).collect();
+            let scores = population.iter().map(|&g| g as f32).collect();
+            (population, scores)
+        };
+        for (topology, links) in [(Topology::Ring, 1), (Topology::Full, 2)] {
+            let mut all: Vec<(Vec<usize>, Vec<f32>)> = (0..3).map(island).collect();
+            migrate(&mut all, &islands(3, 2, topology));
+            for (i, (population, scores)) in all.iter().enumerate() {
+                assert_eq!(population.len(), 5);
+                let arrivals = population.iter().filter(|&&g| g / 10 != i).count();
+                assert_eq!(arrivals, 2 * links);
+                assert!(scores.windows(2).all(|pair| pair[0] <= pair[1]));
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use clap::ValueEnum;
+use noc::simulation::Simulation;
+
+use crate::evolution::Evolution;
+use crate::genome::Genome;
+use crate::metric::{FitnessMetric, Target};
+use crate::stats::GenerationStats;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OptimizerKind {
+    /// The genetic algorithm: a population bred by selection, crossover and
+    /// mutation.
+    Ga,
+    /// A (1+λ) evolution strategy: the fittest of a batch of mutants of a
+    /// single parent replaces it.
+    Es,
+    /// CMA-ES over the genome's continuous parameters.
+    CmaEs,
+    /// Simulated annealing of a single genome.
+    Anneal,
+    /// Hill climbing: annealing that never accepts a worse image.
+    HillClimb,
+}
+
+/// A search for the genome that best matches the target, run one iteration
+/// at a time. Every optimizer scores its candidates with `fitness`, so their
+/// statistics can be compared directly.
+pub trait Optimizer<G: Genome> {
+    fn step(&mut self);
+
+    /// Iterations run so far.
+    fn generation(&self) -> usize;
+
+    fn target(&self) -> &Target;
+
+    /// The best genome of the latest iteration.
+    fn best(&self) -> Option<&G>;
+
+    /// How each iteration fared.
+    fn stats(&self) -> &[GenerationStats];
+
+    /// Swap in a target of different dimensions, resizing the genomes to
+    /// match.
+    fn rescale(&mut self, target: Target);
+
+    /// The optimizer as the GA, for what only the GA has: islands,
+    /// cataclysms and snapshots.
+    fn as_evolution(&self) -> Option<&Evolution<G>> {
+        None
+    }
+
+    fn as_evolution_mut(&mut self) -> Option<&mut Evolution<G>> {
+        None
+    }
+}
+
+/// Draw `genome` and score it against the target. Lower is better.
+pub fn fitness<G: Genome>(metric: &dyn FitnessMetric, target: &Target, genome: &G) -> f32 {
+    metric.fitness(target, &genome.render(target.dimensions))
+}
+
+impl<G: Genome> Optimizer<G> for Evolution<G> {
+    fn step(&mut self) {
+        Simulation::step(self, 1.0 / 60.0);
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn best(&self) -> Option<&G> {
+        Evolution::best(self)
+    }
+
+    fn stats(&self) -> &[GenerationStats] {
+        &self.stats
+    }
+
+    fn rescale(&mut self, target: Target) {
+        Evolution::rescale(self, target);
+    }
+
+    fn as_evolution(&self) -> Option<&Evolution<G>> {
+        Some(self)
+    }
+
+    fn as_evolution_mut(&mut self) -> Option<&mut Evolution<G>> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annealing::{Annealing, Schedule};
+    use crate::cma_es::CmaEs;
+    use crate::genome::{Pixels, MUTATION_RATE};
+    use crate::metric::MetricKind;
+    use crate::strategy::Strategy;
+
+    const SIZE: u32 = 4;
+    const STEPS: usize = 20;
+    // High enough that most mutants of a 16 pixel image differ from their
+    // parent
+    const RATE: f32 = 0.1;
+
+    fn target() -> Target {
+        Target::new(vec![128; (SIZE * SIZE * 4) as usize], (SIZE, SIZE))
+    }
+
+    // Run a few iterations and check the best genome beats the one the
+    // optimizer started from
+    fn improves(mut optimizer: impl Optimizer<Pixels>) {
+        let metric = MetricKind::RgbMse.metric();
+        let start = fitness(
+            metric.as_ref(),
+            optimizer.target(),
+            optimizer.best().unwrap(),
+        );
+        for _ in 0..STEPS {
+            optimizer.step();
+        }
+        let end = fitness(
+            metric.as_ref(),
+            optimizer.target(),
+            optimizer.best().unwrap(),
+        );
+        assert_eq!(optimizer.generation(), STEPS);
+        assert_eq!(optimizer.stats().len(), STEPS);
+        assert!(end < start, "{end} is no better than {start}");
+    }
+
+    #[test]
+    fn the_genetic_algorithm_improves() {
+        improves(Evolution::<Pixels>::new(
+            target(),
+            20,
+            MUTATION_RATE,
+            Default::default(),
+            Default::default(),
+            MetricKind::RgbMse.metric(),
+            1,
+        ));
+    }
+
+    #[test]
+    fn the_evolution_strategy_improves() {
+        improves(Strategy::<Pixels>::new(
+            target(),
+            8,
+            RATE,
+            Default::default(),
+            MetricKind::RgbMse.metric(),
+            1,
+        ));
+    }
+
+    #[test]
+    fn cma_es_improves() {
+        improves(CmaEs::<Pixels>::new(
+            target(),
+            None,
+            0.2,
+            Default::default(),
+            MetricKind::RgbMse.metric(),
+            1,
+        ));
+    }
+
+    #[test]
+    fn annealing_improves() {
+        let schedule = Schedule {
+            temperature: 0.001,
+            cooling: 0.9,
+        };
+        improves(Annealing::<Pixels>::new(
+            target(),
+            8,
+            RATE,
+            schedule,
+            Default::default(),
+            MetricKind::RgbMse.metric(),
+            1,
+        ));
+    }
+
+    #[test]
+    fn hill_climbing_improves() {
+        improves(Annealing::<Pixels>::new(
+            target(),
+            8,
+            RATE,
+            Schedule::frozen(),
+            Default::default(),
+            MetricKind::RgbMse.metric(),
+            1,
+        ));
+    }
+}
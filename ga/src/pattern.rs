@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+
+use nannou::color::{rgb, Rgb};
+use noise::{NoiseFn, Perlin};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::genome::{Continuous, Genome};
+
+pub const MUTATION_RATE: f32 = 0.2;
+pub const PALETTE_SIZE: usize = 4;
+
+/// How far a single mutation moves a gene, as a fraction of its range.
+const NUDGE: f32 = 0.1;
+const MAX_OCTAVES: u32 = 6;
+
+/// The options every pattern in a population shares.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PatternSettings {
+    pub palette_size: usize, // Colors each pattern's palette cycles through
+}
+
+/// A generative genome: a field of fractal Perlin noise, optionally warped
+/// by more noise, colored by cycling through a palette. Every gene but the
+/// seed and the octaves lies in 0..1 and is mapped to its range when drawn.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Pattern {
+    pub seed: u32, // Picks the noise field itself
+    pub octaves: u32,
+    pub scale: f32,     // From a few broad features to many fine ones
+    pub roughness: f32, // How strongly each octave counts against the one before
+    pub warp: f32,      // How far the field is pushed around by a second field
+    pub offset: [f32; 2],
+    pub bands: f32, // Times the palette repeats across the field's range
+    pub palette: Vec<Rgb>,
+}
+
+impl Pattern {
+    // Every number in the pattern, in a fixed order
+    fn numbers_mut(&mut self) -> Vec<&mut f32> {
+        let [x, y] = &mut self.offset;
+        let mut numbers = vec![
+            &mut self.scale,
+            &mut self.roughness,
+            &mut self.warp,
+            x,
+            y,
+            &mut self.bands,
+        ];
+        for color in &mut self.palette {
+            numbers.extend([&mut color.red, &mut color.green, &mut color.blue]);
+        }
+        numbers
+    }
+
+    /// The color at `t`, interpolating around the palette as a loop.
+    fn color(&self, t: f32) -> [u8; 3] {
+        let position = t.rem_euclid(1.0) * self.palette.len() as f32;
+        let index = position as usize % self.palette.len();
+        let (from, to) = (
+            self.palette[index],
+            self.palette[(index + 1) % self.palette.len()],
+        );
+        let blend = position.fract();
+        let mix = |a: f32, b: f32| ((a + (b - a) * blend) * 255.0).round() as u8;
+        [
+            mix(from.red, to.red),
+            mix(from.green, to.green),
+            mix(from.blue, to.blue),
+        ]
+    }
+}
+
+/// Fractal noise at `point`: `octaves` layers of noise, each at twice the
+/// frequency of the one before and `persistence` times its weight, roughly
+/// within -1..1.
+fn fbm(noise: &Perlin, point: [f64; 2], octaves: u32, persistence: f64) -> f64 {
+    let (mut total, mut weight, mut total_weight, mut frequency) = (0.0, 1.0, 0.0, 1.0);
+    for _ in 0..octaves {
+        total += weight * noise.get([point[0] * frequency, point[1] * frequency]);
+        total_weight += weight;
+        weight *= persistence;
+        frequency *= 2.0;
+    }
+    total / total_weight
+}
+
+impl Genome for Pattern {
+    type Settings = PatternSettings;
+
+    const MUTATION_RATE: f32 = MUTATION_RATE;
+
+    fn random<G: Rng>(settings: &PatternSettings, _dimensions: (u32, u32), rng: &mut G) -> Self {
+        Pattern {
+            seed: rng.gen(),
+            octaves: rng.gen_range(1..=MAX_OCTAVES),
+            scale: rng.gen(),
+            roughness: rng.gen(),
+            warp: rng.gen(),
+            offset: [rng.gen(), rng.gen()],
+            bands: rng.gen(),
+            palette: (0..settings.palette_size.max(1))
+                .map(|_| rgb(rng.gen(), rng.gen(), rng.gen()))
+                .collect(),
+        }
+    }
+
+    fn render(&self, dimensions: (u32, u32)) -> Cow<'_, [u8]> {
+        let (width, height) = dimensions;
+        let noise = Perlin::new(self.seed);
+        let scale = 0.5 + 7.5 * self.scale as f64;
+        let persistence = 0.2 + 0.6 * self.roughness as f64;
+        let warp = 2.0 * self.warp as f64;
+        let bands = 0.5 + 3.5 * self.bands;
+        let offset = self.offset.map(|o| o as f64 * 100.0);
+        // The image's longer side spans `scale` units of noise
+        let unit = scale / width.max(height).max(1) as f64;
+        // Octaves finer than a few pixels would only add speckle
+        let finest = ((0.25 / unit).log2().floor() + 1.0).max(1.0) as u32;
+        let octaves = self.octaves.min(finest);
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let p = [x as f64 * unit + offset[0], y as f64 * unit + offset[1]];
+                // Look the field up at a point pushed aside by two more
+                // samples of it, far from the first
+                let q = [
+                    p[0] + warp * fbm(&noise, [p[0] + 5.2, p[1] + 1.3], octaves, persistence),
+                    p[1] + warp * fbm(&noise, [p[0] + 1.7, p[1] + 9.2], octaves, persistence),
+                ];
+                let value = fbm(&noise, q, octaves, persistence) as f32;
+                let [r, g, b] = self.color((value * 0.5 + 0.5) * bands);
+                pixels.extend([r, g, b, u8::MAX]);
+            }
+        }
+        Cow::Owned(pixels)
+    }
+
+    /// Each gene comes from either parent, and each color of the palette
+    /// from the same place in either parent's.
+    fn crossover<G: Rng>(
+        &self,
+        other: &Self,
+        _settings: &PatternSettings,
+        _dimensions: (u32, u32),
+        rng: &mut G,
+    ) -> Self {
+        let mut child = self.clone();
+        if rng.gen() {
+            child.seed = other.seed;
+        }
+        if rng.gen() {
+            child.octaves = other.octaves;
+        }
+        let mut other = other.clone();
+        for (gene, theirs) in child.numbers_mut().into_iter().zip(other.numbers_mut()) {
+            if rng.gen() {
+                *gene = *theirs;
+            }
+        }
+        child
+    }
+
+    /// A mutated number is nudged, a mutated seed replaced, and a mutated
+    /// octave count moved up or down by one.
+    fn mutate<G: Rng>(&mut self, _settings: &PatternSettings, rate: f32, rng: &mut G) {
+        if rng.gen::<f32>() < rate {
+            self.seed = rng.gen();
+        }
+        if rng.gen::<f32>() < rate {
+            self.octaves = if rng.gen() {
+                self.octaves + 1
+            } else {
+                self.octaves.saturating_sub(1)
+            }
+            .clamp(1, MAX_OCTAVES);
+        }
+        for gene in self.numbers_mut() {
+            if rng.gen::<f32>() < rate {
+                *gene = (*gene + rng.gen_range(-NUDGE..NUDGE)).clamp(0.0, 1.0);
+            }
+        }
+    }
+}
+
+/// The scale, roughness, warp, offset, bands and palette. The seed and the
+/// octave count are left as they are.
+impl Continuous for Pattern {
+    fn parameters(&self) -> Vec<f32> {
+        self.clone().numbers_mut().into_iter().map(|n| *n).collect()
+    }
+
+    fn with_parameters(&self, parameters: &[f32]) -> Self {
+        let mut pattern = self.clone();
+        for (value, &parameter) in pattern.numbers_mut().into_iter().zip(parameters) {
+            *value = parameter.clamp(0.0, 1.0);
+        }
+        pattern
+    }
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::stats::GenerationStats;
+
+/// One curve of a chart.
+struct Line {
+    label: String,
+    color: RGBAColor,
+    dashed: bool, // Overlaid runs are dashed
+    points: Vec<(f64, f64)>,
+}
+
+impl Line {
+    fn new(label: impl Into<String>, color: impl Color, points: Vec<(f64, f64)>) -> Self {
+        Line {
+            label: label.into(),
+            color: color.to_rgba(),
+            dashed: false,
+            points,
+        }
+    }
+
+    /// A statistic of each generation of a run.
+    fn of(
+        label: impl Into<String>,
+        color: impl Color,
+        stats: &[GenerationStats],
+        value: fn(&GenerationStats) -> f64,
+    ) -> Self {
+        let points = stats
+            .iter()
+            .map(|s| (s.generation as f64, value(s)))
+            .collect();
+        Line::new(label, color, points)
+    }
+
+    fn dashed(mut self) -> Self {
+        self.dashed = true;
+        self
+    }
+}
+
+// Plot the statistics of each generation so far: the spread of fitness
+// scores, with the best of each island when there is more than one, the
+// diversity of the population and the time each generation took. Each of
+// `overlays` is an earlier run, drawn dashed under its name for comparison.
+pub fn plot_stats(
+    stats: &[GenerationStats],
+    island_history: &[Vec<f32>],
+    overlays: &[(String, Vec<GenerationStats>)],
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let root_area = BitMapBackend::new(path, (1000, 900)).into_drawing_area();
+    root_area.fill(&WHITE)?;
+    let (fitness_area, lower) = root_area.split_vertically(540);
+    let (diversity_area, time_area) = lower.split_horizontally(500);
+
+    let mut fitness = vec![
+        Line::of("best", RED, stats, |s| s.best as f64),
+        Line::of("median", GREEN.mix(0.8), stats, |s| s.median as f64),
+        Line::of("mean", BLUE.mix(0.8), stats, |s| s.mean as f64),
+        Line::of("worst", BLACK.mix(0.5), stats, |s| s.worst as f64),
+    ];
+    if island_history.len() > 1 {
+        for (island, history) in island_history.iter().enumerate() {
+            let points = history
+                .iter()
+                .enumerate()
+                .map(|(i, &f)| (i as f64, f as f64))
+                .collect();
+            fitness.push(Line::new(
+                format!("island {}", island),
+                Palette99::pick(island).mix(0.6),
+                points,
+            ));
+        }
+    }
+    let mut diversity = vec![Line::of("diversity", BLUE, stats, |s| s.diversity as f64)];
+    let mut time = vec![Line::of("seconds", BLUE, stats, |s| s.seconds)];
+
+    for (i, (name, run)) in overlays.iter().enumerate() {
+        // Clear of the colors the islands use
+        let color = Palette99::pick(island_history.len() + i).to_rgba();
+        fitness.push(Line::of(format!("{} best", name), color, run, |s| s.best as f64).dashed());
+        diversity.push(Line::of(name.as_str(), color, run, |s| s.diversity as f64).dashed());
+        time.push(Line::of(name.as_str(), color, run, |s| s.seconds).dashed());
+    }
+
+    draw_chart(&fitness_area, "Fitness Over Generations", &fitness)?;
+    draw_chart(&diversity_area, "Diversity", &diversity)?;
+    draw_chart(&time_area, "Seconds per Generation", &time)?;
+
+    root_area.present()?;
+    Ok(())
+}
+
+// Draw `lines` on axes scaled to fit them
+fn draw_chart(
+    area: &DrawingArea<BitMapBackend<'_>, Shift>,
+    caption: &str,
+    lines: &[Line],
+) -> Result<(), Box<dyn Error>> {
+    let points = || {
+        lines
+            .iter()
+            .flat_map(|line| &line.points)
+            .filter(|(_, y)| y.is_finite())
+    };
+    let x_max = points().map(|&(x, _)| x).fold(1.0, f64::max);
+    let y_min = points().map(|&(_, y)| y).fold(f64::INFINITY, f64::min);
+    let y_max = points().map(|&(_, y)| y).fold(f64::NEG_INFINITY, f64::max);
+    let (y_min, y_max) = if y_min > y_max {
+        (0.0, 1.0) // Nothing to plot yet
+    } else {
+        let margin = ((y_max - y_min) * 0.05).max(1e-6);
+        (y_min - margin, y_max + margin)
+    };
+
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(0f64..x_max, y_min..y_max)?;
+
+    chart.configure_mesh().draw()?;
+
+    for line in lines {
+        let color = line.color;
+        let points = line.points.iter().copied();
+        let series = if line.dashed {
+            chart.draw_series(DashedLineSeries::new(points, 6, 4, color.into()))?
+        } else {
+            let width = if line.label == "best" { 2 } else { 1 };
+            chart.draw_series(LineSeries::new(points, color.stroke_width(width)))?
+        };
+        series
+            .label(line.label.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+    Ok(())
+}
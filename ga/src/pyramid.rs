@@ -0,0 +1,140 @@
+use image::imageops::{self, FilterType};
+use image::{GrayImage, RgbaImage};
+
+use crate::metric::Target;
+
+pub const LEVEL_GENERATIONS: usize = 200;
+
+/// The target at a series of resolutions, each twice the size of the one
+/// before, so the GA can settle the coarse structure of an image on a small
+/// copy before working on the detail.
+pub struct Pyramid {
+    levels: Vec<Target>, // Coarsest first, ending with the full-size target
+    generations: usize,  // Generations spent on each level below the top
+}
+
+impl Pyramid {
+    /// A pyramid of `levels` levels topped by `image`, weighted by `mask` of
+    /// the same size if there is one. A single level is the full-size target
+    /// alone.
+    pub fn new(
+        image: &RgbaImage,
+        mask: Option<&GrayImage>,
+        levels: usize,
+        generations: usize,
+    ) -> Self {
+        let (width, height) = image.dimensions();
+        let levels = (0..levels.max(1))
+            .rev()
+            .map(|halvings| {
+                let halvings = halvings.min(31) as u32;
+                let dimensions = ((width >> halvings).max(1), (height >> halvings).max(1));
+                let pixels = if halvings == 0 {
+                    image.as_raw().clone()
+                } else {
+                    imageops::resize(image, dimensions.0, dimensions.1, FilterType::Triangle)
+                        .into_raw()
+                };
+                let target = Target::new(pixels, dimensions);
+                match mask {
+                    Some(mask) => {
+                        let mask = imageops::resize(
+                            mask,
+                            dimensions.0,
+                            dimensions.1,
+                            FilterType::Triangle,
+                        );
+                        let weights: Vec<f32> =
+                            mask.pixels().map(|p| p.0[0] as f32 / 255.0).collect();
+                        target.with_mask(&weights)
+                    }
+                    None => target,
+                }
+            })
+            .collect();
+        Pyramid {
+            levels,
+            generations: generations.max(1),
+        }
+    }
+
+    /// The level `generation` is evolved at.
+    pub fn level(&self, generation: usize) -> usize {
+        (generation / self.generations).min(self.levels.len() - 1)
+    }
+
+    pub fn target(&self, level: usize) -> &Target {
+        &self.levels[level]
+    }
+
+    /// The full-size target at the top of the pyramid.
+    pub fn full(&self) -> &Target {
+        self.levels.last().unwrap()
+    }
+
+    pub fn is_top(&self, level: usize) -> bool {
+        level + 1 == self.levels.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_double_up_to_the_full_target() {
+        let image = RgbaImage::from_pixel(40, 24, image::Rgba([10, 20, 30, 255]));
+        let pyramid = Pyramid::new(&image, None, 3, 50);
+        let sizes: Vec<(u32, u32)> = (0..3).map(|l| pyramid.target(l).dimensions).collect();
+        assert_eq!(sizes, vec![(10, 6), (20, 12), (40, 24)]);
+        assert_eq!(pyramid.full().pixels, *image.as_raw());
+    }
+
+    #[test]
+    fn the_last_stage_reaches_the_full_target() {
+        let image = RgbaImage::new(16, 16);
+        let pyramid = Pyramid::new(&image, None, 3, 50);
+        let levels: Vec<usize> = [0, 49, 50, 99, 100, 10_000]
+            .iter()
+            .map(|&g| pyramid.level(g))
+            .collect();
+        assert_eq!(levels, vec![0, 0, 1, 1, 2, 2]);
+        assert!(pyramid.is_top(pyramid.level(100)));
+        assert_eq!(
+            pyramid.target(pyramid.level(100)).dimensions,
+            pyramid.full().dimensions
+        );
+    }
+
+    #[test]
+    fn a_single_level_is_the_full_target() {
+        let image = RgbaImage::new(5, 3);
+        let pyramid = Pyramid::new(&image, None, 0, 50);
+        assert!(pyramid.is_top(pyramid.level(0)));
+        assert_eq!(pyramid.full().dimensions, (5, 3));
+    }
+
+    #[test]
+    fn tiny_images_stop_halving_at_a_pixel() {
+        let image = RgbaImage::new(4, 2);
+        let pyramid = Pyramid::new(&image, None, 4, 50);
+        assert_eq!(pyramid.target(0).dimensions, (1, 1));
+    }
+
+    #[test]
+    fn masks_shrink_with_each_level() {
+        use crate::metric::MetricKind;
+        // Only the right half counts, where the candidate matches
+        let image = RgbaImage::from_pixel(16, 16, image::Rgba([200, 0, 0, 255]));
+        let mask = GrayImage::from_fn(16, 16, |x, _| image::Luma([if x < 8 { 0 } else { 255 }]));
+        let pyramid = Pyramid::new(&image, Some(&mask), 2, 50);
+        let target = pyramid.target(0);
+        let mut candidate = target.pixels.clone();
+        for (i, pixel) in candidate.chunks_mut(4).enumerate() {
+            if (i as u32 % target.dimensions.0) < 2 {
+                pixel[..3].copy_from_slice(&[0, 0, 255]);
+            }
+        }
+        assert_eq!(MetricKind::RgbMse.metric().fitness(target, &candidate), 0.0);
+    }
+}
@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use clap::ValueEnum;
+use nannou::prelude::*;
+use noc::raster::Canvas;
+use noc::render::{Renderer, Style};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::genome::{Continuous, Genome};
+
+pub const MUTATION_RATE: f32 = 0.02;
+pub const SHAPE_COUNT: usize = 50;
+pub const MAX_SHAPES: usize = 200;
+
+/// How far a single mutation moves a point, as a fraction of the image.
+const NUDGE: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum ShapeKind {
+    Triangle,
+    Ellipse,
+    Stroke,
+}
+
+/// The options every painting in a population shares.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PaintingSettings {
+    pub kinds: Vec<ShapeKind>,
+    pub shape_count: usize, // Shapes in each painting of the first generation
+    pub max_shapes: usize,
+}
+
+/// The outline of a shape. Points are in image space scaled to the unit
+/// square, with the origin in the top left corner.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Primitive {
+    Triangle([Vec2; 3]),
+    Ellipse { center: Vec2, radii: Vec2 },
+    Stroke { points: Vec<Vec2>, weight: f32 },
+}
+
+/// A translucent shape painted over everything below it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shape {
+    pub primitive: Primitive,
+    pub color: Rgb,
+    pub alpha: f32,
+}
+
+/// A genome made of shapes painted in order over a solid background.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Painting {
+    pub background: Rgb,
+    pub shapes: Vec<Shape>,
+}
+
+impl Shape {
+    fn random<G: Rng>(kinds: &[ShapeKind], rng: &mut G) -> Self {
+        let center = vec2(rng.gen(), rng.gen());
+        let primitive = match kinds[rng.gen_range(0..kinds.len())] {
+            ShapeKind::Triangle => {
+                Primitive::Triangle([(); 3].map(|_| center + random_offset(0.2, rng)))
+            }
+            ShapeKind::Ellipse => Primitive::Ellipse {
+                center,
+                radii: vec2(rng.gen_range(0.02..0.2), rng.gen_range(0.02..0.2)),
+            },
+            ShapeKind::Stroke => {
+                // A short random walk from the starting point
+                let mut points = vec![center];
+                for _ in 0..rng.gen_range(1..4) {
+                    let last = *points.last().unwrap();
+                    points.push(last + random_offset(NUDGE, rng));
+                }
+                Primitive::Stroke {
+                    points,
+                    weight: rng.gen_range(0.005..0.05),
+                }
+            }
+        };
+        Shape {
+            primitive,
+            color: rgb(rng.gen(), rng.gen(), rng.gen()),
+            alpha: rng.gen_range(0.1..0.6),
+        }
+    }
+
+    /// Move one of the shape's points, or resize it.
+    fn move_vertex<G: Rng>(&mut self, rng: &mut G) {
+        match &mut self.primitive {
+            Primitive::Triangle(points) => {
+                let point = &mut points[rng.gen_range(0..3)];
+                *point = nudge(*point, rng);
+            }
+            Primitive::Ellipse { center, radii } => {
+                if rng.gen() {
+                    *center = nudge(*center, rng);
+                } else {
+                    *radii = (*radii + random_offset(NUDGE / 2.0, rng))
+                        .clamp(vec2(0.005, 0.005), vec2(0.5, 0.5));
+                }
+            }
+            Primitive::Stroke { points, weight } => {
+                let index = rng.gen_range(0..=points.len());
+                match points.get_mut(index) {
+                    Some(point) => *point = nudge(*point, rng),
+                    None => *weight = (*weight * rng.gen_range(0.5..2.0)).clamp(0.002, 0.1),
+                }
+            }
+        }
+    }
+
+    /// Shift one channel of the shape's color, including its opacity.
+    fn change_color<G: Rng>(&mut self, rng: &mut G) {
+        let shift = rng.gen_range(-NUDGE..NUDGE);
+        match rng.gen_range(0..4) {
+            0 => self.color.red = (self.color.red + shift).clamp(0.0, 1.0),
+            1 => self.color.green = (self.color.green + shift).clamp(0.0, 1.0),
+            2 => self.color.blue = (self.color.blue + shift).clamp(0.0, 1.0),
+            _ => self.alpha = (self.alpha + shift).clamp(0.05, 1.0),
+        }
+    }
+
+    fn render<R: Renderer>(&self, renderer: &mut R, width: f32, height: f32) {
+        // Convert from the unit square to sketch coordinates
+        let to_sketch = |p: Vec2| pt2((p.x - 0.5) * width, (0.5 - p.y) * height);
+        let color = rgba(
+            self.color.red,
+            self.color.green,
+            self.color.blue,
+            self.alpha,
+        );
+        match &self.primitive {
+            Primitive::Triangle(points) => {
+                renderer.tri(points.map(to_sketch), Style::fill(color));
+            }
+            Primitive::Ellipse { center, radii } => renderer.ellipse(
+                to_sketch(*center),
+                radii.x * 2.0 * width,
+                radii.y * 2.0 * height,
+                Style::fill(color),
+            ),
+            Primitive::Stroke { points, weight } => {
+                let points: Vec<Point2> = points.iter().map(|&p| to_sketch(p)).collect();
+                renderer.polyline(&points, weight * width.min(height), color);
+            }
+        }
+    }
+}
+
+impl Genome for Painting {
+    type Settings = PaintingSettings;
+
+    const MUTATION_RATE: f32 = MUTATION_RATE;
+
+    fn random<G: Rng>(settings: &PaintingSettings, _dimensions: (u32, u32), rng: &mut G) -> Self {
+        Painting {
+            background: rgb(rng.gen(), rng.gen(), rng.gen()),
+            shapes: (0..settings.shape_count.min(settings.max_shapes))
+                .map(|_| Shape::random(&settings.kinds, rng))
+                .collect(),
+        }
+    }
+
+    fn render(&self, dimensions: (u32, u32)) -> Cow<'_, [u8]> {
+        let mut canvas = Canvas::new(dimensions.0, dimensions.1);
+        canvas.background(self.background);
+        for shape in &self.shapes {
+            shape.render(&mut canvas, dimensions.0 as f32, dimensions.1 as f32);
+        }
+        Cow::Owned(canvas.into_image().into_raw())
+    }
+
+    /// Paint the lower layers of one parent under the upper layers of the
+    /// other, cutting both at the same fraction of their depth.
+    fn crossover<G: Rng>(
+        &self,
+        other: &Self,
+        _settings: &PaintingSettings,
+        _dimensions: (u32, u32),
+        rng: &mut G,
+    ) -> Self {
+        let cut = rng.gen::<f32>();
+        let split = |painting: &Painting| (painting.shapes.len() as f32 * cut).round() as usize;
+        let shapes = self.shapes[..split(self)]
+            .iter()
+            .chain(&other.shapes[split(other)..])
+            .cloned()
+            .collect();
+        Painting {
+            background: if rng.gen() {
+                self.background
+            } else {
+                other.background
+            },
+            shapes,
+        }
+    }
+
+    /// Each shape is a gene. A mutated shape has a vertex moved, its color
+    /// shifted, is moved to another layer, is removed, or has a new random
+    /// shape painted over it. A painting with no shapes gains one at the
+    /// mutation rate.
+    fn mutate<G: Rng>(&mut self, settings: &PaintingSettings, rate: f32, rng: &mut G) {
+        if rng.gen::<f32>() < rate {
+            let channel = rng.gen_range(0..3);
+            let shift = rng.gen_range(-NUDGE..NUDGE);
+            let background = &mut self.background;
+            let value = [
+                &mut background.red,
+                &mut background.green,
+                &mut background.blue,
+            ];
+            *value[channel] = (*value[channel] + shift).clamp(0.0, 1.0);
+        }
+
+        if self.shapes.is_empty() && settings.max_shapes > 0 && rng.gen::<f32>() < rate {
+            self.shapes.push(Shape::random(&settings.kinds, rng));
+        }
+
+        let mut i = 0;
+        while i < self.shapes.len() {
+            if rng.gen::<f32>() < rate {
+                match rng.gen_range(0..5) {
+                    0 => self.shapes[i].move_vertex(rng),
+                    1 => self.shapes[i].change_color(rng),
+                    2 => {
+                        let shape = self.shapes.remove(i);
+                        let layer = rng.gen_range(0..=self.shapes.len());
+                        self.shapes.insert(layer, shape);
+                    }
+                    3 if self.shapes.len() > 1 => {
+                        self.shapes.remove(i);
+                        continue;
+                    }
+                    4 if self.shapes.len() < settings.max_shapes => {
+                        self.shapes
+                            .insert(i + 1, Shape::random(&settings.kinds, rng));
+                        i += 1;
+                    }
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+impl Painting {
+    // Every number in the painting along with the range it is kept in, in a
+    // fixed order
+    fn numbers_mut(&mut self) -> Vec<(&mut f32, RangeInclusive<f32>)> {
+        let unit = || 0.0..=1.0;
+        let point = || -0.1..=1.1;
+        let background = &mut self.background;
+        let mut numbers = vec![
+            (&mut background.red, unit()),
+            (&mut background.green, unit()),
+            (&mut background.blue, unit()),
+        ];
+        for shape in &mut self.shapes {
+            match &mut shape.primitive {
+                Primitive::Triangle(points) => {
+                    for p in points {
+                        numbers.extend(coordinates(p).map(|c| (c, point())));
+                    }
+                }
+                Primitive::Ellipse { center, radii } => {
+                    numbers.extend(coordinates(center).map(|c| (c, point())));
+                    numbers.extend(coordinates(radii).map(|c| (c, 0.005..=0.5)));
+                }
+                Primitive::Stroke { points, weight } => {
+                    for p in points {
+                        numbers.extend(coordinates(p).map(|c| (c, point())));
+                    }
+                    numbers.push((weight, 0.002..=0.1));
+                }
+            }
+            let color = &mut shape.color;
+            numbers.extend([
+                (&mut color.red, unit()),
+                (&mut color.green, unit()),
+                (&mut color.blue, unit()),
+                (&mut shape.alpha, 0.05..=1.0),
+            ]);
+        }
+        numbers
+    }
+}
+
+/// The background, then the points, sizes, color and opacity of each shape.
+/// The shapes themselves, their kinds and their order are left as they are.
+impl Continuous for Painting {
+    fn parameters(&self) -> Vec<f32> {
+        self.clone()
+            .numbers_mut()
+            .into_iter()
+            .map(|(value, _)| *value)
+            .collect()
+    }
+
+    fn with_parameters(&self, parameters: &[f32]) -> Self {
+        let mut painting = self.clone();
+        for ((value, range), &parameter) in painting.numbers_mut().into_iter().zip(parameters) {
+            *value = parameter.clamp(*range.start(), *range.end());
+        }
+        painting
+    }
+}
+
+fn coordinates(point: &mut Vec2) -> [&mut f32; 2] {
+    let [x, y] = point.as_mut();
+    [x, y]
+}
+
+fn random_offset<G: Rng>(extent: f32, rng: &mut G) -> Vec2 {
+    vec2(
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+    )
+}
+
+/// Move a point a little, keeping it near the image.
+fn nudge<G: Rng>(point: Vec2, rng: &mut G) -> Vec2 {
+    (point + random_offset(NUDGE, rng)).clamp(vec2(-0.1, -0.1), vec2(1.1, 1.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_painting_gains_a_shape() {
+        let settings = PaintingSettings {
+            kinds: vec![ShapeKind::Triangle],
+            shape_count: 0,
+            max_shapes: MAX_SHAPES,
+        };
+        let mut rng = noc::seed::rng(1);
+        let mut painting = Painting::random(&settings, (8, 8), &mut rng);
+        assert!(painting.shapes.is_empty());
+        painting.mutate(&settings, 1.0, &mut rng);
+        assert!(!painting.shapes.is_empty());
+    }
+}
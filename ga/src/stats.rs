@@ -0,0 +1,107 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+const CSV_HEADER: &str = "generation,best,mean,median,worst,diversity,seconds";
+
+/// How one generation fared.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: usize,
+    pub best: f32,
+    pub mean: f32,
+    pub median: f32,
+    pub worst: f32,
+    pub diversity: f32,
+    pub seconds: f64, // Time taken to evaluate and breed the generation
+}
+
+impl GenerationStats {
+    /// The spread of a generation's fitness scores, in any order.
+    pub fn new(generation: usize, fitness_scores: &[f32], diversity: f32, seconds: f64) -> Self {
+        let mut sorted = fitness_scores.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = sorted.len();
+        let median = if n.is_multiple_of(2) {
+            (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+        } else {
+            sorted[n / 2]
+        };
+        GenerationStats {
+            generation,
+            best: sorted[0],
+            mean: sorted.iter().sum::<f32>() / n as f32,
+            median,
+            worst: sorted[n - 1],
+            diversity,
+            seconds,
+        }
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("json")
+}
+
+/// Write `stats` to `path`, as a JSON array for `.json` files and CSV
+/// otherwise.
+pub fn save(path: &Path, stats: &[GenerationStats]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    if is_json(path) {
+        serde_json::to_writer_pretty(&mut writer, stats)?;
+    } else {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for s in stats {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                s.generation, s.best, s.mean, s.median, s.worst, s.diversity, s.seconds
+            )?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read stats written by `save`.
+pub fn load(path: &Path) -> Result<Vec<GenerationStats>, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    if is_json(path) {
+        return Ok(serde_json::from_str(&text)?);
+    }
+
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some(CSV_HEADER) {
+        return Err(format!("expected the header `{}`", CSV_HEADER).into());
+    }
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 7 {
+                return Err(format!("line {}: expected 7 fields", i + 2).into());
+            }
+            let field = |n: usize| -> Result<f64, Box<dyn Error>> {
+                fields[n]
+                    .parse()
+                    .map_err(|err| format!("line {}: {}", i + 2, err).into())
+            };
+            Ok(GenerationStats {
+                generation: field(0)? as usize,
+                best: field(1)? as f32,
+                mean: field(2)? as f32,
+                median: field(3)? as f32,
+                worst: field(4)? as f32,
+                diversity: field(5)? as f32,
+                seconds: field(6)?,
+            })
+        })
+        .collect()
+}
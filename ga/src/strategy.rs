@@ -0,0 +1,127 @@
+use std::time::Instant;
+
+use noc::seed;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::diversity;
+use crate::genome::Genome;
+use crate::metric::{FitnessMetric, Target};
+use crate::optimizer::{fitness, Optimizer};
+use crate::stats::GenerationStats;
+
+/// A (1+λ) evolution strategy: each iteration mutates `offspring` copies of
+/// a single parent, and the fittest of them replaces the parent if it is at
+/// least as fit. The mutation rate follows the one-fifth success rule,
+/// growing while more than a fifth of the offspring beat their parent and
+/// shrinking otherwise.
+pub struct Strategy<G: Genome> {
+    target: Target,
+    metric: Box<dyn FitnessMetric>,
+    settings: G::Settings,
+    parent: G,
+    parent_fitness: f32,
+    offspring: usize,
+    mutation_rate: f32,
+    min_rate: f32, // The rate never shrinks below a hundredth of where it started
+    generation: usize,
+    stats: Vec<GenerationStats>,
+    rng: StdRng,
+}
+
+impl<G: Genome> Strategy<G> {
+    pub fn new(
+        target: Target,
+        offspring: usize,
+        mutation_rate: f32,
+        settings: G::Settings,
+        metric: Box<dyn FitnessMetric>,
+        seed: u64,
+    ) -> Self {
+        let mut rng = seed::rng(seed);
+        let parent = G::random(&settings, target.dimensions, &mut rng);
+        let parent_fitness = fitness(metric.as_ref(), &target, &parent);
+        Strategy {
+            target,
+            metric,
+            settings,
+            parent,
+            parent_fitness,
+            offspring: offspring.max(1),
+            mutation_rate,
+            min_rate: mutation_rate / 100.0,
+            generation: 0,
+            stats: Vec::new(),
+            rng,
+        }
+    }
+}
+
+impl<G: Genome> Optimizer<G> for Strategy<G> {
+    fn step(&mut self) {
+        let start = Instant::now();
+
+        // Mutate and score the offspring in parallel
+        let seed: u64 = self.rng.gen();
+        let (children, mut scores): (Vec<G>, Vec<f32>) = (0..self.offspring)
+            .into_par_iter()
+            .map(|i| {
+                let mut rng = seed::task_rng(seed, i as u64);
+                let mut child = self.parent.clone();
+                child.mutate(&self.settings, self.mutation_rate, &mut rng);
+                let score = fitness(self.metric.as_ref(), &self.target, &child);
+                (child, score)
+            })
+            .unzip();
+        let diversity = diversity::diversity(&children, self.target.dimensions);
+
+        let successes = scores.iter().filter(|&&f| f < self.parent_fitness).count();
+        let success_ratio = successes as f32 / self.offspring as f32;
+        self.mutation_rate =
+            (self.mutation_rate * ((success_ratio - 0.2) / 0.8).exp()).clamp(self.min_rate, 1.0);
+
+        let (fittest, &fittest_score) = scores
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .unwrap();
+        scores.push(self.parent_fitness);
+        if fittest_score <= self.parent_fitness {
+            self.parent = children.into_iter().nth(fittest).unwrap();
+            self.parent_fitness = fittest_score;
+        }
+
+        self.stats.push(GenerationStats::new(
+            self.generation,
+            &scores,
+            diversity,
+            start.elapsed().as_secs_f64(),
+        ));
+        self.generation += 1;
+    }
+
+    fn generation(&self) -> usize {
+        self.generation
+    }
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    fn best(&self) -> Option<&G> {
+        Some(&self.parent)
+    }
+
+    fn stats(&self) -> &[GenerationStats] {
+        &self.stats
+    }
+
+    fn rescale(&mut self, target: Target) {
+        self.parent = self
+            .parent
+            .resize(self.target.dimensions, target.dimensions);
+        self.target = target;
+        self.parent_fitness = fitness(self.metric.as_ref(), &self.target, &self.parent);
+    }
+}
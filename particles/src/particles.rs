@@ -1,87 +1,139 @@
+use clap::Parser;
 use nannou::prelude::*;
+use noc::particles::{Particle, ParticleSystem};
+use noc::playback::Playback;
+use noc::raster::Canvas;
+use noc::recorder::{self, RecordArgs, Recorder};
+use noc::render::{Renderer, Style};
+use noc::seed::{self, SeedArgs};
+use rand::rngs::StdRng;
+use rand::Rng;
 
-fn main() {
-    nannou::app(model).update(update).run();
-}
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 360;
 
-struct Particle {
-    position: Point2,
-    velocity: Vec2,
-    acceleration: Vec2,
-    life_span: f32,
-}
+/// A particle fountain emitted from the mouse.
+#[derive(Parser)]
+struct Args {
+    #[command(flatten)]
+    seed: SeedArgs,
 
-impl Particle {
-    fn new(l: Point2) -> Self {
-        let acceleration = vec2(0.0, 0.05);
-        let velocity = vec2(random_f32() * 2.0 - 1.0, random_f32() - 1.0);
-        let position = l;
-        let life_span = 255.0;
-        Particle {
-            position,
-            velocity,
-            acceleration,
-            life_span,
-        }
-    }
+    #[command(flatten)]
+    record: RecordArgs,
+}
 
-    fn update(&mut self) {
-        self.velocity += self.acceleration;
-        self.position -= self.velocity;
-        self.life_span -= 2.0;
+fn main() {
+    let args = Args::parse();
+    if let Some(frames) = args.record.headless {
+        let mut model = Model::new(&args);
+        recorder::run_headless(
+            &args.record,
+            WIDTH,
+            HEIGHT,
+            frames,
+            |canvas: &mut Canvas, _| {
+                step(&mut model, pt2(0.0, 0.0));
+                render(&model, canvas);
+            },
+        );
+        return;
     }
+    nannou::app(model).update(update).exit(exit).run();
+}
 
-    fn display(&self, draw: &Draw) {
-        draw.ellipse()
-            .xy(self.position)
-            .w_h(12.0, 12.0)
-            .rgba(
-                random_range(0.0, 1.0),
-                random_range(0.0, 1.0),
-                random_range(0.0, 1.0),
-                self.life_span / 255.0,
-            )
-            .stroke(rgba(0.0, 0.0, 0.0, self.life_span / 255.0))
-            .stroke_weight(2.0);
-    }
+fn new_particle(l: Point2, rng: &mut StdRng) -> Particle {
+    let acceleration = vec2(0.0, 0.05);
+    let velocity = vec2(rng.gen::<f32>() * 2.0 - 1.0, rng.gen::<f32>() - 1.0);
+    Particle::new(l, velocity, acceleration)
+}
 
-    fn is_dead(&self) -> bool {
-        self.life_span < 0.0
-    }
+fn display<R: Renderer>(particle: &Particle, renderer: &mut R, rng: &mut StdRng) {
+    let fill = rgba(
+        rng.gen_range(0.0..1.0),
+        rng.gen_range(0.0..1.0),
+        rng.gen_range(0.0..1.0),
+        particle.alpha(),
+    );
+    let stroke = rgba(0.0, 0.0, 0.0, particle.alpha());
+    renderer.ellipse(
+        particle.position,
+        12.0,
+        12.0,
+        Style::fill(fill).with_stroke(stroke, 2.0),
+    );
 }
 
 struct Model {
-    particles: Vec<Particle>,
+    system: ParticleSystem,
+    rng: StdRng,
+    /// Seeds this frame's particle colours, so drawing doesn't need a
+    /// mutable model.
+    color_seed: u64,
+    playback: Playback,
+    recorder: Recorder,
+}
+
+impl Model {
+    fn new(args: &Args) -> Self {
+        let system = ParticleSystem::new();
+        let mut rng = seed::rng(seed::resolve(args.seed.seed));
+        let color_seed = rng.gen();
+        let playback = Playback::default();
+        let recorder = Recorder::new(&args.record);
+        Model {
+            system,
+            rng,
+            color_seed,
+            playback,
+            recorder,
+        }
+    }
 }
 
 fn model(app: &App) -> Model {
-    app.new_window().size(640, 360).view(view).build().unwrap();
-    let particles: Vec<Particle> = Vec::new();
+    app.new_window()
+        .size(WIDTH, HEIGHT)
+        .view(view)
+        .key_pressed(key_pressed)
+        .build()
+        .unwrap();
+    Model::new(&Args::parse())
+}
 
-    Model { particles }
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    model.playback.key_pressed(key);
+    model.recorder.key_pressed(app, key);
+}
+
+fn step(model: &mut Model, emitter: Point2) {
+    model.system.emit(new_particle(emitter, &mut model.rng));
+    model.system.update();
+    model.color_seed = model.rng.gen();
 }
 
 fn update(app: &App, model: &mut Model, _update: Update) {
-    model
-        .particles
-        .push(Particle::new(app.mouse.position()));
-
-    for i in (0..model.particles.len()).rev() {
-        model.particles[i].update();
-        if model.particles[i].is_dead() {
-            model.particles.remove(i);
-        }
+    model.recorder.capture_window(app);
+    for _ in 0..model.playback.ticks() {
+        step(model, app.mouse.position());
     }
 }
 
-fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
-
-    draw.background().color(WHITE);
+fn render<R: Renderer>(model: &Model, renderer: &mut R) {
+    renderer.background(WHITE);
 
-    for p in model.particles.iter() {
-        p.display(&draw);
+    let mut rng = seed::rng(model.color_seed);
+    for p in model.system.particles.iter() {
+        display(p, renderer, &mut rng);
     }
+}
 
+fn view(app: &App, model: &Model, frame: Frame) {
+    let mut draw = app.draw();
+    render(model, &mut draw);
+    model.playback.render(&mut draw, app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
+
+fn exit(app: &App, mut model: Model) {
+    model.recorder.finish(app);
+}
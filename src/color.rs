@@ -0,0 +1,428 @@
+// Conversions between color spaces and color difference formulas.
+//
+// Colors are `(f32, f32, f32)` tuples. sRGB, HSV and HSL components run
+// from 0 to 1, XYZ is scaled so the D65 white point has Y = 100, hues are
+// in degrees from 0 to 360, and Lab and OKLab follow their usual ranges.
+
+use std::sync::OnceLock;
+
+/// D65 reference white in XYZ.
+const WHITE: (f32, f32, f32) = (95.047, 100.000, 108.883);
+
+/// Convert an sRGB component to linear light
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c > 0.04045 {
+        ((c + 0.055) / 1.055).powf(2.4)
+    } else {
+        c / 12.92
+    }
+}
+
+/// Convert an 8-bit sRGB component to linear light, looked up from a table
+/// rather than calling `powf`
+///
+/// ```
+/// # use noc::color::{srgb8_to_linear, srgb_to_linear};
+/// assert_eq!(srgb8_to_linear(200), srgb_to_linear(200.0 / 255.0));
+/// ```
+pub fn srgb8_to_linear(c: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))[c as usize]
+}
+
+/// Convert a linear light component to sRGB
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c > 0.0031308 {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    } else {
+        c * 12.92
+    }
+}
+
+/// Convert RGB to Lab
+///
+/// ```
+/// # use noc::color::rgb_to_lab;
+/// let (l, a, b) = rgb_to_lab(1.0, 0.0, 0.0);
+/// assert!((l - 53.24).abs() < 0.01);
+/// assert!((a - 80.09).abs() < 0.01);
+/// assert!((b - 67.20).abs() < 0.01);
+/// ```
+pub fn rgb_to_lab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let (x, y, z) = rgb_to_xyz(r, g, b);
+    xyz_to_lab(x, y, z)
+}
+
+/// Convert 8-bit RGB to Lab, using the lookup table for the sRGB curve
+pub fn rgb8_to_lab(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (x, y, z) = linear_to_xyz(srgb8_to_linear(r), srgb8_to_linear(g), srgb8_to_linear(b));
+    xyz_to_lab(x, y, z)
+}
+
+/// Convert Lab to RGB. Colors outside the sRGB gamut come back with
+/// components outside 0..1.
+///
+/// ```
+/// # use noc::color::{lab_to_rgb, rgb_to_lab};
+/// let (l, a, b) = rgb_to_lab(0.2, 0.4, 0.6);
+/// let (r, g, b) = lab_to_rgb(l, a, b);
+/// assert!((r - 0.2).abs() < 1e-4 && (g - 0.4).abs() < 1e-4 && (b - 0.6).abs() < 1e-4);
+/// ```
+pub fn lab_to_rgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let (x, y, z) = lab_to_xyz(l, a, b);
+    xyz_to_rgb(x, y, z)
+}
+
+/// Convert RGB to XYZ
+///
+/// ```
+/// # use noc::color::rgb_to_xyz;
+/// let (x, y, z) = rgb_to_xyz(1.0, 1.0, 1.0);
+/// assert!((x - 95.047).abs() < 0.01 && (y - 100.0).abs() < 0.01 && (z - 108.883).abs() < 0.01);
+/// ```
+pub fn rgb_to_xyz(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    linear_to_xyz(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+}
+
+/// Convert linear light RGB to XYZ
+fn linear_to_xyz(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let r = r * 100.0;
+    let g = g * 100.0;
+    let b = b * 100.0;
+
+    let x = r * 0.4124564 + g * 0.3575761 + b * 0.1804375;
+    let y = r * 0.2126729 + g * 0.7151522 + b * 0.072175;
+    let z = r * 0.0193339 + g * 0.119192 + b * 0.9503041;
+
+    (x, y, z)
+}
+
+/// Convert XYZ to RGB
+pub fn xyz_to_rgb(x: f32, y: f32, z: f32) -> (f32, f32, f32) {
+    let (x, y, z) = (x / 100.0, y / 100.0, z / 100.0);
+
+    let r = x * 3.2404542 - y * 1.5371385 - z * 0.4985314;
+    let g = -x * 0.969266 + y * 1.8760108 + z * 0.041556;
+    let b = x * 0.0556434 - y * 0.2040259 + z * 1.0572252;
+
+    (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+}
+
+/// Convert XYZ to Lab
+pub fn xyz_to_lab(x: f32, y: f32, z: f32) -> (f32, f32, f32) {
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            (7.787 * t) + (16.0 / 116.0)
+        }
+    };
+    let x = f(x / WHITE.0);
+    let y = f(y / WHITE.1);
+    let z = f(z / WHITE.2);
+
+    let l = (116.0 * y) - 16.0;
+    let a = 500.0 * (x - y);
+    let b = 200.0 * (y - z);
+
+    (l, a, b)
+}
+
+/// Convert Lab to XYZ
+pub fn lab_to_xyz(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let f_inv = |t: f32| {
+        if t.powi(3) > 0.008856 {
+            t.powi(3)
+        } else {
+            (t - 16.0 / 116.0) / 7.787
+        }
+    };
+    let y = (l + 16.0) / 116.0;
+    let x = a / 500.0 + y;
+    let z = y - b / 200.0;
+
+    (f_inv(x) * WHITE.0, f_inv(y) * WHITE.1, f_inv(z) * WHITE.2)
+}
+
+/// Convert rectangular a/b coordinates to chroma and a hue in degrees
+fn to_polar(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let c = (a.powi(2) + b.powi(2)).sqrt();
+    let h = b.atan2(a).to_degrees().rem_euclid(360.0);
+    (l, c, h)
+}
+
+/// Convert chroma and a hue in degrees to rectangular a/b coordinates
+fn from_polar(l: f32, c: f32, h: f32) -> (f32, f32, f32) {
+    let (sin, cos) = h.to_radians().sin_cos();
+    (l, c * cos, c * sin)
+}
+
+/// Convert Lab to LCh
+pub fn lab_to_lch(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    to_polar(l, a, b)
+}
+
+/// Convert LCh to Lab
+pub fn lch_to_lab(l: f32, c: f32, h: f32) -> (f32, f32, f32) {
+    from_polar(l, c, h)
+}
+
+/// Convert RGB to OKLab
+///
+/// ```
+/// # use noc::color::rgb_to_oklab;
+/// let (l, a, b) = rgb_to_oklab(1.0, 0.0, 0.0);
+/// assert!((l - 0.6280).abs() < 1e-3 && (a - 0.2249).abs() < 1e-3 && (b - 0.1258).abs() < 1e-3);
+/// let (l, a, b) = rgb_to_oklab(1.0, 1.0, 1.0);
+/// assert!((l - 1.0).abs() < 1e-3 && a.abs() < 1e-3 && b.abs() < 1e-3);
+/// ```
+pub fn rgb_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+
+    // Cone responses
+    let l = (0.41222146 * r + 0.53633255 * g + 0.051445995 * b).cbrt();
+    let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+    let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+
+    (
+        0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+        1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+        0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+    )
+}
+
+/// Convert OKLab to RGB. Colors outside the sRGB gamut come back with
+/// components outside 0..1.
+///
+/// ```
+/// # use noc::color::{oklab_to_rgb, rgb_to_oklab};
+/// let (l, a, b) = rgb_to_oklab(0.9, 0.5, 0.1);
+/// let (r, g, b) = oklab_to_rgb(l, a, b);
+/// assert!((r - 0.9).abs() < 1e-4 && (g - 0.5).abs() < 1e-4 && (b - 0.1).abs() < 1e-4);
+/// ```
+pub fn oklab_to_rgb(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    let l_ = (l + 0.39633778 * a + 0.21580376 * b).powi(3);
+    let m_ = (l - 0.105561346 * a - 0.06385417 * b).powi(3);
+    let s_ = (l - 0.08948418 * a - 1.2914855 * b).powi(3);
+
+    let r = 4.0767417 * l_ - 3.3077116 * m_ + 0.23096994 * s_;
+    let g = -1.268438 * l_ + 2.6097574 * m_ - 0.34131938 * s_;
+    let b = -0.0041960863 * l_ - 0.7034186 * m_ + 1.7076147 * s_;
+
+    (linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b))
+}
+
+/// Convert OKLab to OKLCh
+pub fn oklab_to_oklch(l: f32, a: f32, b: f32) -> (f32, f32, f32) {
+    to_polar(l, a, b)
+}
+
+/// Convert OKLCh to OKLab
+pub fn oklch_to_oklab(l: f32, c: f32, h: f32) -> (f32, f32, f32) {
+    from_polar(l, c, h)
+}
+
+/// Convert RGB to HSV
+///
+/// ```
+/// # use noc::color::rgb_to_hsv;
+/// assert_eq!(rgb_to_hsv(0.0, 0.5, 0.5), (180.0, 1.0, 0.5));
+/// ```
+pub fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+    let s = if max > 0.0 { chroma / max } else { 0.0 };
+    (hue(r, g, b, max, chroma), s, max)
+}
+
+/// Convert HSV to RGB
+///
+/// ```
+/// # use noc::color::hsv_to_rgb;
+/// assert_eq!(hsv_to_rgb(300.0, 1.0, 1.0), (1.0, 0.0, 1.0));
+/// ```
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let chroma = v * s;
+    from_hue(h, chroma, v - chroma)
+}
+
+/// Convert RGB to HSL
+///
+/// ```
+/// # use noc::color::rgb_to_hsl;
+/// let (h, s, l) = rgb_to_hsl(0.75, 0.25, 0.25);
+/// assert!(h.abs() < 1e-4 && (s - 0.5).abs() < 1e-4 && (l - 0.5).abs() < 1e-4);
+/// ```
+pub fn rgb_to_hsl(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let l = (max + min) / 2.0;
+    let s = if chroma > 0.0 {
+        chroma / (1.0 - (2.0 * l - 1.0).abs())
+    } else {
+        0.0
+    };
+    (hue(r, g, b, max, chroma), s, l)
+}
+
+/// Convert HSL to RGB
+///
+/// ```
+/// # use noc::color::hsl_to_rgb;
+/// assert_eq!(hsl_to_rgb(120.0, 1.0, 0.25), (0.0, 0.5, 0.0));
+/// ```
+pub fn hsl_to_rgb(h: f32, s: f32, l: f32) -> (f32, f32, f32) {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue(h, chroma, l - chroma / 2.0)
+}
+
+/// The hue shared by HSV and HSL, in degrees
+fn hue(r: f32, g: f32, b: f32, max: f32, chroma: f32) -> f32 {
+    if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        (60.0 * (g - b) / chroma).rem_euclid(360.0)
+    } else if max == g {
+        60.0 * (b - r) / chroma + 120.0
+    } else {
+        60.0 * (r - g) / chroma + 240.0
+    }
+}
+
+/// RGB with the given hue and chroma, lifted by `offset`
+fn from_hue(h: f32, chroma: f32, offset: f32) -> (f32, f32, f32) {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    (r + offset, g + offset, b + offset)
+}
+
+/// Calculate Delta E (CIE76)
+pub fn delta_e(lab1: (f32, f32, f32), lab2: (f32, f32, f32)) -> f32 {
+    let (l1, a1, b1) = lab1;
+    let (l2, a2, b2) = lab2;
+
+    ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+}
+
+/// Calculate the CIE94 color difference with the graphic arts weights.
+/// Unlike CIE76 it isn't symmetric: `lab1` is the reference color.
+///
+/// ```
+/// # use noc::color::cie94;
+/// let delta = cie94((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485));
+/// assert!((delta - 1.3950).abs() < 1e-3);
+/// ```
+pub fn cie94(lab1: (f32, f32, f32), lab2: (f32, f32, f32)) -> f32 {
+    const K1: f32 = 0.045;
+    const K2: f32 = 0.015;
+
+    let (l1, a1, b1) = lab1;
+    let (l2, a2, b2) = lab2;
+    let c1 = (a1.powi(2) + b1.powi(2)).sqrt();
+    let c2 = (a2.powi(2) + b2.powi(2)).sqrt();
+
+    let delta_l = l1 - l2;
+    let delta_c = c1 - c2;
+    // What's left of the Euclidean difference once lightness and chroma are
+    // accounted for is the hue difference
+    let delta_h_squared = ((a1 - a2).powi(2) + (b1 - b2).powi(2) - delta_c.powi(2)).max(0.0);
+
+    let s_c = 1.0 + K1 * c1;
+    let s_h = 1.0 + K2 * c1;
+    (delta_l.powi(2) + (delta_c / s_c).powi(2) + delta_h_squared / s_h.powi(2)).sqrt()
+}
+
+/// Calculate the CIEDE2000 color difference, which corrects Delta E for the
+/// eye's uneven sensitivity to lightness, chroma and hue
+///
+/// Checked against pairs from Sharma, Wu and Dalal's test data:
+///
+/// ```
+/// # use noc::color::ciede2000;
+/// let pairs = [
+///     ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+///     ((50.0, 2.5, 0.0), (50.0, 0.0, -2.5), 4.3065),
+///     ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+///     ((60.2574, -34.0099, 36.2677), (60.4626, -34.1751, 39.4387), 1.2644),
+///     ((22.7233, 20.0904, -46.694), (23.0331, 14.973, -42.5619), 2.0373),
+///     ((90.9257, -0.5406, -0.9208), (88.6381, -0.8985, -0.7239), 1.5381),
+/// ];
+/// for (lab1, lab2, expected) in pairs {
+///     assert!((ciede2000(lab1, lab2) - expected).abs() < 1e-3);
+/// }
+/// ```
+pub fn ciede2000(lab1: (f32, f32, f32), lab2: (f32, f32, f32)) -> f32 {
+    let (l1, a1, b1) = lab1;
+    let (l2, a2, b2) = lab2;
+
+    // Stretch the a axis to make near-neutral colors more even
+    let c_bar = ((a1.powi(2) + b1.powi(2)).sqrt() + (a2.powi(2) + b2.powi(2)).sqrt()) / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + 25f32.powi(7))).sqrt());
+    let a1 = a1 * (1.0 + g);
+    let a2 = a2 * (1.0 + g);
+    let c1 = (a1.powi(2) + b1.powi(2)).sqrt();
+    let c2 = (a2.powi(2) + b2.powi(2)).sqrt();
+    let hue = |a: f32, b: f32| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1 = hue(a1, b1);
+    let h2 = hue(a2, b2);
+
+    // Differences in lightness, chroma and hue
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 <= h1 {
+        h2 - h1 + 360.0
+    } else {
+        h2 - h1 - 360.0
+    };
+    let delta_big_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+    // Means used by the weighting functions
+    let l_bar = (l1 + l2) / 2.0;
+    let c_bar = (c1 + c2) / 2.0;
+    let h_bar = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (h_bar - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar).to_radians().cos()
+        + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+    let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar;
+    let s_h = 1.0 + 0.015 * c_bar * t;
+
+    // Rotate the chroma and hue terms in the blue region
+    let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar.powi(7) / (c_bar.powi(7) + 25f32.powi(7))).sqrt();
+    let r_t = -r_c * (2.0 * delta_theta).to_radians().sin();
+
+    let l_term = delta_l / s_l;
+    let c_term = delta_c / s_c;
+    let h_term = delta_big_h / s_h;
+    (l_term.powi(2) + c_term.powi(2) + h_term.powi(2) + r_t * c_term * h_term).sqrt()
+}
@@ -0,0 +1,88 @@
+use nannou::prelude::*;
+
+use crate::mover::wrap;
+use crate::simulation::Simulation;
+use crate::steering::{FlockRadii, FlockWeights, Vehicle};
+
+/// How a vehicle's combined steering is limited to its maximum
+/// acceleration.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SteeringLimit {
+    /// Always steer at the maximum acceleration, in the combined direction.
+    Constant,
+    /// Steer as the behaviours ask, up to the maximum acceleration.
+    Clamped,
+}
+
+impl SteeringLimit {
+    fn apply(self, steering: Vec2, max_acceleration: f32) -> Vec2 {
+        match self {
+            SteeringLimit::Constant => steering.normalize_or_zero() * max_acceleration,
+            SteeringLimit::Clamped => steering.clamp_length_max(max_acceleration),
+        }
+    }
+}
+
+/// A flock of vehicles, optionally chasing a target and wrapping around
+/// `bounds`.
+pub struct Flock {
+    pub vehicles: Vec<Vehicle>,
+    pub target: Option<Point2>,
+    pub weights: FlockWeights,
+    pub radii: FlockRadii,
+    pub bounds: Option<Rect>,
+    pub steering: SteeringLimit,
+}
+
+impl Flock {
+    pub fn new(vehicles: Vec<Vehicle>, weights: FlockWeights) -> Self {
+        Flock {
+            vehicles,
+            target: None,
+            weights,
+            radii: FlockRadii::default(),
+            bounds: None,
+            steering: SteeringLimit::Clamped,
+        }
+    }
+}
+
+impl Simulation for Flock {
+    type State = Vec<Vehicle>;
+
+    fn step(&mut self, _dt: f32) {
+        let vehicles = self.vehicles.clone();
+        for vehicle in self.vehicles.iter_mut() {
+            let total_accel = vehicle.flock(&vehicles, self.target, self.weights, self.radii);
+            let max_acceleration = vehicle.max_acceleration;
+            vehicle.apply_force(self.steering.apply(total_accel, max_acceleration));
+            vehicle.update();
+            if let Some(bounds) = self.bounds {
+                wrap(&mut vehicle.position, bounds);
+            }
+        }
+    }
+
+    fn state(&self) -> &Vec<Vehicle> {
+        &self.vehicles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_steering_always_uses_max_acceleration() {
+        let limit = SteeringLimit::Constant;
+        assert!((limit.apply(vec2(0.3, 0.4), 0.1) - vec2(0.06, 0.08)).length() < 1e-6);
+        assert_eq!(limit.apply(vec2(0.0, 0.0), 0.1), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn clamped_steering_only_shortens() {
+        let limit = SteeringLimit::Clamped;
+        assert_eq!(limit.apply(vec2(0.03, 0.04), 0.1), vec2(0.03, 0.04));
+        assert!((limit.apply(vec2(3.0, 4.0), 0.1).length() - 0.1).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,203 @@
+use itertools::Itertools;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A massive, optionally charged body integrated with a leapfrog scheme.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Body {
+    pub position: Point2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+    pub mass: f32,
+    pub charge: f32,
+    pub net_force: Vec2,
+    pub alive: bool,
+    pub color: Rgb,
+}
+
+impl Body {
+    pub fn new(position: Vec2, velocity: Vec2, acceleration: Vec2, mass: f32) -> Self {
+        Body {
+            position,
+            velocity,
+            acceleration,
+            mass,
+            charge: 0.0,
+            net_force: vec2(0.0, 0.0),
+            alive: true,
+            color: rgb(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn apply_force(&mut self, force: Vec2) {
+        self.net_force += force;
+    }
+
+    pub fn reset_force(&mut self) {
+        self.net_force = vec2(0.0, 0.0);
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.mass.log2()
+    }
+
+    /// Turn the accumulated force into half a step of velocity change.
+    pub fn kick(&mut self, delta_t: f32) {
+        self.acceleration = self.net_force / self.mass;
+        self.velocity += self.acceleration * delta_t / 2.0;
+        self.reset_force();
+    }
+
+    pub fn drift(&mut self, delta_t: f32) {
+        self.position += self.velocity * delta_t;
+    }
+
+    /// Absorb `other` into this body, conserving mass and charge.
+    fn absorb(&mut self, other: &mut Body) {
+        self.mass += other.mass;
+        self.charge += other.charge;
+        other.alive = false;
+    }
+}
+
+/// Newtonian attraction felt by `body1` towards `body2`.
+pub fn gravity(g: f32, body1: &Body, body2: &Body) -> Vec2 {
+    let displacement: Vec2 = body2.position - body1.position;
+    g * (body1.mass * body2.mass) / displacement.length().powi(2) * displacement.normalize()
+}
+
+/// Coulomb force felt by `body1` from `body2`; like charges repel.
+pub fn electric(k: f32, body1: &Body, body2: &Body) -> Vec2 {
+    let displacement: Vec2 = body2.position - body1.position;
+    k * (body1.charge * body2.charge) / displacement.length().powi(2) * -displacement.normalize()
+}
+
+/// Attraction towards a fixed mass sitting at the origin.
+pub fn central_gravity(g: f32, central_mass: f32, body: &Body) -> Vec2 {
+    -g * body.mass * central_mass / body.position.length().powi(2) * body.position.normalize()
+}
+
+/// Apply `force` between every pair of bodies, skipping pairs closer than
+/// `min_distance` to avoid dividing by zero.
+pub fn apply_pairwise<F>(bodies: &mut [Body], min_distance: f32, force: F)
+where
+    F: Fn(&Body, &Body) -> Vec2,
+{
+    for (i, j) in (0..bodies.len()).tuple_combinations() {
+        let (left, right) = bodies.split_at_mut(j);
+        let body1: &mut Body = &mut left[i];
+        let body2: &mut Body = &mut right[0];
+        let displacement: Vec2 = body2.position - body1.position;
+        if displacement.length() <= min_distance {
+            continue;
+        }
+        let f = force(body1, body2);
+        body1.apply_force(f);
+        body2.apply_force(-f);
+    }
+}
+
+/// Like [`apply_pairwise`], but overlapping bodies merge into the heavier one
+/// instead of interacting.
+pub fn apply_pairwise_with_merging<F>(bodies: &mut [Body], force: F)
+where
+    F: Fn(&Body, &Body) -> Vec2,
+{
+    for (i, j) in (0..bodies.len()).tuple_combinations() {
+        let (left, right) = bodies.split_at_mut(j);
+        let body1: &mut Body = &mut left[i];
+        let body2: &mut Body = &mut right[0];
+        if !body1.alive || !body2.alive {
+            continue;
+        }
+        let distance = (body2.position - body1.position).length();
+        if distance <= body1.radius() || distance <= body2.radius() {
+            if body1.mass > body2.mass {
+                body1.absorb(body2);
+            } else {
+                body2.absorb(body1);
+            }
+            continue;
+        }
+        let f = force(body1, body2);
+        body1.apply_force(f);
+        body2.apply_force(-f);
+    }
+}
+
+pub fn remove_dead(bodies: &mut Vec<Body>) {
+    bodies.retain(|b| b.alive);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(x: f32, y: f32, mass: f32) -> Body {
+        Body::new(vec2(x, y), vec2(0.0, 0.0), vec2(0.0, 0.0), mass)
+    }
+
+    #[test]
+    fn gravity_follows_inverse_square_law() {
+        let (a, b) = (body(0.0, 0.0, 2.0), body(2.0, 0.0, 3.0));
+        let force = gravity(1.0, &a, &b);
+        assert!((force - vec2(1.5, 0.0)).length() < 1e-6);
+        assert!((gravity(1.0, &b, &a) + force).length() < 1e-6);
+    }
+
+    #[test]
+    fn like_charges_repel_and_unlike_attract() {
+        let (mut a, mut b) = (body(0.0, 0.0, 1.0), body(2.0, 0.0, 1.0));
+        a.charge = 1.0;
+        b.charge = 2.0;
+        assert!((electric(1.0, &a, &b) - vec2(-0.5, 0.0)).length() < 1e-6);
+        b.charge = -2.0;
+        assert!((electric(1.0, &a, &b) - vec2(0.5, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn pairwise_forces_are_equal_and_opposite() {
+        let mut bodies = vec![
+            body(0.0, 0.0, 1.0),
+            body(1.0, 0.0, 1.0),
+            body(0.0, 1.0, 1.0),
+        ];
+        apply_pairwise(&mut bodies, 0.0, |a, b| gravity(1.0, a, b));
+        let total = bodies
+            .iter()
+            .fold(vec2(0.0, 0.0), |total, b| total + b.net_force);
+        assert!(total.length() < 1e-6);
+        assert!(bodies[0].net_force.x > 0.0 && bodies[0].net_force.y > 0.0);
+    }
+
+    #[test]
+    fn pairwise_skips_bodies_closer_than_min_distance() {
+        let mut bodies = vec![body(0.0, 0.0, 1.0), body(0.5, 0.0, 1.0)];
+        apply_pairwise(&mut bodies, 1.0, |a, b| gravity(1.0, a, b));
+        assert!(bodies.iter().all(|b| b.net_force == vec2(0.0, 0.0)));
+    }
+
+    #[test]
+    fn overlapping_bodies_merge_into_the_heavier() {
+        let mut light = body(1.0, 0.0, 4.0);
+        light.charge = 1.0;
+        let mut heavy = body(0.0, 0.0, 16.0);
+        heavy.charge = 2.0;
+        let mut bodies = vec![light, heavy, body(1000.0, 0.0, 2.0)];
+        apply_pairwise_with_merging(&mut bodies, |a, b| gravity(1.0, a, b));
+        assert!(!bodies[0].alive);
+        assert_eq!(bodies[1].mass, 20.0);
+        assert_eq!(bodies[1].charge, 3.0);
+        remove_dead(&mut bodies);
+        assert_eq!(bodies.len(), 2);
+    }
+
+    #[test]
+    fn distant_bodies_attract_instead_of_merging() {
+        let mut bodies = vec![body(0.0, 0.0, 4.0), body(100.0, 0.0, 4.0)];
+        apply_pairwise_with_merging(&mut bodies, |a, b| gravity(1.0, a, b));
+        assert!(bodies.iter().all(|b| b.alive));
+        assert!(bodies[0].net_force.x > 0.0);
+        assert!(bodies[1].net_force.x < 0.0);
+    }
+}
@@ -0,0 +1,17 @@
+pub mod color;
+pub mod flock;
+pub mod forces;
+pub mod life;
+pub mod mover;
+pub mod n_body;
+pub mod panel;
+pub mod particles;
+pub mod playback;
+pub mod raster;
+pub mod recorder;
+pub mod render;
+pub mod scene;
+pub mod seed;
+pub mod simulation;
+pub mod snapshot;
+pub mod steering;
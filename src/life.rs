@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::Simulation;
+
+/// Conway's Game of Life on a toroidal grid of 0/1 cells.
+#[derive(Serialize, Deserialize)]
+pub struct Life {
+    pub cells: Vec<Vec<i32>>,
+    pub generation: i32,
+}
+
+impl Life {
+    pub fn new(cells: Vec<Vec<i32>>) -> Self {
+        Life {
+            cells,
+            generation: 0,
+        }
+    }
+
+    fn neighbor_sum(&self, i: usize, j: usize) -> i32 {
+        let rows = self.cells.len() as i32;
+        let cols = self.cells[0].len() as i32;
+        let mut sum = 0;
+        for x in -1..=1i32 {
+            for y in -1..=1i32 {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+                let board_x = (i as i32 + x + rows) % rows;
+                let board_y = (j as i32 + y + cols) % cols;
+                sum += self.cells[board_x as usize][board_y as usize];
+            }
+        }
+        sum
+    }
+}
+
+pub fn rules(value: i32, neighbor_sum: i32) -> i32 {
+    if value == 1 && (neighbor_sum <= 1 || neighbor_sum >= 4) {
+        0
+    } else if value == 0 && neighbor_sum == 3 {
+        1
+    } else {
+        value
+    }
+}
+
+impl Simulation for Life {
+    type State = Vec<Vec<i32>>;
+
+    fn step(&mut self, _dt: f32) {
+        let new_cells = self
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(j, &cell)| rules(cell, self.neighbor_sum(i, j)))
+                    .collect()
+            })
+            .collect();
+        self.cells = new_cells;
+        self.generation += 1;
+    }
+
+    fn state(&self) -> &Vec<Vec<i32>> {
+        &self.cells
+    }
+}
@@ -0,0 +1,98 @@
+use nannou::prelude::*;
+
+/// A point mass that can be nudged around by changing its acceleration.
+pub struct Mover {
+    pub position: Point2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+    pub top_speed: f32,
+}
+
+impl Mover {
+    pub fn new(position: Point2, top_speed: f32) -> Self {
+        Mover {
+            position,
+            velocity: vec2(0.0, 0.0),
+            acceleration: vec2(0.0, 0.0),
+            top_speed,
+        }
+    }
+
+    pub fn accelerate(&mut self, jerk: Vec2) {
+        self.acceleration += jerk;
+    }
+
+    pub fn update(&mut self) {
+        if (self.velocity + self.acceleration).length() <= self.top_speed {
+            self.velocity += self.acceleration;
+        };
+        self.position += self.velocity;
+    }
+
+    pub fn check_edges(&mut self, bounds: Rect) {
+        wrap(&mut self.position, bounds);
+    }
+}
+
+/// Wrap a position around the edges of `bounds`, torus style.
+pub fn wrap(position: &mut Point2, bounds: Rect) {
+    if position.x > bounds.right() {
+        position.x = bounds.left();
+    } else if position.x < bounds.left() {
+        position.x = bounds.right();
+    }
+
+    if position.y > bounds.top() {
+        position.y = bounds.bottom();
+    } else if position.y < bounds.bottom() {
+        position.y = bounds.top();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds() -> Rect {
+        Rect::from_w_h(100.0, 100.0)
+    }
+
+    #[test]
+    fn wrap_moves_past_an_edge_to_the_opposite_one() {
+        let mut position = pt2(51.0, -51.0);
+        wrap(&mut position, bounds());
+        assert_eq!(position, pt2(-50.0, 50.0));
+
+        let mut position = pt2(-51.0, 51.0);
+        wrap(&mut position, bounds());
+        assert_eq!(position, pt2(50.0, -50.0));
+    }
+
+    #[test]
+    fn wrap_leaves_positions_inside_alone() {
+        let mut position = pt2(10.0, -20.0);
+        wrap(&mut position, bounds());
+        assert_eq!(position, pt2(10.0, -20.0));
+    }
+
+    #[test]
+    fn check_edges_wraps_the_mover() {
+        let mut mover = Mover::new(pt2(49.0, 0.0), 5.0);
+        mover.velocity = vec2(2.0, 0.0);
+        mover.update();
+        mover.check_edges(bounds());
+        assert_eq!(mover.position, pt2(-50.0, 0.0));
+    }
+
+    #[test]
+    fn velocity_never_exceeds_top_speed() {
+        let mut mover = Mover::new(pt2(0.0, 0.0), 5.0);
+        mover.accelerate(vec2(0.75, 0.0));
+        for _ in 0..20 {
+            mover.update();
+            assert!(mover.velocity.length() <= mover.top_speed);
+        }
+        // Acceleration that would pass the top speed is not applied
+        assert_eq!(mover.velocity, vec2(4.5, 0.0));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::forces::{self, Body};
+use crate::simulation::Simulation;
+
+/// Self-gravitating bodies orbiting a fixed central mass at the origin.
+pub struct NBody {
+    pub bodies: Vec<Body>,
+    pub g: f32,
+    pub central_mass: f32,
+}
+
+impl NBody {
+    pub fn new(bodies: Vec<Body>, g: f32, central_mass: f32) -> Self {
+        NBody {
+            bodies,
+            g,
+            central_mass,
+        }
+    }
+
+    fn apply_central_force(&mut self) {
+        for body in self.bodies.iter_mut() {
+            let central_force = forces::central_gravity(self.g, self.central_mass, body);
+            body.apply_force(central_force);
+        }
+    }
+}
+
+impl Simulation for NBody {
+    type State = Vec<Body>;
+
+    fn step(&mut self, dt: f32) {
+        let g = self.g;
+
+        // apply gravitational force between each pair of bodies
+        forces::apply_pairwise(&mut self.bodies, 1.0, |b1, b2| forces::gravity(g, b1, b2));
+        self.apply_central_force();
+        for body in self.bodies.iter_mut() {
+            body.kick(dt);
+            body.drift(dt);
+        }
+
+        // apply gravitational force again, merging bodies that collide
+        forces::apply_pairwise_with_merging(&mut self.bodies, |b1, b2| forces::gravity(g, b1, b2));
+        self.apply_central_force();
+        for body in self.bodies.iter_mut() {
+            body.kick(dt);
+        }
+
+        forces::remove_dead(&mut self.bodies);
+    }
+
+    fn state(&self) -> &Vec<Body> {
+        &self.bodies
+    }
+}
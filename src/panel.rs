@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+
+use nannou::prelude::*;
+use nannou::winit::event::WindowEvent;
+use nannou_egui::{egui, Egui};
+use serde::Serialize;
+
+use crate::scene::{self, Scene};
+use crate::steering::FlockWeights;
+
+/// A sketch setup that can be tweaked from the [`Panel`] while it runs.
+pub trait Controls {
+    /// Lay out a control for each tunable value.
+    fn ui(&mut self, ui: &mut egui::Ui);
+}
+
+/// An immediate-mode panel of controls for a sketch's setup `T`, with
+/// buttons to reset it to the values the sketch started with and to save it
+/// as a scene file.
+///
+/// The panel only appears once it's attached to a window; `Tab` hides and
+/// shows it.
+pub struct Panel<T> {
+    egui: Option<Egui>,
+    visible: bool,
+    defaults: Scene<T>,
+    path: String,
+    status: String,
+}
+
+impl<T: Controls + Clone + Serialize> Panel<T> {
+    /// A panel that resets to `defaults` and saves to `path` unless the path
+    /// is edited.
+    pub fn new(defaults: Scene<T>, path: PathBuf) -> Self {
+        Panel {
+            egui: None,
+            visible: true,
+            defaults,
+            path: path.display().to_string(),
+            status: String::new(),
+        }
+    }
+
+    pub fn attach(&mut self, window: &Window) {
+        self.egui = Some(Egui::from_window(window));
+    }
+
+    /// Pass window events on to the panel. Call this from the window's
+    /// `raw_event` function.
+    pub fn raw_event(&mut self, event: &WindowEvent) {
+        if let Some(egui) = &mut self.egui {
+            egui.handle_raw_event(event);
+        }
+    }
+
+    /// Whether a text field has focus, so the sketch should ignore key
+    /// presses.
+    pub fn wants_keyboard(&self) -> bool {
+        self.egui
+            .as_ref()
+            .is_some_and(|egui| egui.ctx().wants_keyboard_input())
+    }
+
+    /// Hide or show the panel when `Tab` is pressed.
+    pub fn key_pressed(&mut self, key: Key) {
+        if key == Key::Tab && !self.wants_keyboard() {
+            self.visible = !self.visible;
+        }
+    }
+
+    /// Lay out the panel for this frame, applying any changes to `setup`.
+    pub fn update(&mut self, update: &Update, setup: &mut T) {
+        let Some(egui) = &mut self.egui else {
+            return;
+        };
+        egui.set_elapsed_time(update.since_start);
+        if !self.visible {
+            return;
+        }
+        let ctx = egui.begin_frame();
+        egui::Window::new("Settings").show(&ctx, |ui| {
+            setup.ui(ui);
+            ui.separator();
+            if ui.button("Reset").clicked() {
+                *setup = self.defaults.sketch.clone();
+                self.status.clear();
+            }
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.path);
+                if ui.button("Save").clicked() {
+                    let scene = Scene {
+                        window: self.defaults.window,
+                        seed: self.defaults.seed,
+                        sketch: setup.clone(),
+                    };
+                    self.status = match scene::save(self.path.as_ref(), &scene) {
+                        Ok(()) => format!("saved {}", self.path),
+                        Err(err) => format!("failed to save: {}", err),
+                    };
+                }
+            });
+            if !self.status.is_empty() {
+                ui.label(&self.status);
+            }
+        });
+    }
+
+    /// Draw the panel over the sketch. Call this from `view` after drawing
+    /// the frame.
+    pub fn draw_to_frame(&self, frame: &Frame) {
+        if let (Some(egui), true) = (&self.egui, self.visible) {
+            if let Err(err) = egui.draw_to_frame(frame) {
+                eprintln!("failed to draw panel: {:?}", err);
+            }
+        }
+    }
+}
+
+impl Controls for FlockWeights {
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.seek, 0.0..=5.0).text("seek"));
+        ui.add(egui::Slider::new(&mut self.separation, 0.0..=5.0).text("separation"));
+        ui.add(egui::Slider::new(&mut self.align, 0.0..=5.0).text("align"));
+        ui.add(egui::Slider::new(&mut self.cohere, 0.0..=5.0).text("cohere"));
+    }
+}
@@ -0,0 +1,109 @@
+use nannou::prelude::*;
+
+const LIFE_SPAN: f32 = 255.0;
+const DECAY: f32 = 2.0;
+
+pub struct Particle {
+    pub position: Point2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+    pub life_span: f32,
+}
+
+impl Particle {
+    pub fn new(position: Point2, velocity: Vec2, acceleration: Vec2) -> Self {
+        Particle {
+            position,
+            velocity,
+            acceleration,
+            life_span: LIFE_SPAN,
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.velocity += self.acceleration;
+        self.position -= self.velocity;
+        self.life_span -= DECAY;
+    }
+
+    /// Remaining life as an opacity in 0..1.
+    pub fn alpha(&self) -> f32 {
+        self.life_span / LIFE_SPAN
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.life_span < 0.0
+    }
+}
+
+#[derive(Default)]
+pub struct ParticleSystem {
+    pub particles: Vec<Particle>,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+        }
+    }
+
+    pub fn emit(&mut self, particle: Particle) {
+        self.particles.push(particle);
+    }
+
+    /// Advance every particle and drop the ones that have faded out.
+    pub fn update(&mut self) {
+        for particle in self.particles.iter_mut() {
+            particle.update();
+        }
+        self.particles.retain(|p| !p.is_dead());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle() -> Particle {
+        Particle::new(pt2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 0.5))
+    }
+
+    #[test]
+    fn particles_fade_as_they_age() {
+        let mut particle = particle();
+        assert_eq!(particle.alpha(), 1.0);
+        particle.update();
+        assert_eq!(particle.life_span, LIFE_SPAN - DECAY);
+        assert!(particle.alpha() < 1.0);
+        assert_eq!(particle.velocity, vec2(1.0, 0.5));
+    }
+
+    #[test]
+    fn particles_die_once_their_life_is_spent() {
+        let mut particle = particle();
+        let updates = (LIFE_SPAN / DECAY) as usize;
+        for _ in 0..updates {
+            particle.update();
+        }
+        assert!(!particle.is_dead());
+        particle.update();
+        assert!(particle.is_dead());
+    }
+
+    #[test]
+    fn system_drops_dead_particles() {
+        let mut system = ParticleSystem::new();
+        system.emit(particle());
+        for _ in 0..100 {
+            system.update();
+        }
+        system.emit(particle());
+        assert_eq!(system.particles.len(), 2);
+        for _ in 0..40 {
+            system.update();
+        }
+        assert_eq!(system.particles.len(), 1);
+        assert!(system.particles[0].alpha() > 0.0);
+    }
+}
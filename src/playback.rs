@@ -0,0 +1,120 @@
+use nannou::prelude::*;
+
+use crate::render::{Renderer, Style};
+
+/// The fastest and slowest speeds `]` and `[` can reach.
+const MAX_SPEED: f32 = 64.0;
+const MIN_SPEED: f32 = 1.0 / 64.0;
+
+/// Pause, single-step and speed controls shared by every sketch. Call
+/// [`ticks`](Playback::ticks) once a frame and step the sketch that many
+/// times.
+///
+/// `P` pauses and resumes, `.` pauses and advances a single tick, `]`
+/// doubles the speed by running several ticks a frame, `[` halves it by
+/// running a tick every few frames, and `=` returns to normal speed.
+pub struct Playback {
+    paused: bool,
+    step: bool,
+    speed: f32,
+    /// The fraction of a tick carried over from earlier frames.
+    owed: f32,
+    title: Option<String>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            paused: false,
+            step: false,
+            speed: 1.0,
+            owed: 0.0,
+            title: None,
+        }
+    }
+}
+
+impl Playback {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Ticks run per frame.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn key_pressed(&mut self, key: Key) {
+        match key {
+            Key::P => self.paused = !self.paused,
+            Key::Period => {
+                self.paused = true;
+                self.step = true;
+            }
+            Key::RBracket => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+            Key::LBracket => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+            Key::Equals => {
+                self.speed = 1.0;
+                self.owed = 0.0;
+            }
+            _ => {}
+        }
+    }
+
+    /// The number of ticks to run this frame.
+    pub fn ticks(&mut self) -> usize {
+        if self.paused {
+            return std::mem::take(&mut self.step) as usize;
+        }
+        self.owed += self.speed;
+        let ticks = self.owed.floor();
+        self.owed -= ticks;
+        ticks as usize
+    }
+
+    /// A short description of the playback state, or `None` when the sketch
+    /// is running at normal speed.
+    pub fn label(&self) -> Option<String> {
+        if self.paused {
+            Some("paused".to_string())
+        } else if self.speed > 1.0 {
+            Some(format!("{}x", self.speed))
+        } else if self.speed < 1.0 {
+            Some(format!("1/{}x", 1.0 / self.speed))
+        } else {
+            None
+        }
+    }
+
+    /// Draw the indicator in the top right corner of `bounds`.
+    pub fn render<R: Renderer>(&self, renderer: &mut R, bounds: Rect) {
+        let Some(label) = self.label() else {
+            return;
+        };
+        let (w, h) = (90.0, 28.0);
+        let center = pt2(bounds.right() - w / 2.0 - 8.0, bounds.top() - h / 2.0 - 8.0);
+        renderer.rect(center, w, h, 0.0, Style::fill(rgba(0.0, 0.0, 0.0, 0.6)));
+        renderer.text(&label, center, 16, WHITE);
+    }
+
+    /// Show the indicator in the window title instead, for sketches that
+    /// never clear the frame and so can't have it drawn over them.
+    pub fn show_in_title(&mut self, app: &App) {
+        let label = self.label();
+        if label == self.title {
+            return;
+        }
+        let exe = std::env::current_exe().ok();
+        let name = exe
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("sketch");
+        let title = match &label {
+            Some(label) => format!("nannou - {} ({})", name, label),
+            None => format!("nannou - {}", name),
+        };
+        app.main_window().set_title(&title);
+        self.title = label;
+    }
+}
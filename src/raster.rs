@@ -0,0 +1,310 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use image::{ImageResult, Rgba, RgbaImage};
+use nannou::color::{IntoLinSrgba, LinSrgba, Srgba};
+use nannou::prelude::*;
+use nannou::text::{font, rt, Font, Scale};
+
+use crate::render::{Renderer, Style};
+
+/// A CPU renderer that draws into an RGBA image instead of a window.
+///
+/// Pixels persist between frames, so sketches that only paint their
+/// background on the first frame leave trails just like they do on screen.
+pub struct Canvas {
+    image: RgbaImage,
+}
+
+// The font for text, parsed once and shared by every canvas
+fn font() -> &'static Font {
+    static FONT: OnceLock<Font> = OnceLock::new();
+    FONT.get_or_init(font::default_notosans)
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Canvas {
+            image: RgbaImage::new(width, height),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.image
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.image.save(path)
+    }
+
+    /// Convert sketch coordinates to (fractional) pixel coordinates.
+    fn to_pixel(&self, p: Point2) -> Point2 {
+        pt2(
+            p.x + self.width() as f32 / 2.0,
+            self.height() as f32 / 2.0 - p.y,
+        )
+    }
+
+    /// Blend `color` over the pixel at `(x, y)` with extra `coverage`.
+    fn blend(&mut self, x: i64, y: i64, color: LinSrgba, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width() as i64 || y >= self.height() as i64 {
+            return;
+        }
+        let src: Srgba = Srgba::from_linear(color);
+        let alpha = (src.alpha * coverage).clamp(0.0, 1.0);
+        if alpha <= 0.0 {
+            return;
+        }
+        let dst = self.image.get_pixel_mut(x as u32, y as u32);
+        let [r, g, b, a] = dst.0;
+        let mix = |s: f32, d: u8| (s * 255.0 * alpha + d as f32 * (1.0 - alpha)).round() as u8;
+        *dst = Rgba([
+            mix(src.red, r),
+            mix(src.green, g),
+            mix(src.blue, b),
+            ((alpha + a as f32 / 255.0 * (1.0 - alpha)) * 255.0).round() as u8,
+        ]);
+    }
+
+    /// Pixel-space bounding box of `points`, clipped to the image.
+    fn bounds(&self, points: &[Point2]) -> (i64, i64, i64, i64) {
+        let min_x = points.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let max_x = points.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let max_y = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        (
+            (min_x.floor() as i64).max(0),
+            (max_x.ceil() as i64).min(self.width() as i64 - 1),
+            (min_y.floor() as i64).max(0),
+            (max_y.ceil() as i64).min(self.height() as i64 - 1),
+        )
+    }
+
+    /// Fill a polygon given in pixel coordinates using the even-odd rule,
+    /// sampling at pixel centres.
+    fn fill_polygon(&mut self, points: &[Point2], color: LinSrgba) {
+        if points.len() < 3 {
+            return;
+        }
+        let (x0, x1, y0, y1) = self.bounds(points);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if contains(points, pt2(x as f32 + 0.5, y as f32 + 0.5)) {
+                    self.blend(x, y, color, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Fill a `weight` wide band around each segment of a pixel-space path.
+    /// The bands are gathered into one coverage mask and blended once, so a
+    /// translucent stroke is not drawn twice where its segments meet.
+    fn stroke_path(&mut self, points: &[Point2], weight: f32, color: LinSrgba, closed: bool) {
+        let half_weight = weight.max(1.0) / 2.0;
+        let segment_count = if closed {
+            points.len()
+        } else {
+            points.len().saturating_sub(1)
+        };
+        let bands: Vec<[Point2; 4]> = (0..segment_count)
+            .map(|i| {
+                let start = points[i];
+                let end = points[(i + 1) % points.len()];
+                let normal =
+                    vec2(start.y - end.y, end.x - start.x).normalize_or_zero() * half_weight;
+                [start + normal, end + normal, end - normal, start - normal]
+            })
+            .collect();
+        if bands.is_empty() {
+            return;
+        }
+
+        let corners: Vec<Point2> = bands.iter().flatten().copied().collect();
+        let (x0, x1, y0, y1) = self.bounds(&corners);
+        if x0 > x1 || y0 > y1 {
+            return;
+        }
+        let columns = (x1 - x0 + 1) as usize;
+        let mut coverage = vec![0.0f32; columns * (y1 - y0 + 1) as usize];
+        for band in &bands {
+            let (bx0, bx1, by0, by1) = self.bounds(band);
+            for y in by0..=by1 {
+                for x in bx0..=bx1 {
+                    if contains(band, pt2(x as f32 + 0.5, y as f32 + 0.5)) {
+                        let cell = &mut coverage[(y - y0) as usize * columns + (x - x0) as usize];
+                        *cell = cell.max(1.0);
+                    }
+                }
+            }
+        }
+        for (i, &cover) in coverage.iter().enumerate() {
+            if cover > 0.0 {
+                let (x, y) = ((i % columns) as i64 + x0, (i / columns) as i64 + y0);
+                self.blend(x, y, color, cover);
+            }
+        }
+    }
+
+    fn paint_polygon(&mut self, points: &[Point2], style: Style) {
+        let points: Vec<Point2> = points.iter().map(|&p| self.to_pixel(p)).collect();
+        if let Some(fill) = style.fill {
+            self.fill_polygon(&points, fill);
+        }
+        if let Some(stroke) = style.stroke {
+            self.stroke_path(&points, style.stroke_weight, stroke, true);
+        }
+    }
+}
+
+/// Even-odd point in polygon test.
+fn contains(points: &[Point2], p: Point2) -> bool {
+    let mut inside = false;
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[j]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+impl Renderer for Canvas {
+    fn background<C: IntoLinSrgba<f32>>(&mut self, color: C) {
+        let mut color: Srgba = Srgba::from_linear(color.into_lin_srgba());
+        color.alpha = 1.0;
+        let pixel = Rgba([
+            (color.red * 255.0).round() as u8,
+            (color.green * 255.0).round() as u8,
+            (color.blue * 255.0).round() as u8,
+            255,
+        ]);
+        for p in self.image.pixels_mut() {
+            *p = pixel;
+        }
+    }
+
+    fn ellipse(&mut self, center: Point2, w: f32, h: f32, style: Style) {
+        let c = self.to_pixel(center);
+        let (rx, ry) = (w / 2.0, h / 2.0);
+        let half_stroke = match style.stroke {
+            Some(_) => style.stroke_weight / 2.0,
+            None => 0.0,
+        };
+        let (outer_x, outer_y) = (rx + half_stroke, ry + half_stroke);
+        let (x0, x1, y0, y1) =
+            self.bounds(&[c - vec2(outer_x, outer_y), c + vec2(outer_x, outer_y)]);
+        let inside = |p: Point2, rx: f32, ry: f32| {
+            rx > 0.0 && ry > 0.0 && ((p.x - c.x) / rx).powi(2) + ((p.y - c.y) / ry).powi(2) <= 1.0
+        };
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let p = pt2(x as f32 + 0.5, y as f32 + 0.5);
+                if let Some(fill) = style.fill {
+                    if inside(p, rx, ry) {
+                        self.blend(x, y, fill, 1.0);
+                    }
+                }
+                if let Some(stroke) = style.stroke {
+                    let in_outer = inside(p, outer_x, outer_y);
+                    let in_inner = inside(p, rx - half_stroke, ry - half_stroke);
+                    if in_outer && !in_inner {
+                        self.blend(x, y, stroke, 1.0);
+                    }
+                }
+            }
+        }
+    }
+
+    fn rect(&mut self, center: Point2, w: f32, h: f32, rotation: f32, style: Style) {
+        let corners = [
+            vec2(-w / 2.0, -h / 2.0),
+            vec2(w / 2.0, -h / 2.0),
+            vec2(w / 2.0, h / 2.0),
+            vec2(-w / 2.0, h / 2.0),
+        ];
+        let points: Vec<Point2> = corners
+            .iter()
+            .map(|c| center + c.rotate(rotation))
+            .collect();
+        self.paint_polygon(&points, style);
+    }
+
+    fn tri(&mut self, points: [Point2; 3], style: Style) {
+        self.paint_polygon(&points, style);
+    }
+
+    fn polyline<C: IntoLinSrgba<f32>>(&mut self, points: &[Point2], weight: f32, color: C) {
+        let points: Vec<Point2> = points.iter().map(|&p| self.to_pixel(p)).collect();
+        self.stroke_path(&points, weight, color.into_lin_srgba(), false);
+    }
+
+    fn text<C: IntoLinSrgba<f32>>(
+        &mut self,
+        text: &str,
+        position: Point2,
+        font_size: u32,
+        color: C,
+    ) {
+        let color = color.into_lin_srgba();
+        let scale = Scale::uniform(font_size as f32);
+        let v_metrics = font().v_metrics(scale);
+        let glyphs: Vec<_> = font()
+            .layout(text, scale, rt::point(0.0, v_metrics.ascent))
+            .collect();
+        let width = glyphs
+            .iter()
+            .filter_map(|g| g.pixel_bounding_box())
+            .map(|bb| bb.max.x)
+            .max()
+            .unwrap_or(0) as f32;
+        let height = v_metrics.ascent - v_metrics.descent;
+        let origin = self.to_pixel(position) - vec2(width / 2.0, height / 2.0);
+        let mut coverage = Vec::new();
+        for glyph in glyphs.iter() {
+            if let Some(bb) = glyph.pixel_bounding_box() {
+                glyph.draw(|x, y, v| {
+                    coverage.push((
+                        origin.x as i64 + bb.min.x as i64 + x as i64,
+                        origin.y as i64 + bb.min.y as i64 + y as i64,
+                        v,
+                    ));
+                });
+            }
+        }
+        for (x, y, v) in coverage {
+            self.blend(x, y, color, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translucent_polyline_is_blended_once_at_joints() {
+        let mut canvas = Canvas::new(40, 40);
+        canvas.background(BLACK);
+        let points = [pt2(-15.0, 0.0), pt2(0.0, 0.0), pt2(0.0, 15.0)];
+        canvas.polyline(&points, 4.0, rgba(1.0, 1.0, 1.0, 0.5));
+        // The corner, the middle of the first segment and of the second
+        let joint = canvas.image().get_pixel(19, 19);
+        assert_eq!(joint, canvas.image().get_pixel(10, 19));
+        assert_eq!(joint, canvas.image().get_pixel(19, 10));
+        assert!(joint.0[0] > 0);
+    }
+}
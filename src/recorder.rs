@@ -0,0 +1,304 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageError, ImageResult, RgbaImage};
+use nannou::prelude::*;
+
+use crate::raster::Canvas;
+
+/// nannou's default loop rate, used to time the exported animations.
+const FRAMES_PER_SECOND: u64 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+    None,
+}
+
+/// Command-line options shared by every sketch for capturing frames.
+#[derive(Args, Clone)]
+pub struct RecordArgs {
+    /// Render this many frames offscreen, record them and exit, instead of
+    /// opening a window.
+    #[arg(long, value_name = "FRAMES")]
+    pub headless: Option<u64>,
+
+    /// Capture every Nth frame while recording.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub record_every: u64,
+
+    /// Directory the numbered PNGs and animations are written to.
+    #[arg(long, value_name = "DIR", default_value = "frames")]
+    pub record_dir: PathBuf,
+
+    /// Animation assembled from the PNG sequence when recording stops.
+    #[arg(long, value_enum, default_value_t = AnimationFormat::Gif)]
+    pub animation: AnimationFormat,
+}
+
+/// Captures every Nth frame of a sketch to a numbered PNG sequence and
+/// assembles each recording session into an animation when it stops. The
+/// animation is encoded a frame at a time from the PNGs, so long recordings
+/// are never held in memory.
+///
+/// In a window, `R` starts and stops a session.
+pub struct Recorder {
+    directory: PathBuf,
+    every: u64,
+    format: AnimationFormat,
+    recording: bool,
+    session: usize,
+    frame: u64,
+    captured: Vec<PathBuf>,
+    names: Option<(String, u64)>, // Prefix and first frame number of the PNGs
+}
+
+impl Recorder {
+    pub fn new(args: &RecordArgs) -> Self {
+        Recorder {
+            directory: args.record_dir.clone(),
+            every: args.record_every.max(1),
+            format: args.animation,
+            recording: false,
+            session: 0,
+            frame: 0,
+            captured: Vec::new(),
+            names: None,
+        }
+    }
+
+    /// Name the PNGs `{prefix}_{n}.png`, with `n` the frame number counting
+    /// from `first`, rather than by session and capture order. Every session
+    /// writes to the same names.
+    pub fn frame_names(mut self, prefix: &str, first: u64) -> Self {
+        self.names = Some((prefix.to_string(), first));
+        self
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn start(&mut self) -> io::Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        self.recording = true;
+        self.frame = 0;
+        self.captured.clear();
+        Ok(())
+    }
+
+    /// Stop the current session and write its animation, if one was asked for.
+    pub fn stop(&mut self) -> ImageResult<()> {
+        if !self.recording {
+            return Ok(());
+        }
+        self.recording = false;
+        let result = match self.format {
+            AnimationFormat::Gif => self.write_gif(),
+            AnimationFormat::Apng => self.write_apng(),
+            AnimationFormat::None => Ok(()),
+        };
+        self.session += 1;
+        result
+    }
+
+    /// Start a session, or stop one once the window's pending captures
+    /// have been written.
+    pub fn toggle(&mut self, app: &App) -> ImageResult<()> {
+        if self.recording {
+            await_captures(app);
+            self.stop()
+        } else {
+            self.start().map_err(ImageError::IoError)
+        }
+    }
+
+    /// Start or stop recording when `R` is pressed.
+    pub fn key_pressed(&mut self, app: &App, key: Key) {
+        if key == Key::R {
+            if let Err(err) = self.toggle(app) {
+                eprintln!("recording failed: {}", err);
+            }
+        }
+    }
+
+    /// The path the current frame should be written to, if it is one of the
+    /// frames being captured.
+    fn next_path(&mut self) -> Option<PathBuf> {
+        if !self.recording {
+            return None;
+        }
+        let frame = self.frame;
+        self.frame += 1;
+        if !frame.is_multiple_of(self.every) {
+            return None;
+        }
+        let name = match &self.names {
+            Some((prefix, first)) => format!("{}_{}.png", prefix, first + frame),
+            None => format!("{:02}_{:06}.png", self.session, self.captured.len()),
+        };
+        let path = self.directory.join(name);
+        self.captured.push(path.clone());
+        Some(path)
+    }
+
+    /// Ask nannou to write the main window's next frame to disk.
+    pub fn capture_window(&mut self, app: &App) {
+        if let Some(path) = self.next_path() {
+            app.main_window().capture_frame(path);
+        }
+    }
+
+    pub fn capture(&mut self, image: &RgbaImage) -> ImageResult<()> {
+        match self.next_path() {
+            Some(path) => image.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Wait for pending window captures and close any open session. Call
+    /// this from the sketch's `exit` function.
+    pub fn finish(&mut self, app: &App) {
+        await_captures(app);
+        if let Err(err) = self.stop() {
+            eprintln!("failed to write animation: {}", err);
+        }
+    }
+
+    fn animation_path(&self, extension: &str) -> PathBuf {
+        self.directory
+            .join(format!("animation_{:02}.{}", self.session, extension))
+    }
+
+    fn frame_delay_ms(&self) -> u32 {
+        (self.every * 1000 / FRAMES_PER_SECOND) as u32
+    }
+
+    /// The captured frames that made it to disk.
+    fn frame_paths(&self) -> Vec<&PathBuf> {
+        self.captured.iter().filter(|path| path.exists()).collect()
+    }
+
+    fn write_gif(&self) -> ImageResult<()> {
+        let file = File::create(self.animation_path("gif"))?;
+        let mut encoder = GifEncoder::new(BufWriter::new(file));
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(self.frame_delay_ms(), 1);
+        for path in self.frame_paths() {
+            let image = image::open(path)?.to_rgba8();
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+        }
+        Ok(())
+    }
+
+    fn write_apng(&self) -> ImageResult<()> {
+        let frames = self.frame_paths();
+        let Some(first) = frames.first() else {
+            return Ok(());
+        };
+        let (width, height) = image::image_dimensions(first)?;
+        let file = File::create(self.animation_path("png"))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let to_image_error = |err: png::EncodingError| ImageError::IoError(io::Error::other(err));
+        encoder
+            .set_animated(frames.len() as u32, 0)
+            .map_err(to_image_error)?;
+        encoder
+            .set_frame_delay(self.frame_delay_ms() as u16, 1000)
+            .map_err(to_image_error)?;
+        let mut writer = encoder.write_header().map_err(to_image_error)?;
+        for path in frames {
+            let image = image::open(path)?.to_rgba8();
+            writer
+                .write_image_data(image.as_raw())
+                .map_err(to_image_error)?;
+        }
+        writer.finish().map_err(to_image_error)
+    }
+}
+
+/// Block until nannou has written every frame `capture_window` asked for.
+fn await_captures(app: &App) {
+    if let Err(err) = app.main_window().await_capture_frame_jobs() {
+        eprintln!("failed to write captured frames: {:?}", err);
+    }
+}
+
+/// Drive a sketch offscreen for `frames` frames on a `width` by `height`
+/// canvas, recording each one. `frame` advances the sketch and draws it.
+pub fn run_headless<F>(args: &RecordArgs, width: u32, height: u32, frames: u64, mut frame: F)
+where
+    F: FnMut(&mut Canvas, u64),
+{
+    let mut canvas = Canvas::new(width, height);
+    let mut recorder = Recorder::new(args);
+    let result = recorder.start().map_err(ImageError::IoError).and_then(|_| {
+        for nth in 0..frames {
+            frame(&mut canvas, nth);
+            recorder.capture(canvas.image())?;
+        }
+        recorder.stop()
+    });
+    if let Err(err) = result {
+        eprintln!("headless run failed: {}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(directory: &str, animation: AnimationFormat) -> RecordArgs {
+        RecordArgs {
+            headless: None,
+            record_every: 2,
+            record_dir: std::env::temp_dir().join(directory),
+            animation,
+        }
+    }
+
+    fn record(recorder: &mut Recorder, frames: u8) {
+        recorder.start().unwrap();
+        for shade in 0..frames {
+            let image = RgbaImage::from_pixel(4, 3, image::Rgba([shade, 0, 0, 255]));
+            recorder.capture(&image).unwrap();
+        }
+        recorder.stop().unwrap();
+    }
+
+    #[test]
+    fn frames_are_numbered_by_session_and_capture() {
+        let args = args("noc_recorder_numbered", AnimationFormat::Apng);
+        let _ = fs::remove_dir_all(&args.record_dir);
+        let mut recorder = Recorder::new(&args);
+        record(&mut recorder, 5);
+        for name in ["00_000000.png", "00_000001.png", "00_000002.png"] {
+            assert!(args.record_dir.join(name).exists(), "{} missing", name);
+        }
+        assert!(!args.record_dir.join("00_000003.png").exists());
+
+        let animation = fs::File::open(args.record_dir.join("animation_00.png")).unwrap();
+        let reader = png::Decoder::new(animation).read_info().unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_frames, 3);
+        assert_eq!(reader.info().size(), (4, 3));
+    }
+
+    #[test]
+    fn frame_names_keep_the_frame_number() {
+        let args = args("noc_recorder_named", AnimationFormat::Gif);
+        let _ = fs::remove_dir_all(&args.record_dir);
+        let mut recorder = Recorder::new(&args).frame_names("sketch", 100);
+        record(&mut recorder, 4);
+        for name in ["sketch_100.png", "sketch_102.png", "animation_00.gif"] {
+            assert!(args.record_dir.join(name).exists(), "{} missing", name);
+        }
+        assert!(!args.record_dir.join("sketch_101.png").exists());
+    }
+}
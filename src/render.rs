@@ -0,0 +1,147 @@
+use nannou::color::{IntoLinSrgba, LinSrgba};
+use nannou::prelude::*;
+
+/// How a closed shape is painted: an optional fill and an optional outline.
+#[derive(Clone, Copy)]
+pub struct Style {
+    pub fill: Option<LinSrgba>,
+    pub stroke: Option<LinSrgba>,
+    pub stroke_weight: f32,
+}
+
+impl Style {
+    pub fn fill<C: IntoLinSrgba<f32>>(color: C) -> Self {
+        Style {
+            fill: Some(color.into_lin_srgba()),
+            stroke: None,
+            stroke_weight: 1.0,
+        }
+    }
+
+    pub fn outline<C: IntoLinSrgba<f32>>(color: C, weight: f32) -> Self {
+        Style {
+            fill: None,
+            stroke: Some(color.into_lin_srgba()),
+            stroke_weight: weight,
+        }
+    }
+
+    pub fn with_stroke<C: IntoLinSrgba<f32>>(self, color: C, weight: f32) -> Self {
+        Style {
+            stroke: Some(color.into_lin_srgba()),
+            stroke_weight: weight,
+            ..self
+        }
+    }
+}
+
+/// The subset of nannou's `Draw` API the sketches use, so a sketch can be
+/// drawn to a window or to an offscreen [`Canvas`](crate::raster::Canvas)
+/// with the same code.
+///
+/// Coordinates follow nannou: the origin is the centre of the frame and y
+/// points up.
+pub trait Renderer {
+    fn background<C: IntoLinSrgba<f32>>(&mut self, color: C);
+
+    fn ellipse(&mut self, center: Point2, w: f32, h: f32, style: Style);
+
+    /// A `w` by `h` rectangle rotated by `rotation` radians about its centre.
+    fn rect(&mut self, center: Point2, w: f32, h: f32, rotation: f32, style: Style);
+
+    fn tri(&mut self, points: [Point2; 3], style: Style);
+
+    fn polyline<C: IntoLinSrgba<f32>>(&mut self, points: &[Point2], weight: f32, color: C);
+
+    fn line<C: IntoLinSrgba<f32>>(&mut self, start: Point2, end: Point2, weight: f32, color: C) {
+        self.polyline(&[start, end], weight, color);
+    }
+
+    /// A line with a triangular head at `end`, sized like nannou's default.
+    fn arrow<C: IntoLinSrgba<f32>>(&mut self, start: Point2, end: Point2, weight: f32, color: C) {
+        if start == end {
+            return;
+        }
+        let color = color.into_lin_srgba();
+        let direction = end - start;
+        let head_length = (weight * 4.0).min(direction.length());
+        let head_start = end - direction.normalize() * head_length;
+        let head_side = vec2(-direction.y, direction.x).normalize() * weight * 2.0;
+        if direction.length() > head_length {
+            self.line(start, head_start, weight, color);
+        }
+        self.tri(
+            [end, head_start + head_side, head_start - head_side],
+            Style::fill(color),
+        );
+    }
+
+    /// Single-line text centred on `position`.
+    fn text<C: IntoLinSrgba<f32>>(
+        &mut self,
+        text: &str,
+        position: Point2,
+        font_size: u32,
+        color: C,
+    );
+}
+
+impl Renderer for Draw {
+    fn background<C: IntoLinSrgba<f32>>(&mut self, color: C) {
+        Draw::background(self).color(color);
+    }
+
+    fn ellipse(&mut self, center: Point2, w: f32, h: f32, style: Style) {
+        let ellipse = Draw::ellipse(self).xy(center).w_h(w, h);
+        let ellipse = match style.fill {
+            Some(fill) => ellipse.color(fill),
+            None => ellipse.no_fill(),
+        };
+        if let Some(stroke) = style.stroke {
+            ellipse.stroke(stroke).stroke_weight(style.stroke_weight);
+        }
+    }
+
+    fn rect(&mut self, center: Point2, w: f32, h: f32, rotation: f32, style: Style) {
+        let rect = Draw::rect(self).xy(center).w_h(w, h).rotate(rotation);
+        let rect = match style.fill {
+            Some(fill) => rect.color(fill),
+            None => rect.no_fill(),
+        };
+        if let Some(stroke) = style.stroke {
+            rect.stroke(stroke).stroke_weight(style.stroke_weight);
+        }
+    }
+
+    fn tri(&mut self, points: [Point2; 3], style: Style) {
+        let tri = Draw::tri(self).points(points[0], points[1], points[2]);
+        let tri = match style.fill {
+            Some(fill) => tri.color(fill),
+            None => tri.no_fill(),
+        };
+        if let Some(stroke) = style.stroke {
+            tri.stroke(stroke).stroke_weight(style.stroke_weight);
+        }
+    }
+
+    fn polyline<C: IntoLinSrgba<f32>>(&mut self, points: &[Point2], weight: f32, color: C) {
+        Draw::polyline(self)
+            .weight(weight)
+            .points(points.iter().cloned())
+            .color(color);
+    }
+
+    fn text<C: IntoLinSrgba<f32>>(
+        &mut self,
+        text: &str,
+        position: Point2,
+        font_size: u32,
+        color: C,
+    ) {
+        Draw::text(self, text)
+            .xy(position)
+            .no_line_wrap()
+            .font_size(font_size)
+            .color(color);
+    }
+}
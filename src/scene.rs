@@ -0,0 +1,366 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use nannou::prelude::*;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::forces::Body;
+
+/// Command-line option for loading a sketch's setup from a scene file.
+#[derive(Args, Clone)]
+pub struct SceneArgs {
+    /// TOML or RON file describing the run. Replaces the sketch's other
+    /// parameter options.
+    #[arg(long, value_name = "FILE")]
+    pub scene: Option<PathBuf>,
+}
+
+impl SceneArgs {
+    /// Where changes to the scene are saved: the file it was loaded from, or
+    /// `default` if it came from the command line.
+    pub fn save_path(&self, default: &str) -> PathBuf {
+        self.scene.clone().unwrap_or_else(|| PathBuf::from(default))
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A run described in a file: the window, the random seed and the
+/// sketch-specific setup `T`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene<T> {
+    pub window: WindowSize,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub sketch: T,
+}
+
+impl<T> Scene<T> {
+    pub fn new(width: u32, height: u32, sketch: T) -> Self {
+        Scene {
+            window: WindowSize { width, height },
+            seed: None,
+            sketch,
+        }
+    }
+}
+
+/// Checks of a sketch's setup that its types can't express, made when a
+/// scene file is loaded so that bad values are reported rather than
+/// panicking later.
+pub trait Validate {
+    fn validate(&self) -> Result<(), SceneError> {
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned + Validate> Scene<T> {
+    /// Read a scene file and check its setup.
+    pub fn load(path: &Path) -> Result<Self, SceneError> {
+        let scene: Self = load(path)?;
+        scene.sketch.validate()?;
+        Ok(scene)
+    }
+
+    /// Load the scene named by `--scene`, or build one from the command line
+    /// with `default`. Exits with a message if the file can't be read.
+    pub fn from_args<F: FnOnce() -> Self>(args: &SceneArgs, default: F) -> Self {
+        match &args.scene {
+            Some(path) => Self::load(path).unwrap_or_else(|err| {
+                eprintln!("failed to load scene {}: {}", path.display(), err);
+                std::process::exit(1);
+            }),
+            None => default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Ron(ron::error::SpannedError),
+    TomlWrite(toml::ser::Error),
+    RonWrite(ron::Error),
+    UnknownFormat(PathBuf),
+    Invalid { field: &'static str, reason: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Toml(err) => write!(f, "{}", err),
+            SceneError::Ron(err) => write!(f, "{}", err),
+            SceneError::TomlWrite(err) => write!(f, "{}", err),
+            SceneError::RonWrite(err) => write!(f, "{}", err),
+            SceneError::UnknownFormat(path) => {
+                write!(f, "{} is not a .toml or .ron file", path.display())
+            }
+            SceneError::Invalid { field, reason } => write!(f, "invalid `{}`: {}", field, reason),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+/// Check that every one of `values` is a finite number.
+pub fn check_finite(field: &'static str, values: &[f32]) -> Result<(), SceneError> {
+    if values.iter().all(|v| v.is_finite()) {
+        return Ok(());
+    }
+    Err(SceneError::Invalid {
+        field,
+        reason: "must be a finite number".to_string(),
+    })
+}
+
+/// Check that `value` is a finite number above 0.
+pub fn check_positive(field: &'static str, value: f32) -> Result<(), SceneError> {
+    if value > 0.0 && value.is_finite() {
+        return Ok(());
+    }
+    Err(SceneError::Invalid {
+        field,
+        reason: "must be a finite number above 0".to_string(),
+    })
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+/// Read a scene, picking the format from the file extension.
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, SceneError> {
+    let text = fs::read_to_string(path)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&text).map_err(SceneError::Toml),
+        Some("ron") => ron::from_str(&text).map_err(SceneError::Ron),
+        _ => Err(SceneError::UnknownFormat(path.to_path_buf())),
+    }
+}
+
+/// Write a scene in the format given by the file extension.
+pub fn save<T: Serialize>(path: &Path, scene: &T) -> Result<(), SceneError> {
+    let text = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::to_string(scene).map_err(SceneError::TomlWrite)?,
+        Some("ron") => ron::ser::to_string_pretty(scene, ron::ser::PrettyConfig::default())
+            .map_err(SceneError::RonWrite)?,
+        _ => return Err(SceneError::UnknownFormat(path.to_path_buf())),
+    };
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// A single body placed by hand.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BodySpec {
+    pub position: [f32; 2],
+    #[serde(default)]
+    pub velocity: [f32; 2],
+    pub mass: f32,
+    #[serde(default)]
+    pub charge: f32,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+}
+
+// A massless body has no radius and takes infinite acceleration
+impl Validate for BodySpec {
+    fn validate(&self) -> Result<(), SceneError> {
+        check_finite("bodies.position", &self.position)?;
+        check_finite("bodies.velocity", &self.velocity)?;
+        check_positive("bodies.mass", self.mass)?;
+        check_finite("bodies.charge", &[self.charge])
+    }
+}
+
+impl BodySpec {
+    pub fn to_body(&self) -> Body {
+        let mut body = Body::new(
+            Vec2::from(self.position),
+            Vec2::from(self.velocity),
+            vec2(0.0, 0.0),
+            self.mass,
+        );
+        body.charge = self.charge;
+        if let Some([r, g, b]) = self.color {
+            body.color = rgb(r, g, b);
+        }
+        body
+    }
+}
+
+/// Bodies scattered uniformly over a square, with random masses, charges,
+/// velocities and colours drawn from the given ranges.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RandomBodies {
+    pub count: usize,
+    /// Half the side of the square the bodies start in.
+    pub extent: f32,
+    /// Largest starting speed along each axis.
+    pub speed: f32,
+    pub mass: [f32; 2],
+    #[serde(default)]
+    pub charge: [f32; 2],
+}
+
+impl Validate for RandomBodies {
+    fn validate(&self) -> Result<(), SceneError> {
+        let invalid = |field, reason: &str| {
+            Err(SceneError::Invalid {
+                field,
+                reason: reason.to_string(),
+            })
+        };
+        if !(self.extent >= 0.0 && self.extent.is_finite()) {
+            return invalid("random.extent", "must be a finite number of at least 0");
+        }
+        let [low, high] = self.mass;
+        if !(low > 0.0 && high.is_finite()) {
+            return invalid("random.mass", "masses must be positive and finite");
+        }
+        if low > high {
+            return invalid("random.mass", "the lower bound is above the upper");
+        }
+        Ok(())
+    }
+}
+
+impl RandomBodies {
+    pub fn generate<G: Rng>(&self, rng: &mut G) -> Vec<Body> {
+        (0..self.count)
+            .map(|_| {
+                let position = vec2(
+                    rng.gen_range(-self.extent..=self.extent),
+                    rng.gen_range(-self.extent..=self.extent),
+                );
+                let velocity = vec2(
+                    rng.gen::<f32>() * self.speed * 2.0 - self.speed,
+                    rng.gen::<f32>() * self.speed * 2.0 - self.speed,
+                );
+                let mass = rng.gen_range(self.mass[0]..=self.mass[1]);
+                let mut body = Body::new(position, velocity, vec2(0.0, 0.0), mass);
+                if self.charge[0] < self.charge[1] {
+                    body.charge = rng.gen_range(self.charge[0]..self.charge[1]);
+                }
+                body.color = rgb(rng.gen(), rng.gen(), rng.gen());
+                body
+            })
+            .collect()
+    }
+}
+
+/// Initial bodies for the force sketches: any number of hand-placed bodies
+/// plus an optional random cloud.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct BodyScene {
+    #[serde(default)]
+    pub bodies: Vec<BodySpec>,
+    #[serde(default)]
+    pub random: Option<RandomBodies>,
+}
+
+impl Validate for BodyScene {
+    fn validate(&self) -> Result<(), SceneError> {
+        self.bodies.iter().try_for_each(Validate::validate)?;
+        self.random.as_ref().map_or(Ok(()), Validate::validate)
+    }
+}
+
+impl BodyScene {
+    pub fn build<G: Rng>(&self, rng: &mut G) -> Vec<Body> {
+        let mut bodies: Vec<Body> = self.bodies.iter().map(BodySpec::to_body).collect();
+        if let Some(random) = &self.random {
+            bodies.extend(random.generate(rng));
+        }
+        bodies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_sketch(name: &str, sketch: &str) -> Result<Scene<BodyScene>, SceneError> {
+        let path = std::env::temp_dir().join(name);
+        let text = format!("[window]\nwidth = 100\nheight = 100\n\n{}", sketch);
+        fs::write(&path, text).unwrap();
+        Scene::load(&path)
+    }
+
+    fn load_random(name: &str, random: &str) -> Result<Scene<BodyScene>, SceneError> {
+        load_sketch(name, &format!("[sketch.random]\n{}", random))
+    }
+
+    fn load_body(name: &str, body: &str) -> Result<Scene<BodyScene>, SceneError> {
+        load_sketch(name, &format!("[[sketch.bodies]]\n{}", body))
+    }
+
+    fn invalid_field(result: Result<Scene<BodyScene>, SceneError>) -> &'static str {
+        match result {
+            Err(SceneError::Invalid { field, .. }) => field,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("scene should not load"),
+        }
+    }
+
+    #[test]
+    fn loads_a_valid_random_cloud() {
+        let random = "count = 3\nextent = 10.0\nspeed = 1.0\nmass = [1.0, 5.0]\n";
+        let scene = load_random("noc_scene_valid.toml", random).unwrap();
+        let bodies = scene.sketch.build(&mut crate::seed::rng(1));
+        assert_eq!(bodies.len(), 3);
+    }
+
+    #[test]
+    fn rejects_a_reversed_mass_range() {
+        let random = "count = 3\nextent = 10.0\nspeed = 1.0\nmass = [5.0, 1.0]\n";
+        let result = load_random("noc_scene_mass.toml", random);
+        assert_eq!(invalid_field(result), "random.mass");
+    }
+
+    #[test]
+    fn rejects_a_negative_extent() {
+        let random = "count = 3\nextent = -10.0\nspeed = 1.0\nmass = [1.0, 5.0]\n";
+        let result = load_random("noc_scene_extent.toml", random);
+        assert_eq!(invalid_field(result), "random.extent");
+    }
+
+    #[test]
+    fn loads_a_placed_body() {
+        let body = "position = [1.0, 2.0]\nmass = 10.0\n";
+        let scene = load_body("noc_scene_body.toml", body).unwrap();
+        assert_eq!(scene.sketch.build(&mut crate::seed::rng(1)).len(), 1);
+    }
+
+    #[test]
+    fn rejects_a_massless_or_negative_body() {
+        for (name, mass) in [
+            ("noc_scene_zero.toml", "0.0"),
+            ("noc_scene_neg.toml", "-1.0"),
+        ] {
+            let body = format!("position = [1.0, 2.0]\nmass = {}\n", mass);
+            assert_eq!(invalid_field(load_body(name, &body)), "bodies.mass");
+        }
+    }
+
+    #[test]
+    fn rejects_a_body_at_infinity() {
+        let body = "position = [inf, 2.0]\nmass = 10.0\n";
+        let result = load_body("noc_scene_inf.toml", body);
+        assert_eq!(invalid_field(result), "bodies.position");
+    }
+}
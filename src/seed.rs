@@ -0,0 +1,42 @@
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Command-line option for reproducible runs.
+#[derive(Args, Clone)]
+pub struct SeedArgs {
+    /// Seed for every random choice the sketch makes. Runs with the same seed
+    /// and options produce identical output. Without it a seed is picked and
+    /// printed.
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+/// The seed for a run: `seed` if there is one, otherwise a fresh one that is
+/// printed so the run can be repeated.
+pub fn resolve(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| {
+        let seed = rand::thread_rng().gen();
+        println!("seed: {}", seed);
+        seed
+    })
+}
+
+pub fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// An independent seed for the `index`th task of a parallel job seeded with
+/// `seed`, so the result doesn't depend on how rayon schedules the tasks.
+pub fn derive(seed: u64, index: u64) -> u64 {
+    // splitmix64 of the pair
+    let mut z = seed ^ index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A generator for the `index`th task of a parallel job seeded with `seed`.
+pub fn task_rng(seed: u64, index: u64) -> StdRng {
+    rng(derive(seed, index))
+}
@@ -0,0 +1,119 @@
+/// A sketch's update logic, independent of nannou's `App` and `Draw`, so it
+/// can be driven by a window, a test, or a batch job alike.
+pub trait Simulation {
+    /// The data a renderer needs to draw the current state.
+    type State;
+
+    /// Advance the simulation by one tick of `dt` seconds.
+    fn step(&mut self, dt: f32);
+
+    fn state(&self) -> &Self::State;
+
+    /// An owned copy of the current state.
+    fn snapshot(&self) -> Self::State
+    where
+        Self::State: Clone,
+    {
+        self.state().clone()
+    }
+
+    /// Advance the simulation `steps` ticks of `dt` seconds each.
+    fn run(&mut self, steps: usize, dt: f32) {
+        for _ in 0..steps {
+            self.step(dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nannou::prelude::*;
+    use rand::Rng;
+
+    use super::*;
+    use crate::flock::Flock;
+    use crate::forces::Body;
+    use crate::life::Life;
+    use crate::n_body::NBody;
+    use crate::seed;
+    use crate::steering::{FlockWeights, Vehicle};
+
+    const STEPS: usize = 50;
+
+    fn life(seed: u64) -> Life {
+        let mut rng = seed::rng(seed);
+        let cells = (0..32)
+            .map(|_| (0..32).map(|_| rng.gen_range(0..2)).collect())
+            .collect();
+        Life::new(cells)
+    }
+
+    fn n_body(seed: u64) -> NBody {
+        let mut rng = seed::rng(seed);
+        let bodies = (0..40)
+            .map(|_| {
+                let position = vec2(rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
+                let velocity = vec2(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+                Body::new(position, velocity, vec2(0.0, 0.0), rng.gen_range(2.0..20.0))
+            })
+            .collect();
+        NBody::new(bodies, 1.0, 1000.0)
+    }
+
+    fn flock(seed: u64) -> Flock {
+        let mut rng = seed::rng(seed);
+        let vehicles = (0..50)
+            .map(|_| {
+                let position = vec2(rng.gen_range(-200.0..200.0), rng.gen_range(-200.0..200.0));
+                Vehicle::new(position, 1.0, 0.005)
+            })
+            .collect();
+        let weights = FlockWeights {
+            seek: 1.0,
+            separation: 1.0,
+            align: 0.5,
+            cohere: 0.5,
+        };
+        let mut flock = Flock::new(vehicles, weights);
+        flock.target = Some(pt2(50.0, -50.0));
+        flock
+    }
+
+    fn bodies(simulation: &NBody) -> Vec<(Vec2, Vec2, f32)> {
+        let bodies = simulation.state().iter();
+        bodies.map(|b| (b.position, b.velocity, b.mass)).collect()
+    }
+
+    // Vehicle ids differ between runs, so only their motion is compared
+    fn vehicles(simulation: &Flock) -> Vec<(Vec2, Vec2)> {
+        let vehicles = simulation.state().iter();
+        vehicles.map(|v| (v.position, v.velocity)).collect()
+    }
+
+    #[test]
+    fn life_is_deterministic() {
+        let (mut a, mut b) = (life(7), life(7));
+        a.run(STEPS, 1.0);
+        b.run(STEPS, 1.0);
+        assert!(a.state() == b.state());
+        assert!(life(7).state() != life(8).state());
+    }
+
+    #[test]
+    fn n_body_is_deterministic() {
+        let (mut a, mut b) = (n_body(7), n_body(7));
+        a.run(STEPS, 0.1);
+        b.run(STEPS, 0.1);
+        assert!(bodies(&a) == bodies(&b));
+        assert!(bodies(&n_body(7)) != bodies(&n_body(8)));
+    }
+
+    #[test]
+    fn flock_is_deterministic() {
+        let (mut a, mut b) = (flock(7), flock(7));
+        a.run(STEPS, 1.0);
+        b.run(STEPS, 1.0);
+        assert!(vehicles(&a) == vehicles(&b));
+        assert!(vehicles(&flock(7)) != vehicles(&flock(8)));
+    }
+}
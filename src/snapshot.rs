@@ -0,0 +1,141 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use nannou::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Command-line options for saving a running sketch's state and picking it
+/// up again later.
+#[derive(Args, Clone)]
+pub struct SnapshotArgs {
+    /// Start from a state saved earlier instead of a fresh one.
+    #[arg(long, value_name = "FILE")]
+    pub resume: Option<PathBuf>,
+
+    /// File `S` saves the state to, overwriting it each time. Given
+    /// explicitly, the state is also saved here when the run ends. `.ron`
+    /// files are written as text, anything else as compact binary. Defaults
+    /// to the `--resume` file.
+    #[arg(long, value_name = "FILE")]
+    pub snapshot: Option<PathBuf>,
+}
+
+impl SnapshotArgs {
+    /// The state named by `--resume`, if any. Exits with a message if the
+    /// file can't be read.
+    pub fn resume<T: DeserializeOwned>(&self) -> Option<T> {
+        self.resume.as_ref().map(|path| {
+            load(path).unwrap_or_else(|err| {
+                eprintln!("failed to resume from {}: {}", path.display(), err);
+                std::process::exit(1);
+            })
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Ron(ron::Error),
+    RonRead(ron::error::SpannedError),
+    Binary(bincode::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{}", err),
+            SnapshotError::Ron(err) => write!(f, "{}", err),
+            SnapshotError::RonRead(err) => write!(f, "{}", err),
+            SnapshotError::Binary(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+fn is_ron(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("ron")
+}
+
+/// Write `state` to `path`, as RON text for `.ron` files and bincode
+/// otherwise.
+pub fn save<T: Serialize>(path: &Path, state: &T) -> Result<(), SnapshotError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if is_ron(path) {
+        let text = ron::ser::to_string_pretty(state, ron::ser::PrettyConfig::default())
+            .map_err(SnapshotError::Ron)?;
+        fs::write(path, text)?;
+    } else {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, state).map_err(SnapshotError::Binary)?;
+    }
+    Ok(())
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, SnapshotError> {
+    if is_ron(path) {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(SnapshotError::RonRead)
+    } else {
+        let reader = BufReader::new(File::open(path)?);
+        bincode::deserialize_from(reader).map_err(SnapshotError::Binary)
+    }
+}
+
+/// Saves a sketch's state on request.
+///
+/// In a window, `S` writes a snapshot.
+pub struct Snapshots {
+    path: PathBuf,
+    requested: bool,
+}
+
+impl Snapshots {
+    /// Snapshots go to `--snapshot`, then `--resume`, then `default`.
+    pub fn new(args: &SnapshotArgs, default: &str) -> Self {
+        let path = args
+            .snapshot
+            .clone()
+            .or_else(|| args.resume.clone())
+            .unwrap_or_else(|| PathBuf::from(default));
+        Snapshots {
+            path,
+            requested: args.snapshot.is_some(),
+        }
+    }
+
+    pub fn save<T: Serialize>(&self, state: &T) {
+        match save(&self.path, state) {
+            Ok(()) => println!("saved snapshot to {}", self.path.display()),
+            Err(err) => eprintln!("failed to save {}: {}", self.path.display(), err),
+        }
+    }
+
+    /// Save the state returned by `state` when `S` is pressed.
+    pub fn key_pressed<T: Serialize, F: FnOnce() -> T>(&self, key: Key, state: F) {
+        if key == Key::S {
+            self.save(&state());
+        }
+    }
+
+    /// Save the state a run ends with, if `--snapshot` was given.
+    pub fn finish<T: Serialize, F: FnOnce() -> T>(&self, state: F) {
+        if self.requested {
+            self.save(&state());
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::render::{Renderer, Style};
+use crate::scene::{check_finite, SceneError, Validate};
+
+/// Relative strength of each flocking behaviour.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct FlockWeights {
+    pub seek: f32,
+    pub separation: f32,
+    pub align: f32,
+    pub cohere: f32,
+}
+
+impl Validate for FlockWeights {
+    fn validate(&self) -> Result<(), SceneError> {
+        let weights = [self.seek, self.separation, self.align, self.cohere];
+        check_finite("weights", &weights)
+    }
+}
+
+/// Neighbourhood radii used by the flocking behaviours.
+#[derive(Clone, Copy)]
+pub struct FlockRadii {
+    pub separation: f32,
+    pub neighbor: f32,
+}
+
+impl Default for FlockRadii {
+    fn default() -> Self {
+        FlockRadii {
+            separation: 25.0,
+            neighbor: 50.0,
+        }
+    }
+}
+
+/// Vehicle ids are handed out in creation order so that runs are
+/// reproducible.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A Reynolds-style autonomous agent.
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub struct Vehicle {
+    /// Only meaningful within a run, so restored vehicles get fresh ids.
+    #[serde(skip, default = "next_id")]
+    pub id: u64,
+    pub position: Point2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+    pub max_speed: f32,
+    pub max_acceleration: f32,
+}
+
+impl Vehicle {
+    pub fn new(position: Point2, max_speed: f32, max_acceleration: f32) -> Self {
+        Vehicle {
+            id: next_id(),
+            position,
+            velocity: vec2(0.0, 0.0),
+            acceleration: vec2(0.0, 0.0),
+            max_speed,
+            max_acceleration,
+        }
+    }
+
+    fn neighbors<'a>(
+        &'a self,
+        vehicles: &'a [Vehicle],
+        radius: f32,
+    ) -> impl Iterator<Item = &'a Vehicle> + 'a {
+        let radius_squared = radius * radius;
+        vehicles.iter().filter(move |vehicle| {
+            vehicle.id != self.id
+                && self.position.distance_squared(vehicle.position) < radius_squared
+        })
+    }
+
+    pub fn seek(&self, target: Point2) -> Vec2 {
+        (target - self.position).normalize_or_zero()
+    }
+
+    /// Desired velocity change towards `target`, slowing down inside
+    /// `slowing_radius`.
+    pub fn arrive(&self, target: Point2, slowing_radius: f32) -> Vec2 {
+        let displacement = target - self.position;
+        let d = displacement.length();
+        let speed = if d < slowing_radius {
+            map_range(d, 0.0, slowing_radius, 0.0, self.max_speed)
+        } else {
+            self.max_speed
+        };
+        let desired_velocity = displacement.normalize_or_zero() * speed;
+        (desired_velocity - self.velocity).clamp_length_max(self.max_acceleration)
+    }
+
+    pub fn separate(&self, vehicles: &[Vehicle], desired_separation: f32) -> Vec2 {
+        let mut steer = vec2(0.0, 0.0);
+        for vehicle in self.neighbors(vehicles, desired_separation) {
+            if vehicle.position != self.position {
+                steer += self.position - vehicle.position;
+            }
+        }
+        steer.normalize_or_zero()
+    }
+
+    pub fn align(&self, vehicles: &[Vehicle], neighbor_distance: f32) -> Vec2 {
+        let mut sum = vec2(0.0, 0.0);
+        for vehicle in self.neighbors(vehicles, neighbor_distance) {
+            sum += vehicle.velocity;
+        }
+        sum.normalize_or_zero()
+    }
+
+    pub fn cohere(&self, vehicles: &[Vehicle], neighbor_distance: f32) -> Vec2 {
+        let mut sum = vec2(0.0, 0.0);
+        for vehicle in self.neighbors(vehicles, neighbor_distance) {
+            sum += self.seek(vehicle.position);
+        }
+        sum.normalize_or_zero()
+    }
+
+    /// Weighted sum of the flocking behaviours, plus seeking `target` if any.
+    pub fn flock(
+        &self,
+        vehicles: &[Vehicle],
+        target: Option<Point2>,
+        weights: FlockWeights,
+        radii: FlockRadii,
+    ) -> Vec2 {
+        let seek_acceleration = target.map_or(vec2(0.0, 0.0), |t| self.seek(t) * weights.seek);
+        let separation_acceleration =
+            self.separate(vehicles, radii.separation) * weights.separation;
+        let align_acceleration = self.align(vehicles, radii.neighbor) * weights.align;
+        let cohere_acceleration = self.cohere(vehicles, radii.neighbor) * weights.cohere;
+        seek_acceleration + separation_acceleration + align_acceleration + cohere_acceleration
+    }
+
+    pub fn apply_force(&mut self, acceleration: Vec2) {
+        self.acceleration += acceleration;
+    }
+
+    pub fn update(&mut self) {
+        self.velocity += self.acceleration;
+        self.velocity = self.velocity.clamp_length_max(self.max_speed);
+        self.position += self.velocity;
+        self.acceleration = vec2(0.0, 0.0);
+    }
+
+    pub fn render<R, C>(&self, renderer: &mut R, color: C)
+    where
+        R: Renderer,
+        C: IntoLinSrgba<f32>,
+    {
+        let angle = self.velocity.angle();
+        let points = [
+            pt2(10.0, 0.0),
+            pt2(10.0, 0.0).rotate(2.5 * PI / 3.0),
+            pt2(10.0, 0.0).rotate(-2.5 * PI / 3.0),
+        ]
+        .map(|p| self.position + p.rotate(angle));
+        renderer.tri(points, Style::fill(color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vehicle_at(x: f32, y: f32) -> Vehicle {
+        Vehicle::new(pt2(x, y), 4.0, 0.1)
+    }
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.distance(expected) < 1e-5,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn separate_steers_away_from_close_neighbors() {
+        let vehicle = vehicle_at(0.0, 0.0);
+        let vehicles = [
+            vehicle.clone(),
+            vehicle_at(10.0, 0.0),
+            vehicle_at(100.0, 0.0),
+        ];
+        assert_close(vehicle.separate(&vehicles, 25.0), vec2(-1.0, 0.0));
+    }
+
+    #[test]
+    fn separate_ignores_itself_and_distant_vehicles() {
+        let vehicle = vehicle_at(0.0, 0.0);
+        let vehicles = [vehicle.clone(), vehicle_at(100.0, 0.0)];
+        assert_eq!(vehicle.separate(&vehicles, 25.0), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn align_follows_neighbors_heading() {
+        let vehicle = vehicle_at(0.0, 0.0);
+        let mut a = vehicle_at(10.0, 0.0);
+        a.velocity = vec2(0.0, 2.0);
+        let mut b = vehicle_at(-10.0, 0.0);
+        b.velocity = vec2(0.0, 3.0);
+        let mut far = vehicle_at(200.0, 0.0);
+        far.velocity = vec2(-5.0, 0.0);
+        let vehicles = [vehicle.clone(), a, b, far];
+        assert_close(vehicle.align(&vehicles, 50.0), vec2(0.0, 1.0));
+    }
+
+    #[test]
+    fn cohere_steers_towards_neighbors() {
+        let vehicle = vehicle_at(0.0, 0.0);
+        let vehicles = [
+            vehicle.clone(),
+            vehicle_at(10.0, 10.0),
+            vehicle_at(10.0, -10.0),
+        ];
+        assert_close(vehicle.cohere(&vehicles, 50.0), vec2(1.0, 0.0));
+    }
+
+    #[test]
+    fn seek_points_at_target() {
+        let vehicle = vehicle_at(1.0, 1.0);
+        assert_close(vehicle.seek(pt2(1.0, 5.0)), vec2(0.0, 1.0));
+        assert_eq!(vehicle.seek(pt2(1.0, 1.0)), vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn arrive_is_limited_to_max_acceleration() {
+        let vehicle = vehicle_at(0.0, 0.0);
+        let steer = vehicle.arrive(pt2(500.0, 0.0), 100.0);
+        assert_close(steer, vec2(vehicle.max_acceleration, 0.0));
+    }
+
+    #[test]
+    fn arrive_slows_down_inside_radius() {
+        let mut vehicle = vehicle_at(0.0, 0.0);
+        vehicle.max_acceleration = 100.0;
+        vehicle.velocity = vec2(4.0, 0.0);
+        // Halfway into the slowing radius the desired speed is half the
+        // maximum, so the vehicle brakes
+        let steer = vehicle.arrive(pt2(50.0, 0.0), 100.0);
+        assert_close(steer, vec2(-2.0, 0.0));
+        // At the target it wants to stop entirely
+        let steer = vehicle.arrive(pt2(0.0, 0.0), 100.0);
+        assert_close(steer, vec2(-4.0, 0.0));
+    }
+}